@@ -0,0 +1,170 @@
+//! This module contains the write layer for the tracker data files, which makes every write crash-safe.
+//!
+//! A file is never truncated in place. The new contents are written to a temporary file next to it, which is
+//! synced to disk and then renamed over the original, so a crash or power loss leaves either the old or the new
+//! file. Before the file is replaced, the previous version is kept in a rotating set of [`BACKUP_COUNT`] backups
+//! (`hours.txt.bak`, `hours.txt.bak.1`, ...), which the [`crate::recovery`] module restores from on startup.
+//!
+//! The session log only ever grows, so new sessions are appended to it by [`append_synced`] instead of rewriting
+//! it. A crash during an append can only cut off the last line, which is repaired by the [`crate::recovery`] module.
+use chrono::Local;
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+/// The number of previous versions kept for every file.
+pub const BACKUP_COUNT: usize = 3;
+
+/// Returns the path of the temporary file which is written before it replaces the file at `path`.
+pub fn temp_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".tmp")
+}
+
+/// Returns the path of backup `index` of the file at `path`, where `0` is the most recent backup.
+pub fn backup_path(path: &Path, index: usize) -> PathBuf {
+    match index {
+        0 => with_suffix(path, ".bak"),
+        _ => with_suffix(path, &format!(".bak.{index}")),
+    }
+}
+
+/// Returns the paths of the backups of the file at `path` which exist, most recent first.
+pub fn backups(path: &Path) -> Vec<PathBuf> {
+    (0..BACKUP_COUNT)
+        .map(|index| backup_path(path, index))
+        .filter(|backup| backup.is_file())
+        .collect()
+}
+
+/// This function atomically replaces the contents of the file at `path` with `contents`.
+///
+/// The contents are written to [`temp_path`] and synced, the current file is copied into the most recent backup,
+/// and the temporary file is then renamed over the original. The file is created if it does not exist.
+///
+/// # Errors
+/// Returns an [`io::Error`] if any file operations failed, in which case the original file is left untouched.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let tmp_path = temp_path(path);
+
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(contents.as_ref())?;
+    tmp_file.sync_all()?;
+    drop(tmp_file);
+
+    if path.is_file() {
+        rotate_backups(path)?;
+    }
+
+    fs::rename(&tmp_path, path)?;
+    sync_parent(path);
+
+    Ok(())
+}
+
+/// This function appends `contents` to the end of the file at `path` in a single write, and syncs it to disk. The
+/// rest of the file is never rewritten, so the time taken does not grow with the file. A newline is added before
+/// `contents` if the file does not end with one, so a line which was cut off by an earlier crash never merges with
+/// the new line. The file is created if it does not exist.
+///
+/// # Errors
+/// Returns an [`io::Error`] if the file could not be opened, read or written.
+pub fn append_synced(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let created = !path.exists();
+    let mut file = File::options()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+
+    let mut buffer = Vec::new();
+
+    if file.metadata()?.len() > 0 {
+        let mut last = [0; 1];
+
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+
+        if last[0] != b'\n' {
+            buffer.push(b'\n');
+        }
+    }
+
+    buffer.extend_from_slice(contents.as_ref());
+
+    file.write_all(&buffer)?;
+    file.sync_all()?;
+
+    if created {
+        sync_parent(path);
+    }
+
+    Ok(())
+}
+
+/// This function replaces the file at `path` with a copy of `backup`, without rotating the backups.
+///
+/// # Errors
+/// Returns an [`io::Error`] if the backup could not be copied.
+pub fn restore_backup(path: &Path, backup: &Path) -> io::Result<()> {
+    let tmp_path = temp_path(path);
+
+    fs::copy(backup, &tmp_path)?;
+    File::options().write(true).open(&tmp_path)?.sync_all()?;
+    fs::rename(&tmp_path, path)?;
+    sync_parent(path);
+
+    Ok(())
+}
+
+/// This function moves the file at `path` aside to `<file name>.corrupt-<timestamp>`, so it can be
+/// recreated without losing the original contents. The new path is returned.
+///
+/// # Errors
+/// Returns an [`io::Error`] if the file could not be renamed.
+pub fn quarantine(path: &Path) -> io::Result<PathBuf> {
+    let timestamp = Local::now().format("%Y-%m-%d_%H-%M-%S");
+    let corrupt_path = with_suffix(path, &format!(".corrupt-{timestamp}"));
+
+    fs::rename(path, &corrupt_path)?;
+
+    Ok(corrupt_path)
+}
+
+/// Shifts the backups of the file at `path` back by one, dropping the oldest, and copies the file into
+/// the most recent backup.
+fn rotate_backups(path: &Path) -> io::Result<()> {
+    for index in (1..BACKUP_COUNT).rev() {
+        let newer = backup_path(path, index - 1);
+
+        if newer.is_file() {
+            fs::rename(newer, backup_path(path, index))?;
+        }
+    }
+
+    fs::copy(path, backup_path(path, 0))?;
+
+    Ok(())
+}
+
+/// Syncs the folder which contains `path`, so the rename is persisted. This is only possible on Unix,
+/// and a failure is ignored because the file contents were already synced.
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        let _ = File::open(parent).and_then(|dir| dir.sync_all());
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+/// Appends `suffix` to the file name of `path`.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
@@ -1,42 +1,213 @@
 //! Module contains functions for caclulating the hours in the past two weeks.
-use std::{
-    error::Error,
-    fs::File,
-    io::{Read, Write},
+//!
+//! The period is a [`Window`], which defaults to the Steam-style past two weeks. The windows shown in `hours.txt` and
+//! the website are set by `windows` in `config.toml`, where each window is written as:
+//! - `7d` or `2w` for the days before today, and today, up to [`MAX_WINDOW_DAYS`].
+//! - `month` for the calendar month up to today.
+//! - `2024-09-01..2024-09-30` for a fixed range of dates.
+//!
+//! ```toml
+//! windows = ["14d", "7d", "month"]
+//! ```
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use chrono::{prelude::*, Days};
+use colour::{green_ln_bold, yellow_ln_bold};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    data_dir::DataDir,
+    error::{PastTwoError, TrackerResult},
+    hours::recalculate_game,
+    session::Session,
+    store::{group_totals_between, open_store, Grouping, SessionStore, Total},
+    timezone::{self, Timezone},
+    website_files,
 };
 
-use chrono::{prelude::*, Duration as CDuration};
-use colour::{dark_red_ln_bold, green_ln_bold, yellow_ln_bold};
-use log::{info, warn};
+/// Used for returning errors when a [`Window`] could not be parsed
+#[derive(Debug)]
+pub struct WindowError {
+    message: String,
+}
+
+impl Display for WindowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Window Error: {}", self.message)
+    }
+}
+
+impl Error for WindowError {}
+
+/// A period of days which the time played is added up over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Window {
+    /// The given number of days before today, and today.
+    Days(u32),
+    /// The calendar month up to today.
+    CalendarMonth,
+    /// The dates between the first and the second date inclusive.
+    Range(NaiveDate, NaiveDate),
+}
+
+/// The longest [`Window::Days`] which is parsed, about a hundred years.
+pub const MAX_WINDOW_DAYS: u32 = 36_525;
+
+/// The Steam-style past two weeks.
+impl Default for Window {
+    fn default() -> Self {
+        Window::Days(14)
+    }
+}
 
-use crate::{retrieve_time, website_files, PastTwoError};
+impl Window {
+    /// Returns the first and last date of the window, when the current date is `today`. A window which reaches back
+    /// further than the earliest date starts at [`NaiveDate::MIN`].
+    pub fn bounds(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match *self {
+            Window::Days(days) => (
+                today
+                    .checked_sub_days(Days::new(days.into()))
+                    .unwrap_or(NaiveDate::MIN),
+                today,
+            ),
+            Window::CalendarMonth => (today.with_day(1).unwrap_or(today), today),
+            Window::Range(since, until) => (since, until),
+        }
+    }
+
+    /// Returns the name of the window which is shown in `hours.txt`, such as "Past Two Weeks".
+    pub fn label(&self) -> String {
+        match *self {
+            Window::Days(14) => String::from("Past Two Weeks"),
+            Window::Days(1) => String::from("Past Day"),
+            Window::Days(days) => format!("Past {days} Days"),
+            Window::CalendarMonth => String::from("This Month"),
+            Window::Range(since, until) => format!("{since} To {until}"),
+        }
+    }
+}
 
-/// This function takes a reference of a [`Vec<&str>`] Vector and returns a [`Some`] with the index of the closest
-/// after the date two weeks ago.
+impl FromStr for Window {
+    type Err = WindowError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let error = || WindowError {
+            message: format!(
+                "'{s}' is not a window, such as '14d', '2w', 'month' or '2024-09-01..2024-09-30'"
+            ),
+        };
+
+        if s == "month" {
+            return Ok(Window::CalendarMonth);
+        }
+
+        if let Some((since, until)) = s.split_once("..") {
+            let since = since.parse().map_err(|_| error())?;
+            let until = until.parse().map_err(|_| error())?;
+
+            return if since <= until {
+                Ok(Window::Range(since, until))
+            } else {
+                Err(error())
+            };
+        }
+
+        let days = if let Some(days) = s.strip_suffix('d') {
+            days.parse().map_err(|_| error())?
+        } else if let Some(weeks) = s.strip_suffix('w') {
+            let weeks: u32 = weeks.parse().map_err(|_| error())?;
+
+            weeks.checked_mul(7).ok_or_else(error)?
+        } else {
+            return Err(error());
+        };
+
+        if days > MAX_WINDOW_DAYS {
+            return Err(WindowError {
+                message: format!("'{s}' is longer than {MAX_WINDOW_DAYS} days"),
+            });
+        }
+
+        Ok(Window::Days(days))
+    }
+}
+
+impl TryFrom<String> for Window {
+    type Error = WindowError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Window> for String {
+    fn from(window: Window) -> Self {
+        window.to_string()
+    }
+}
+
+/// Formats the window in the same way as it is parsed, such as `14d`.
+impl Display for Window {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Window::Days(days) => write!(f, "{days}d"),
+            Window::CalendarMonth => write!(f, "month"),
+            Window::Range(since, until) => write!(f, "{since}..{until}"),
+        }
+    }
+}
+
+/// This function takes a reference of a [`Vec<&str>`] Vector of sorted `date.txt` lines and returns a [`Some`] with the index
+/// of the closest after the date two weeks ago.
+#[deprecated(
+    note = "date.txt was replaced by the session history, use `window_seconds` with a `SessionStore` instead"
+)]
+#[allow(deprecated)]
 pub fn closest_date(split_newline: &[&str]) -> Option<usize> {
-    let today = Local::now().date_naive();
-    let mut current_date = today - CDuration::days(14);
+    closest_date_in(
+        split_newline,
+        Window::default(),
+        timezone::current().today(),
+    )
+}
 
-    // Find the closest date to the date two weeks ago
-    while current_date <= today {
+/// This function takes a reference of a [`Vec<&str>`] Vector of sorted `date.txt` lines and returns a [`Some`] with the index
+/// of the closest date after the start of the `window`, when the current date is `today`.
+#[deprecated(
+    note = "date.txt was replaced by the session history, use `window_seconds_in` with a `SessionStore` instead"
+)]
+#[allow(deprecated)]
+pub fn closest_date_in(split_newline: &[&str], window: Window, today: NaiveDate) -> Option<usize> {
+    let (mut current_date, last_date) = window.bounds(today);
+
+    // Find the closest date to the start of the window
+    while current_date <= last_date {
         let idx = date_binary_search(split_newline, &current_date.to_string());
 
         if let Some(index) = idx {
             return Some(index);
         }
 
-        current_date += CDuration::days(1);
+        current_date = current_date.succ_opt()?;
     }
 
     // Return None if the date is not found
     None
 }
 
-/// This function is used to perform a binary search on a [`Vec<&str>`] Vector and compares the dates in the Vector with
+/// This function is used to perform a binary search on a [`Vec<&str>`] Vector of sorted `date.txt` lines and compares the dates in the Vector with
 /// the `c_date` [`String`]. The function then returns a [`Some`] with the index of the date, or a [`None`] if the
 /// date is not present. The worst case for this search is O(L * log n).
+#[deprecated(
+    note = "date.txt was replaced by the session history, use `SessionStore::sessions_between` instead"
+)]
 pub fn date_binary_search(split_newline: &[&str], c_date: &String) -> Option<usize> {
-    let mut high = split_newline.len() - 1;
+    let mut high = split_newline.len().checked_sub(1)?;
     let mut date_found = false;
     let mut low = 0;
     let mut result = 0;
@@ -44,16 +215,20 @@ pub fn date_binary_search(split_newline: &[&str], c_date: &String) -> Option<usi
     while low <= high {
         let mid = low + (high - low) / 2;
 
-        let s_mid: Vec<&str> = split_newline[mid].split_whitespace().collect();
+        // A blank line sorts before every date
+        let s_mid = split_newline[mid]
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
 
-        if s_mid[0] == c_date {
+        if s_mid == c_date {
             if mid == 0 {
                 break;
             }
             date_found = true;
             result = mid;
             high = mid - 1;
-        } else if *s_mid[0] < **c_date {
+        } else if s_mid < c_date.as_str() {
             if date_found {
                 break;
             }
@@ -74,156 +249,211 @@ pub fn date_binary_search(split_newline: &[&str], c_date: &String) -> Option<usi
 }
 
 /// This function calculates the hours recorded in the past two weeks and returns the total number of seconds as [`prim@u64`]
-/// The contents from `date.txt` are read and split by `\n` character and stored in a [`Vec<&str>`] Vector.
-/// It is then ordered and looped through in order to compare the date to the current iteration of the date two weeks ago.
-/// The seconds are retrieved from the dates that match the current date in the iteration of the while loop and the seconds
-/// are added to `seconds_past_two` which is returned as an [`Result<u64>`] at the end of the function.
+/// The sessions are queried from the [`SessionStore`] returned by [`open_store`], and the durations of the sessions which ended
+/// between the date two weeks ago and today are added together by [`past_two_seconds`].
 ///
 /// # Errors
-/// This function returns [`Box<dyn Error>`] which could potentially be two types of errors:
-/// - A [`PastTwoError`], which is a custom error which occurs when [`closest_date`] fails.
-/// - An [`io::Error`], which occurs when the `date.txt` file could not be opened, or read.
-pub fn calculate_past_two() -> Result<u64, Box<dyn Error>> {
-    // Check if the date.txt file exists
-    let mut date_file_result = File::open("C:\\RLHoursFolder\\date.txt")?;
-    let mut seconds_past_two: u64 = 0;
-
-    info!("Calculate Past Two: START\n");
-    let mut date_file_str = String::new();
-
-    // Checks if the file was read successfully
-    date_file_result.read_to_string(&mut date_file_str)?;
-
-    yellow_ln_bold!("Dates retrieved...");
-    let mut split_newline: Vec<&str> = date_file_str.split("\n").collect();
-    split_newline.pop();
-    split_newline.sort();
-
-    let today = Local::now().date_naive();
-    let two_weeks_ago = today - CDuration::days(14);
-    let cur_date: NaiveDate = two_weeks_ago;
-
-    // Declare variable for string reference slice
-    let split_line_copy: &[&str];
-
-    yellow_ln_bold!("Finding date two weeks ago...");
-    let date_idx = date_binary_search(&split_newline, &cur_date.to_string());
-
-    // Checks the value returned from date_binary_search
-    match date_idx {
-        Some(index) => {
-            yellow_ln_bold!("Date found...");
-            split_line_copy = &split_newline[index..];
-        }
-        // Find closest date if the target date was not found
-        None => {
-            dark_red_ln_bold!("\nDate not found. Searching for closest date...\n");
-            let closest = closest_date(&split_newline);
-
-            match closest {
-                Some(index) => {
-                    yellow_ln_bold!("Date found...");
-                    split_line_copy = &split_newline[index..];
-                }
-                None => return Err(PastTwoError.into()),
-            }
-        }
-    }
-
-    yellow_ln_bold!("Calculating past two...");
-    sum_total_seconds(split_line_copy, cur_date, today, &mut seconds_past_two)?;
-
-    green_ln_bold!("Past two calculated\n");
-    info!("Calculate Past Two: FINISHED\n");
-    Ok(seconds_past_two)
+/// This function returns a [`TrackerError`](crate::error::TrackerError) which could potentially be two types of errors:
+/// - A [`PastTwoError`], which is a custom error which occurs when no sessions were recorded in the past two weeks.
+/// - An [`io::Error`](std::io::Error), which occurs when the session store could not be read.
+pub fn calculate_past_two() -> TrackerResult<u64> {
+    calculate_window(Window::default())
 }
 
-fn sum_total_seconds(
-    split_line_copy: &[&str],
-    mut cur_date: NaiveDate,
-    today: NaiveDate,
-    seconds_past_two: &mut u64,
-) -> Result<(), Box<dyn Error>> {
-    let mut is_after_today = false;
-    while !is_after_today {
-        // Checks if the current iteration of the date two weeks ago is greater than today
-        if cur_date > today {
-            is_after_today = true;
-            continue;
-        }
-
-        for date in split_line_copy {
-            let split_whitespace: Vec<&str> = date.split_whitespace().collect();
+/// This function calculates the hours recorded in the `window` in the same way as [`calculate_past_two`], and returns
+/// the total number of seconds.
+///
+/// # Errors
+/// Returns a [`PastTwoError`] if no sessions were recorded in the `window`, or an error if the session store could
+/// not be read.
+pub fn calculate_window(window: Window) -> TrackerResult<u64> {
+    info!("Calculate Past Two: START ({window})\n");
 
-            // Check if cur_date is equivalent to the date from split_whitespace Vector
-            if cur_date.to_string() == split_whitespace[0] {
-                let split_chars = split_whitespace[1].chars();
+    let store = open_store(DataDir::current())?;
+    let today = timezone::current().today();
 
-                let mut sec_vec: Vec<char> = vec![];
+    yellow_ln_bold!("Calculating {}...", window.label().to_lowercase());
+    let seconds = window_seconds(store.as_ref(), window, today)?;
 
-                for num in split_chars {
-                    if num.is_numeric() {
-                        sec_vec.push(num);
-                    }
-                }
+    green_ln_bold!("{} calculated\n", window.label());
+    info!("Calculate Past Two: FINISHED\n");
+    Ok(seconds)
+}
 
-                let seconds_str: String = sec_vec.iter().collect();
-                let total_seconds: u64 = seconds_str.parse()?;
+/// This function adds together the time played by the sessions in the `store` between the date two weeks before `today`
+/// and `today`, through [`window_seconds`]. The total is returned in seconds.
+///
+/// # Errors
+/// Returns a [`PastTwoError`] if no sessions were played in that period, or an error if the store could not be read.
+pub fn past_two_seconds(store: &dyn SessionStore, today: NaiveDate) -> TrackerResult<u64> {
+    window_seconds(store, Window::default(), today)
+}
 
-                // Add the total seconds to the seconds_past_two variable
-                *seconds_past_two += total_seconds;
-            }
-        }
+/// This function adds together the time played in the `window` by the sessions in the `store`, when the current date is
+/// `today`. A session which spans the start or the end of the window only counts the time played on the days inside
+/// it. The total is returned in seconds, where the sessions are split into days in the reporting timezone.
+///
+/// # Errors
+/// Returns a [`PastTwoError`] if no sessions were played in the `window`, or an error if the store could not be read.
+pub fn window_seconds(
+    store: &dyn SessionStore,
+    window: Window,
+    today: NaiveDate,
+) -> TrackerResult<u64> {
+    window_seconds_in(store, window, today, &timezone::current())
+}
 
-        // Increase the current date to the next day
-        cur_date += CDuration::days(1);
+/// This function adds together the time played in the `window` in the same way as [`window_seconds`], where the
+/// sessions are split into days in the `timezone`.
+///
+/// # Errors
+/// Returns a [`PastTwoError`] if no sessions were played in the `window`, or an error if the store could not be read.
+pub fn window_seconds_in(
+    store: &dyn SessionStore,
+    window: Window,
+    today: NaiveDate,
+    timezone: &Timezone,
+) -> TrackerResult<u64> {
+    let (since, until) = window.bounds(today);
+    let (from, to) = widen(since, until);
+
+    let sessions: Vec<Session> = store
+        .sessions_between(from, to)?
+        .into_iter()
+        .filter(|session| {
+            session
+                .days_in(timezone)
+                .iter()
+                .any(|(date, _)| (since..=until).contains(date))
+        })
+        .collect();
+
+    if sessions.is_empty() {
+        return Err(PastTwoError.into());
     }
 
-    Ok(())
+    Ok(sessions
+        .iter()
+        .map(|session| session.seconds_between_in(since, until, timezone))
+        .sum())
 }
 
-/// This function updates the hours in the past two weeks in the `hours.txt` file.
-/// The hours past two is calculated through the [`calculate_past_two`] function
-/// The function returns a [`Result<bool>`] if the function was able to successfully
-/// update the hours past two, and write it to the `hours.txt` file.
+/// This function updates the hours in the past two weeks in the `hours.txt` file of every game.
+/// Each file is rebuilt from the session history through the [`recalculate_game`] function, and any
+/// discrepancy which was corrected is reported. The function returns a [`Result<bool>`] which is `true`
+/// if any game had sessions in the past two weeks, in which case the website files are also updated.
 ///
 /// # Errors
-/// Returns an [`io::Error`] if there were any issues with file operations.
-pub fn update_past_two() -> Result<bool, Box<dyn Error>> {
-    // Checks if the 'hours.txt' file exists
-    let mut hours_file_result = File::open("C:\\RLHoursFolder\\hours.txt")?;
-
-    // Buffer which stores the hours in the past two weeks
-    let hours_buffer = calculate_past_two().unwrap_or_else(|e| {
-        warn!("failed to calculate past two: {e}\n");
-        0
-    });
-
-    // Check the value of the buffer
-    let hours_past_two: f32 = if hours_buffer != 0 {
-        hours_buffer as f32 / 3600_f32
-    } else {
-        warn!("past two returned zero seconds");
-        return Ok(false);
-    };
-
-    let mut hours_file_str = String::new();
-
-    // Attempt to read from old hours.txt file
-    hours_file_result.read_to_string(&mut hours_file_str)?;
-
-    let (seconds, hours) = retrieve_time(&hours_file_str)?;
-
-    // Attempt to write to the file
-    let mut write_hours_result = File::create("C:\\RLHoursFolder\\hours.txt")?;
+/// Returns an error if the session history could not be read or `hours.txt` could not be written.
+pub fn update_past_two() -> TrackerResult<bool> {
+    let mut past_two_seconds = 0;
+
+    for game in Config::load().unwrap_or_default().games() {
+        let report = recalculate_game(DataDir::current(), &game)?;
+
+        if report.has_discrepancy() {
+            yellow_ln_bold!(
+                "hours.txt of {} did not match the session history and was corrected by {}s\n",
+                game.name,
+                report.seconds_corrected()
+            );
+        }
 
-    let rl_hours_str = format!("Rocket League Hours\nTotal Seconds: {}s\nTotal Hours: {:.1}hrs\nHours Past Two Weeks: {:.1}hrs\n", seconds, hours, hours_past_two);
+        past_two_seconds += report.summary.past_two_seconds;
+    }
 
-    // Check if the write succeeds
-    write_hours_result.write_all(rl_hours_str.as_bytes())?;
+    // Check the hours in the past two weeks
+    if past_two_seconds == 0 {
+        warn!("past two returned zero seconds");
+        return Ok(false);
+    }
 
     // Update website files
     website_files::generate_website_files(false)
         .unwrap_or_else(|e| warn!("failed to generate website files: {e}"));
     Ok(true)
 }
+
+/// The time played between two dates, returned by [`report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The first day of the report.
+    pub since: NaiveDate,
+    /// The last day of the report.
+    pub until: NaiveDate,
+    /// The time played in seconds.
+    pub seconds: u64,
+    /// The time the player was idle in the sessions of the report in seconds, which is not counted in `seconds`.
+    pub idle_seconds: u64,
+    /// The number of sessions which were played in the report.
+    pub sessions: usize,
+    /// The time played on each day which has sessions.
+    pub days: Vec<Total>,
+}
+
+/// This function reports the time played in the sessions of the `store` between `since` and `until` inclusive, where
+/// the time of a session which spans midnight is split between the days it covered. Only the idle time between the
+/// start of `since` and the end of `until` is counted. Unlike [`past_two_seconds`], an empty period is not an error and reports zero seconds.
+///
+/// # Errors
+/// Returns an error if the store could not be read.
+pub fn report(
+    store: &dyn SessionStore,
+    since: NaiveDate,
+    until: NaiveDate,
+) -> TrackerResult<Report> {
+    let timezone = timezone::current();
+    let (from, to) = widen(since, until);
+
+    let sessions: Vec<Session> = store
+        .sessions_between(from, to)?
+        .into_iter()
+        .filter(|session| session.played_between(since, until))
+        .collect();
+
+    Ok(Report {
+        since,
+        until,
+        seconds: sessions
+            .iter()
+            .map(|session| session.seconds_between(since, until))
+            .sum(),
+        idle_seconds: sessions
+            .iter()
+            .map(|session| idle_seconds_between(session, since, until, &timezone))
+            .sum(),
+        sessions: sessions.len(),
+        days: group_totals_between(&sessions, Grouping::Day, since, until),
+    })
+}
+
+/// Returns the dates a day either side of `since` and `until`, or the dates themselves at the ends of the calendar.
+/// The dates of a session can move by a day in another timezone, so the sessions either side are checked too.
+fn widen(since: NaiveDate, until: NaiveDate) -> (NaiveDate, NaiveDate) {
+    (
+        since.checked_sub_days(Days::new(1)).unwrap_or(since),
+        until.checked_add_days(Days::new(1)).unwrap_or(until),
+    )
+}
+
+/// Returns the time the player was idle in the `session` between the start of `since` and the end of `until` in the
+/// `timezone`, in seconds.
+fn idle_seconds_between(
+    session: &Session,
+    since: NaiveDate,
+    until: NaiveDate,
+    timezone: &Timezone,
+) -> u64 {
+    let start = timezone.start_of_day(since, *session.start.offset());
+    let end = timezone.next_midnight(timezone.start_of_day(until, *session.end.offset()));
+
+    session
+        .idle
+        .iter()
+        .map(|span| {
+            (span.end.min(end) - span.start.max(start))
+                .num_seconds()
+                .max(0) as u64
+        })
+        .sum()
+}
@@ -0,0 +1,318 @@
+//! This module contains the charts of the website, which are rendered as inline SVG, so they are shown without any
+//! JavaScript library or network access.
+//!
+//! The [`Charts`] of the website are:
+//! - A bar chart of the time played on each day of the first window in `config.toml`, the past two weeks by default.
+//! - Trend lines of the time played in each of the past 12 weeks and the past 12 months.
+//! - A calendar heatmap of the time played on each day of the past year.
+//!
+//! Hovering over a bar, point or day shows its date and hours. The colours are set by the `chart-*` and `heat-*`
+//! classes in `charts.css`.
+use crate::{
+    calculate_past_two::Window,
+    session::Session,
+    store::{group_totals_between, Grouping},
+};
+use chrono::{Datelike, Duration as CDuration, Months, NaiveDate};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// The width of the bar chart and trend lines, in SVG units.
+const WIDTH: f64 = 640.0;
+/// The height of the bar chart and trend lines, in SVG units.
+const HEIGHT: f64 = 200.0;
+/// The space left of the plot for the hours axis.
+const LEFT: f64 = 44.0;
+/// The space above the plot.
+const TOP: f64 = 12.0;
+/// The space below the plot for the date labels.
+const BOTTOM: f64 = 24.0;
+/// The size of a day in the calendar heatmap, including the gap to the next day.
+const CELL: f64 = 13.0;
+/// The number of weeks in the calendar heatmap.
+const CALENDAR_WEEKS: i64 = 53;
+/// The number of weeks and months in the trend lines.
+const TREND_PERIODS: u32 = 12;
+
+/// The SVG charts which are shown in the website.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Charts {
+    /// The bar chart of the time played on each day of the window.
+    pub daily: String,
+    /// The trend line of the time played in each of the past 12 weeks.
+    pub weekly: String,
+    /// The trend line of the time played in each of the past 12 months.
+    pub monthly: String,
+    /// The calendar heatmap of the time played on each day of the past year.
+    pub calendar: String,
+}
+
+impl Charts {
+    /// Renders the charts of the `sessions`, where the bar chart shows the days of the `window`, when the current
+    /// date is `today`.
+    pub fn new(sessions: &[Session], window: Window, today: NaiveDate) -> Charts {
+        let (from, to) = window.bounds(today);
+
+        let weeks_from =
+            Grouping::Week.period_start(today) - CDuration::weeks(TREND_PERIODS as i64 - 1);
+        let months_from = Grouping::Month
+            .period_start(today)
+            .checked_sub_months(Months::new(TREND_PERIODS - 1))
+            .unwrap_or(today);
+        let calendar_from =
+            Grouping::Week.period_start(today) - CDuration::weeks(CALENDAR_WEEKS - 1);
+
+        Charts {
+            daily: bar_chart(&filled_totals(sessions, Grouping::Day, from, to)),
+            weekly: trend_line(
+                &filled_totals(sessions, Grouping::Week, weeks_from, today),
+                "%d %b",
+                "Time played per week",
+            ),
+            monthly: trend_line(
+                &filled_totals(sessions, Grouping::Month, months_from, today),
+                "%b %Y",
+                "Time played per month",
+            ),
+            calendar: calendar_heatmap(&filled_totals(
+                sessions,
+                Grouping::Day,
+                calendar_from,
+                today,
+            )),
+        }
+    }
+}
+
+/// This function returns the time played in seconds in every period of the `grouping` between `from` and `to`
+/// inclusive, ordered by period. Unlike [`group_totals_between`], the periods without any sessions are included
+/// with `0` seconds.
+pub fn filled_totals(
+    sessions: &[Session],
+    grouping: Grouping,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<(NaiveDate, u64)> {
+    let totals = group_totals_between(sessions, grouping, from, to);
+    let mut totals = totals.iter().peekable();
+    let mut filled = Vec::new();
+    let mut period = grouping.period_start(from);
+
+    while period <= to {
+        let seconds = match totals.next_if(|total| total.period_start == period) {
+            Some(total) => total.seconds,
+            None => 0,
+        };
+
+        filled.push((period, seconds));
+
+        period = match grouping {
+            Grouping::Day => period + CDuration::days(1),
+            Grouping::Week => period + CDuration::weeks(1),
+            Grouping::Month => match period.checked_add_months(Months::new(1)) {
+                Some(next) => next,
+                None => break,
+            },
+        };
+    }
+
+    filled
+}
+
+/// This function renders a bar chart of the seconds played on each of the `days`.
+pub fn bar_chart(days: &[(NaiveDate, u64)]) -> String {
+    let max = max_seconds(days);
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let slot = (WIDTH - LEFT) / days.len().max(1) as f64;
+    let label_step = days.len().div_ceil(10).max(1);
+
+    let mut svg = open_svg("bar-chart", WIDTH, HEIGHT, "Time played per day");
+    svg.push_str(&hours_axis(max));
+
+    for (idx, (date, seconds)) in days.iter().enumerate() {
+        let height = *seconds as f64 / max as f64 * plot_height;
+        let x = LEFT + idx as f64 * slot;
+
+        let _ = write!(
+            svg,
+            r#"<rect class="chart-bar" x="{:.1}" y="{:.1}" width="{:.1}" height="{height:.1}"><title>{date}: {}</title></rect>"#,
+            x + slot * 0.1,
+            TOP + plot_height - height,
+            slot * 0.8,
+            hours_label(*seconds)
+        );
+
+        if idx % label_step == 0 {
+            let _ = write!(
+                svg,
+                r#"<text class="chart-label" x="{:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                x + slot / 2.0,
+                HEIGHT - 6.0,
+                date.format("%d %b")
+            );
+        }
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+/// This function renders a trend line of the seconds played in each of the `periods`, which are labelled with the
+/// `label_format` of their first day. The `title` describes the chart to screen readers.
+pub fn trend_line(periods: &[(NaiveDate, u64)], label_format: &str, title: &str) -> String {
+    let max = max_seconds(periods);
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let step = (WIDTH - LEFT - 16.0) / periods.len().saturating_sub(1).max(1) as f64;
+    let label_step = periods.len().div_ceil(6).max(1);
+
+    let points: Vec<(f64, f64)> = periods
+        .iter()
+        .enumerate()
+        .map(|(idx, (_, seconds))| {
+            (
+                LEFT + 8.0 + idx as f64 * step,
+                TOP + plot_height - *seconds as f64 / max as f64 * plot_height,
+            )
+        })
+        .collect();
+
+    let mut svg = open_svg("trend-line", WIDTH, HEIGHT, title);
+    svg.push_str(&hours_axis(max));
+
+    let line: Vec<String> = points
+        .iter()
+        .map(|(x, y)| format!("{x:.1},{y:.1}"))
+        .collect();
+    let _ = write!(
+        svg,
+        r#"<polyline class="chart-line" fill="none" points="{}"/>"#,
+        line.join(" ")
+    );
+
+    for (idx, ((date, seconds), (x, y))) in periods.iter().zip(&points).enumerate() {
+        let _ = write!(
+            svg,
+            r#"<circle class="chart-point" cx="{x:.1}" cy="{y:.1}" r="4"><title>{}: {}</title></circle>"#,
+            date.format(label_format),
+            hours_label(*seconds)
+        );
+
+        if idx % label_step == 0 {
+            let _ = write!(
+                svg,
+                r#"<text class="chart-label" x="{x:.1}" y="{:.1}" text-anchor="middle">{}</text>"#,
+                HEIGHT - 6.0,
+                date.format(label_format)
+            );
+        }
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+/// This function renders a calendar heatmap of the seconds played on each of the `days`, with a column for every
+/// week starting on Monday. Each day is given a `heat-0` to `heat-4` class, where `heat-0` is a day without any
+/// sessions and `heat-4` is close to the day with the most time played.
+pub fn calendar_heatmap(days: &[(NaiveDate, u64)]) -> String {
+    let Some((first, _)) = days.first() else {
+        return open_svg("calendar-heatmap", 0.0, 0.0, "Time played per day") + "</svg>";
+    };
+
+    let start = Grouping::Week.period_start(*first);
+    let weeks = days
+        .last()
+        .map(|(last, _)| (*last - start).num_days() / 7 + 1)
+        .unwrap_or(1);
+    let max = max_seconds(days);
+    let left = 2.0 * CELL;
+    let top = 1.5 * CELL;
+
+    let mut svg = open_svg(
+        "calendar-heatmap",
+        left + weeks as f64 * CELL,
+        top + 7.0 * CELL,
+        "Time played per day",
+    );
+
+    for (row, weekday) in [(1, "Tue"), (3, "Thu"), (5, "Sat")] {
+        let _ = write!(
+            svg,
+            r#"<text class="chart-label" x="0" y="{:.1}">{weekday}</text>"#,
+            top + row as f64 * CELL + CELL * 0.75
+        );
+    }
+
+    for (date, seconds) in days {
+        let column = (*date - start).num_days() / 7;
+        let row = date.weekday().num_days_from_monday();
+        let x = left + column as f64 * CELL;
+
+        // Label the first week of every month
+        if date.day() <= 7 && row == 0 {
+            let _ = write!(
+                svg,
+                r#"<text class="chart-label" x="{x:.1}" y="{:.1}">{}</text>"#,
+                CELL,
+                date.format("%b")
+            );
+        }
+
+        let _ = write!(
+            svg,
+            r#"<rect class="heat-{}" x="{x:.1}" y="{:.1}" width="{:.1}" height="{:.1}" rx="2"><title>{date}: {}</title></rect>"#,
+            heat_level(*seconds, max),
+            top + row as f64 * CELL,
+            CELL - 2.0,
+            CELL - 2.0,
+            hours_label(*seconds)
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Returns the heat level of the `seconds` from `0` to `4`, relative to the `max` seconds.
+pub fn heat_level(seconds: u64, max: u64) -> u64 {
+    if seconds == 0 {
+        return 0;
+    }
+
+    (seconds * 4).div_ceil(max.max(1)).clamp(1, 4)
+}
+
+/// Returns the most seconds of the `periods`, which is at least `1` so it can be divided by.
+fn max_seconds(periods: &[(NaiveDate, u64)]) -> u64 {
+    periods
+        .iter()
+        .map(|(_, seconds)| *seconds)
+        .max()
+        .unwrap_or(0)
+        .max(1)
+}
+
+/// Opens an SVG element with the `class`, which scales to the width of the page.
+fn open_svg(class: &str, width: f64, height: f64, title: &str) -> String {
+    format!(
+        r#"<svg class="chart {class}" viewBox="0 0 {width:.0} {height:.0}" role="img" aria-label="{title}"><title>{title}</title>"#
+    )
+}
+
+/// Renders the hours axis of a chart whose highest value is `max` seconds.
+fn hours_axis(max: u64) -> String {
+    let bottom = HEIGHT - BOTTOM;
+
+    format!(
+        r#"<line class="chart-axis" x1="{LEFT}" y1="{TOP}" x2="{LEFT}" y2="{bottom}"/><line class="chart-axis" x1="{LEFT}" y1="{bottom}" x2="{WIDTH}" y2="{bottom}"/><text class="chart-label" x="{:.1}" y="{:.1}" text-anchor="end">{}</text><text class="chart-label" x="{:.1}" y="{bottom:.1}" text-anchor="end">0</text>"#,
+        LEFT - 4.0,
+        TOP + 8.0,
+        hours_label(max),
+        LEFT - 4.0,
+    )
+}
+
+/// Formats the `seconds` as hours, such as `1.5hrs`.
+fn hours_label(seconds: u64) -> String {
+    format!("{:.1}hrs", seconds as f64 / 3600_f64)
+}
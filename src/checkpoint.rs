@@ -0,0 +1,190 @@
+//! This module checkpoints the session in progress, so it is not lost if the tracker is closed before the session ends.
+//!
+//! While Rocket League is running, the elapsed time is written to `checkpoint.json` every
+//! [`Config::checkpoint_interval`](crate::config::Config) seconds. The checkpoint is removed once the session has been
+//! recorded, so a checkpoint which is found on startup belongs to an unfinished session. Depending on the
+//! [`UnfinishedSession`] setting, that session is recorded up to its last checkpoint with the
+//! [`EndReason::Recovered`] end reason, discarded, or the user is asked which to do.
+//!
+//! ```json
+//! {"version":1,"start":"2024-09-15T18:02:11+01:00","checkpointed_at":"2024-09-15T18:32:11+01:00","duration":1800,"app_version":"0.4.4"}
+//! ```
+use crate::{
+    atomic_file::{backups, write_atomic},
+    config::UnfinishedSession,
+    daemon::is_daemon,
+    data_dir::DataDir,
+    error::TrackerResult,
+    session::{EndReason, IdleSpan, Session, SESSION_VERSION},
+    store::open_store,
+};
+use chrono::{DateTime, FixedOffset, Local};
+use colour::{green_ln_bold, white, yellow_ln_bold};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, ErrorKind, Write},
+    path::Path,
+};
+
+/// The default number of seconds between checkpoints.
+pub const DEFAULT_CHECKPOINT_INTERVAL: u64 = 60;
+
+/// The state of the session in progress at its last checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The version of the record format, which matches the [`Session`] version.
+    pub version: u32,
+    /// When the session started.
+    pub start: DateTime<FixedOffset>,
+    /// When the checkpoint was written.
+    pub checkpointed_at: DateTime<FixedOffset>,
+    /// The time played in seconds up to the checkpoint, without the time the player was idle.
+    pub duration: u64,
+    /// The version of the program which recorded the session.
+    pub app_version: String,
+    /// The spans in which the player was idle up to the checkpoint.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub idle: Vec<IdleSpan>,
+}
+
+impl Checkpoint {
+    /// Creates the checkpoint for a session which started at `start`.
+    pub fn new(start: DateTime<Local>) -> Checkpoint {
+        Checkpoint {
+            version: SESSION_VERSION,
+            start: start.fixed_offset(),
+            checkpointed_at: start.fixed_offset(),
+            duration: 0,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            idle: Vec::new(),
+        }
+    }
+
+    /// Updates the checkpoint with the `duration` played so far.
+    pub fn update(&mut self, duration: u64) {
+        self.checkpointed_at = Local::now().fixed_offset();
+        self.duration = duration;
+    }
+
+    /// Loads the checkpoint from the file at `path`, or returns [`None`] if there is no unfinished session.
+    ///
+    /// # Errors
+    /// Returns an error if the file could not be read or is not a valid checkpoint.
+    pub fn load(path: &Path) -> TrackerResult<Option<Checkpoint>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Atomically writes the checkpoint to the file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the checkpoint could not be serialized or written.
+    pub fn save(&self, path: &Path) -> TrackerResult<()> {
+        write_atomic(path, serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Removes the checkpoint at `path` and its backups, which marks the session as finished.
+    ///
+    /// # Errors
+    /// Returns an [`io::Error`] if a file could not be removed.
+    pub fn remove(path: &Path) -> io::Result<()> {
+        for backup in backups(path) {
+            fs::remove_file(backup)?;
+        }
+
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Converts the checkpoint into a [`Session`] which ends at the checkpoint.
+    pub fn into_session(self) -> Session {
+        Session {
+            version: SESSION_VERSION,
+            start: self.start,
+            end: self.checkpointed_at,
+            duration: self.duration,
+            end_reason: EndReason::Recovered,
+            app_version: self.app_version,
+            idle: self.idle,
+        }
+    }
+}
+
+/// This function looks for an unfinished session in the `data_dir` and handles it according to `choice`.
+/// The recovered [`Session`] is returned if it was recorded.
+///
+/// Sessions without any time played are discarded without asking, and in daemon mode
+/// [`UnfinishedSession::Ask`] recovers the session instead of asking.
+///
+/// # Errors
+/// Returns an error if the checkpoint could not be read or removed, or the session could not be recorded.
+pub fn handle_unfinished_session(
+    data_dir: &DataDir,
+    choice: UnfinishedSession,
+) -> TrackerResult<Option<Session>> {
+    let path = data_dir.checkpoint_file();
+
+    let Some(checkpoint) = Checkpoint::load(&path)? else {
+        return Ok(None);
+    };
+
+    info!(
+        "found an unfinished session which started at {} with {}s played",
+        checkpoint.start, checkpoint.duration
+    );
+
+    let recover = checkpoint.duration > 0
+        && match choice {
+            UnfinishedSession::Ask if is_daemon() => true,
+            UnfinishedSession::Ask => prompt_recovery(&checkpoint),
+            UnfinishedSession::Recover => true,
+            UnfinishedSession::Discard => false,
+        };
+
+    let session = if recover {
+        let session = checkpoint.into_session();
+
+        open_store(data_dir)?.add(&session)?;
+        green_ln_bold!("Recovered the unfinished session: {session}\n");
+
+        Some(session)
+    } else {
+        warn!("discarded the unfinished session");
+        None
+    };
+
+    Checkpoint::remove(&path)?;
+
+    Ok(session)
+}
+
+/// Asks the user whether the session of the `checkpoint` should be recovered.
+fn prompt_recovery(checkpoint: &Checkpoint) -> bool {
+    let mut option = String::new();
+
+    yellow_ln_bold!(
+        "The tracker was closed during a session which started at {} ({}s played).",
+        checkpoint.start.format("%Y-%m-%d %H:%M"),
+        checkpoint.duration
+    );
+    white!("Recover the session up to its last checkpoint (y/n)? ");
+    io::stdout()
+        .flush()
+        .unwrap_or_else(|_| warn!("could not flush output stream"));
+
+    if let Err(e) = io::stdin().read_line(&mut option) {
+        warn!("could not read the answer, the session will be recovered: {e}");
+        return true;
+    }
+
+    option.trim().to_lowercase() != "n"
+}
@@ -0,0 +1,415 @@
+//! This module contains the command line interface of the program, which is parsed by [`clap`].
+//!
+//! Running the program without a subcommand is the same as `track`, which waits for the game and records the
+//! sessions with the tray icon. The other subcommands run once and exit without the tray icon, so the tracker
+//! can be scripted and queried:
+//!
+//! ```text
+//! rl-hours-tracker status
+//! rl-hours-tracker report --since 2024-09-01 --until 2024-09-30
+//! rl-hours-tracker report --window month
+//! rl-hours-tracker export --format csv --output sessions.csv
+//! rl-hours-tracker import sessions.jsonl
+//! rl-hours-tracker recalc
+//! rl-hours-tracker validate
+//! rl-hours-tracker website --open
+//! rl-hours-tracker update --check
+//! rl-hours-tracker config set idle.timeout_minutes 5
+//! ```
+//!
+//! The `--data-dir` and `--daemon` flags can be passed to any subcommand.
+use crate::{
+    calculate_past_two::{report, Window},
+    check_latest_version,
+    config::Config,
+    create_directory,
+    data_dir::DataDir,
+    error::{TrackerError, TrackerResult},
+    export_sessions,
+    games::{Game, ROCKET_LEAGUE_ID},
+    hours::recalculate_game,
+    import_sessions,
+    parser::validate_games,
+    run_self_update, status,
+    store::open_store,
+    timezone,
+    website_files::generate_website_files,
+    ExportFormat,
+};
+use chrono::{Days, NaiveDate};
+use clap::{Parser, Subcommand};
+use colour::{green_ln_bold, yellow_ln_bold};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Write},
+    path::PathBuf,
+};
+use toml::Value;
+
+/// Tracks the hours played of Rocket League and other games.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Overrides the folder which stores the tracker data
+    #[arg(long, global = true, value_name = "PATH")]
+    pub data_dir: Option<PathBuf>,
+    /// Runs without interactive prompts
+    #[arg(long, global = true)]
+    pub daemon: bool,
+    /// The command to run, which is `track` if it is left out
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// Returns the command to run, which defaults to [`Command::Track`].
+    pub fn command(&self) -> Command {
+        self.command.clone().unwrap_or(Command::Track)
+    }
+}
+
+impl Command {
+    /// Returns `true` if the command reads the data folder, so the data files are recovered and the legacy
+    /// `date.txt` file is migrated before it runs. `validate` reports the files as they are.
+    pub fn reads_data(&self) -> bool {
+        !matches!(
+            self,
+            Command::Validate | Command::Update { .. } | Command::Config { .. }
+        )
+    }
+}
+
+/// The subcommands of the program.
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Waits for the game and records the sessions, with the tray icon
+    Track,
+    /// Shows the hours of every game, and any session in progress
+    Status,
+    /// Shows the time played on each day between two dates
+    Report {
+        /// The first day of the report, which defaults to two weeks before `--until`
+        #[arg(long)]
+        since: Option<NaiveDate>,
+        /// The last day of the report, which defaults to today
+        #[arg(long)]
+        until: Option<NaiveDate>,
+        /// The window of the report instead of the dates, such as `7d`, `2w`, `month` or `2024-09-01..2024-09-30`
+        #[arg(long, conflicts_with_all = ["since", "until"])]
+        window: Option<Window>,
+        /// The id of the game
+        #[arg(long, default_value = ROCKET_LEAGUE_ID)]
+        game: String,
+    },
+    /// Writes every session to stdout or a file
+    Export {
+        /// The format of the sessions
+        #[arg(long, value_enum, default_value_t)]
+        format: ExportFormat,
+        /// The file which the sessions are written to, instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// The id of the game
+        #[arg(long, default_value = ROCKET_LEAGUE_ID)]
+        game: String,
+    },
+    /// Adds the sessions of a JSON lines file, skipping sessions which are already recorded
+    Import {
+        /// The file which the sessions are read from
+        path: PathBuf,
+        /// The id of the game
+        #[arg(long, default_value = ROCKET_LEAGUE_ID)]
+        game: String,
+    },
+    /// Rebuilds hours.txt of every game from the session history
+    Recalc,
+    /// Checks the data files of every game, and reports every line which can not be parsed
+    Validate,
+    /// Generates the website files
+    Website {
+        /// Opens the website in the browser
+        #[arg(long)]
+        open: bool,
+    },
+    /// Updates the program to the latest release
+    Update {
+        /// Only checks if there is a new release, without installing it
+        #[arg(long)]
+        check: bool,
+    },
+    /// Reads or changes config.toml
+    Config {
+        /// What to do with the configuration
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+/// The subcommands of [`Command::Config`].
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum ConfigAction {
+    /// Prints a setting, or the whole configuration if the key is left out
+    Get {
+        /// The key of the setting, such as `idle.timeout_minutes`
+        key: Option<String>,
+    },
+    /// Changes a setting and saves config.toml
+    Set {
+        /// The key of the setting, such as `idle.timeout_minutes`
+        key: String,
+        /// The new value, such as `5`, `true` or `yes`
+        value: String,
+    },
+    /// Prints the path of config.toml
+    Path,
+}
+
+/// This function runs a `command` which exits once it is done. The [`DataDir`] must be initialized first.
+///
+/// # Errors
+/// Returns an error if the `command` failed, or it is [`Command::Track`], which is run by the binary.
+pub fn execute(command: Command) -> TrackerResult<()> {
+    let data_dir = DataDir::current();
+
+    match command {
+        Command::Track => Err(TrackerError::Command(
+            "the tracker is run by the binary, not execute".to_string(),
+        )),
+        Command::Status => {
+            let config = Config::load()?;
+
+            for status in status(data_dir, &config.games())? {
+                green_ln_bold!("{}", status.game.name);
+                println!("Total Hours: {:.1}hrs", status.summary.total_hours());
+                println!(
+                    "Hours Past Two Weeks: {:.1}hrs",
+                    status.summary.past_two_hours()
+                );
+
+                for (window, seconds) in &status.summary.windows {
+                    println!(
+                        "Hours {}: {:.1}hrs",
+                        window.label(),
+                        *seconds as f64 / 3600.0
+                    );
+                }
+
+                println!("Idle Hours: {:.1}hrs", status.summary.idle_hours());
+
+                match status.session {
+                    Some(session) => println!(
+                        "Session in progress since {} ({}s)\n",
+                        session.start.format("%Y-%m-%d %H:%M"),
+                        session.duration
+                    ),
+                    None => println!("No session in progress\n"),
+                }
+            }
+
+            Ok(())
+        }
+        Command::Report {
+            since,
+            until,
+            window,
+            game,
+        } => {
+            let game = find_game(&Config::load()?, &game)?;
+            let today = timezone::current().today();
+            let (since, until) = match window {
+                Some(window) => window.bounds(today),
+                None => {
+                    let until = until.unwrap_or(today);
+                    let two_weeks = until
+                        .checked_sub_days(Days::new(14))
+                        .unwrap_or(NaiveDate::MIN);
+
+                    (since.unwrap_or(two_weeks), until)
+                }
+            };
+            let store = open_store(&game.data_dir(data_dir))?;
+            let report = report(store.as_ref(), since, until)?;
+
+            green_ln_bold!("{} from {} to {}", game.name, report.since, report.until);
+
+            for day in &report.days {
+                println!(
+                    "{}: {:.1}hrs ({}s in {} sessions)",
+                    day.period_start,
+                    day.seconds as f64 / 3600.0,
+                    day.seconds,
+                    day.sessions
+                );
+            }
+
+            println!(
+                "Total: {:.1}hrs ({}s in {} sessions, {}s idle)",
+                report.seconds as f64 / 3600.0,
+                report.seconds,
+                report.sessions,
+                report.idle_seconds
+            );
+
+            Ok(())
+        }
+        Command::Export {
+            format,
+            output,
+            game,
+        } => {
+            let game_dir = find_game(&Config::load()?, &game)?.data_dir(data_dir);
+
+            match output {
+                Some(path) => {
+                    let mut writer = BufWriter::new(File::create(&path)?);
+                    let count = export_sessions(&game_dir, format, &mut writer)?;
+                    writer.flush()?;
+
+                    green_ln_bold!("Exported {count} sessions to {}", path.display());
+                }
+                None => {
+                    let mut stdout = io::stdout().lock();
+                    export_sessions(&game_dir, format, &mut stdout)?;
+                    stdout.flush()?;
+                }
+            }
+
+            Ok(())
+        }
+        Command::Import { path, game } => {
+            let game = find_game(&Config::load()?, &game)?;
+            let game_dir = game.data_dir(data_dir);
+            let added = import_sessions(&game_dir, BufReader::new(File::open(&path)?))?;
+
+            recalculate_game(data_dir, &game)?;
+
+            green_ln_bold!("Imported {added} sessions from {}", path.display());
+            Ok(())
+        }
+        Command::Recalc => {
+            for game in Config::load()?.games() {
+                let report = recalculate_game(data_dir, &game)?;
+
+                if report.has_discrepancy() {
+                    yellow_ln_bold!(
+                        "hours.txt of {} was corrected by {}s",
+                        game.name,
+                        report.seconds_corrected()
+                    );
+                }
+
+                print!("{}", report.summary.contents(&game.name));
+            }
+
+            Ok(())
+        }
+        Command::Validate => {
+            let mut problems = 0;
+
+            for (game, errors) in validate_games(data_dir, &Config::load()?.games())? {
+                if errors.is_empty() {
+                    green_ln_bold!("{}: no problems found", game.name);
+                    continue;
+                }
+
+                yellow_ln_bold!("{}: {} problems found", game.name, errors.len());
+
+                for error in &errors {
+                    println!(
+                        "{}:{}: {}: '{}'",
+                        error.file, error.line, error.message, error.text
+                    );
+                }
+
+                problems += errors.len();
+            }
+
+            match problems {
+                0 => Ok(()),
+                problems => Err(TrackerError::Invalid(problems)),
+            }
+        }
+        Command::Website { open } => {
+            for result in create_directory() {
+                if let Err(e) = result {
+                    if e.kind() != ErrorKind::AlreadyExists {
+                        return Err(e.into());
+                    }
+                }
+            }
+
+            generate_website_files(false)?;
+
+            let index_path = data_dir.website_pages_dir().join("index.html");
+
+            if open {
+                webbrowser::open(&index_path.to_string_lossy())?;
+            }
+
+            green_ln_bold!("Website generated at {}", index_path.display());
+            Ok(())
+        }
+        Command::Update { check: true } => {
+            match check_latest_version()? {
+                Some(version) => yellow_ln_bold!("Version {version} is available"),
+                None => green_ln_bold!("Latest Version: {}", env!("CARGO_PKG_VERSION")),
+            }
+
+            Ok(())
+        }
+        Command::Update { check: false } => {
+            if run_self_update()? {
+                green_ln_bold!("Updated, the new version starts the next time the tracker is run");
+            }
+
+            Ok(())
+        }
+        Command::Config { action } => {
+            // The path and set actions do not need a valid file, so a broken configuration can be fixed
+            match action {
+                ConfigAction::Get { key: None } => {
+                    print!("{}", toml::to_string_pretty(&Config::load()?)?)
+                }
+                ConfigAction::Get { key: Some(key) } => {
+                    println!("{}", display_value(&Config::load()?.get(&key)?))
+                }
+                ConfigAction::Set { key, value } => {
+                    let path = Config::path().ok_or(TrackerError::NoConfigDir)?;
+                    let (config, backup) = Config::set_in_file(&path, &key, &value)?;
+
+                    if let Some(backup) = backup {
+                        yellow_ln_bold!(
+                            "config.toml was not valid, it was replaced and kept as {}",
+                            backup.display()
+                        );
+                    }
+
+                    green_ln_bold!("{key} = {}", config.get(&key)?);
+                }
+                ConfigAction::Path => match Config::path() {
+                    Some(path) => println!("{}", path.display()),
+                    None => return Err(TrackerError::NoConfigDir),
+                },
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Returns the game in the configuration with the `id`.
+fn find_game(config: &Config, id: &str) -> TrackerResult<Game> {
+    config
+        .games()
+        .into_iter()
+        .find(|game| game.id == id)
+        .ok_or_else(|| TrackerError::UnknownGame(id.to_string()))
+}
+
+/// Formats a setting for printing, where strings are printed without quotes.
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,349 @@
+//! This module contains the user configuration for the Rocket League Hours Tracker.
+//!
+//! The configuration is stored as `config.toml` in the platform configuration directory,
+//! which is resolved through the [`directories`] crate:
+//! - Windows: `C:\Users\<user>\AppData\Roaming\OneilNvM\rl-hours-tracker\config\config.toml`
+//! - Linux: `~/.config/rl-hours-tracker/config.toml`
+//! - macOS: `~/Library/Application Support/com.OneilNvM.rl-hours-tracker/config.toml`
+//!
+//! ```toml
+//! # Folder where the hours, dates, logs and website are stored
+//! data_dir = "/home/oneil/rl-hours"
+//! # Seconds between checkpoints of the session in progress
+//! checkpoint_interval = 60
+//! # What to do with an unfinished session on startup: "ask", "recover" or "discard"
+//! unfinished_session = "ask"
+//! # Runs without prompts, see the daemon module
+//! daemon = false
+//! # Answers to the prompts: "ask", "yes" or "no"
+//! open_website = "ask"
+//! install_updates = "ask"
+//! end_after_session = "ask"
+//! # Periods shown in hours.txt and the website, see the calculate_past_two module
+//! windows = ["14d", "7d", "month"]
+//! # Timezone the sessions are split into days in: "recorded", "local" or a name, see the timezone module
+//! timezone = "recorded"
+//! # What to do with invalid lines in date.txt and hours.txt: "strict" or "lenient", see the parser module
+//! parsing = "strict"
+//!
+//! # Rules which detect the game process, see the detection module
+//! [detection]
+//! process_names = ["RocketLeague.exe"]
+//! launcher_patterns = true
+//!
+//! # Pauses the session clock while the player is idle, see the idle module
+//! [idle]
+//! enabled = true
+//! timeout_minutes = 10
+//!
+//! # Where the CSS, JavaScript and images of the website come from: "bundled" or "github", see the website_files module
+//! [website]
+//! theme = "bundled"
+//!
+//! # Serves the website and the live status of the tracker on localhost, see the server module
+//! [server]
+//! enabled = false
+//! port = 8787
+//!
+//! # Other games to track, see the games module
+//! [[games]]
+//! id = "fall-guys"
+//! name = "Fall Guys"
+//! process_names = ["FallGuys_client_game.exe"]
+//! ```
+use crate::{
+    atomic_file::{backup_path, write_atomic},
+    calculate_past_two::Window,
+    checkpoint::DEFAULT_CHECKPOINT_INTERVAL,
+    daemon::is_daemon,
+    detection::DetectionRules,
+    error::{TrackerError, TrackerResult},
+    games::{registry, CustomGame, Game},
+    idle::IdleConfig,
+    parser::ParseMode,
+    server::ServerConfig,
+    timezone::Timezone,
+    website_files::WebsiteConfig,
+};
+use directories::ProjectDirs;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// Returns the [`ProjectDirs`] for the program, which is used to find the platform specific
+/// configuration and data directories.
+pub fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("com", "OneilNvM", "rl-hours-tracker")
+}
+
+/// Used for returning errors when a setting is read or changed by its key
+#[derive(Debug)]
+pub struct ConfigKeyError {
+    message: String,
+}
+
+impl Display for ConfigKeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Config Key Error: {}", self.message)
+    }
+}
+
+impl Error for ConfigKeyError {}
+
+/// What to do with an unfinished session which is found on startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnfinishedSession {
+    /// Ask the user whether the session should be recovered.
+    #[default]
+    Ask,
+    /// Record the session up to its last checkpoint.
+    Recover,
+    /// Discard the session.
+    Discard,
+}
+
+/// The answer to a question which the program asks on stdin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Prompt {
+    /// Ask the user, or use the default answer in daemon mode.
+    #[default]
+    Ask,
+    /// Always answer yes.
+    Yes,
+    /// Always answer no.
+    No,
+}
+
+impl Prompt {
+    /// Returns the answer to the question, or [`None`] if the user should be asked. In daemon mode,
+    /// [`Prompt::Ask`] is answered with `daemon_default`.
+    pub fn decide(self, daemon_default: bool) -> Option<bool> {
+        match self {
+            Prompt::Ask if is_daemon() => Some(daemon_default),
+            Prompt::Ask => None,
+            Prompt::Yes => Some(true),
+            Prompt::No => Some(false),
+        }
+    }
+}
+
+/// The user configuration for the program.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Overrides the folder which stores the tracker data.
+    pub data_dir: Option<PathBuf>,
+    /// Seconds between checkpoints of the session in progress.
+    pub checkpoint_interval: u64,
+    /// What to do with an unfinished session which is found on startup.
+    pub unfinished_session: UnfinishedSession,
+    /// Whether the program runs without prompts, as in the `--daemon` flag.
+    pub daemon: bool,
+    /// Whether the website is opened in the browser after a session.
+    pub open_website: Prompt,
+    /// Whether a new version is installed when it is found on startup.
+    pub install_updates: Prompt,
+    /// Whether the program ends after a session, instead of waiting for the next one.
+    pub end_after_session: Prompt,
+    /// The periods which the time played is shown for, besides the total.
+    pub windows: Vec<Window>,
+    /// The timezone which the sessions are split into days in.
+    pub timezone: Timezone,
+    /// What to do with invalid lines in `date.txt` and `hours.txt`.
+    pub parsing: ParseMode,
+    /// The rules which detect the Rocket League process.
+    pub detection: DetectionRules,
+    /// The settings of the idle detection.
+    pub idle: IdleConfig,
+    /// The settings of the website.
+    pub website: WebsiteConfig,
+    /// The settings of the dashboard server.
+    pub server: ServerConfig,
+    /// The games which are tracked alongside Rocket League.
+    pub games: Vec<CustomGame>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            data_dir: None,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+            unfinished_session: UnfinishedSession::default(),
+            daemon: false,
+            open_website: Prompt::default(),
+            install_updates: Prompt::default(),
+            end_after_session: Prompt::default(),
+            windows: vec![Window::default()],
+            timezone: Timezone::default(),
+            parsing: ParseMode::default(),
+            detection: DetectionRules::default(),
+            idle: IdleConfig::default(),
+            website: WebsiteConfig::default(),
+            server: ServerConfig::default(),
+            games: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Returns the registry of every tracked [`Game`], starting with Rocket League.
+    pub fn games(&self) -> Vec<Game> {
+        registry(&self.detection, &self.games)
+    }
+
+    /// Returns the path of `config.toml` in the platform configuration directory,
+    /// or [`None`] if the home directory of the user could not be found.
+    pub fn path() -> Option<PathBuf> {
+        project_dirs().map(|dirs| dirs.config_dir().join("config.toml"))
+    }
+
+    /// Loads the configuration from `config.toml`. The default configuration is returned
+    /// if the file does not exist.
+    ///
+    /// # Errors
+    /// Returns an [`std::io::Error`] if the file could not be read, or a [`toml::de::Error`]
+    /// if the contents are not valid.
+    pub fn load() -> TrackerResult<Config> {
+        match Config::path() {
+            Some(path) => Config::load_from(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Loads the configuration from the file at `path`. The default configuration is returned
+    /// if the file does not exist.
+    ///
+    /// # Errors
+    /// Returns an [`std::io::Error`] if the file could not be read, or a [`toml::de::Error`]
+    /// if the contents are not valid.
+    pub fn load_from(path: &Path) -> TrackerResult<Config> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the configuration to `config.toml`, creating the configuration directory if needed.
+    ///
+    /// # Errors
+    /// Returns an [`std::io::Error`] if the file could not be written.
+    pub fn save(&self) -> TrackerResult<()> {
+        let path = Config::path().ok_or(TrackerError::NoConfigDir)?;
+
+        self.save_to(&path)
+    }
+
+    /// Writes the configuration to the file at `path`, creating the parent directory if needed.
+    ///
+    /// # Errors
+    /// Returns an [`std::io::Error`] if the file could not be written.
+    pub fn save_to(&self, path: &Path) -> TrackerResult<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        write_atomic(path, toml::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Returns the value of the setting at `key`, where the keys of nested tables are separated by dots,
+    /// such as `idle.timeout_minutes`.
+    ///
+    /// # Errors
+    /// Returns a [`ConfigKeyError`] if there is no setting at `key`, or it is not set.
+    pub fn get(&self, key: &str) -> TrackerResult<Value> {
+        let config = Value::try_from(self)?;
+
+        key.split('.')
+            .try_fold(&config, |value, part| value.get(part))
+            .cloned()
+            .ok_or_else(|| {
+                ConfigKeyError {
+                    message: format!("no value is set for '{key}'"),
+                }
+                .into()
+            })
+    }
+
+    /// Changes the setting at `key` to `value`, where the keys of nested tables are separated by dots. The
+    /// `value` is parsed as a TOML value, such as `true`, `30` or `["RocketLeague.exe"]`, and is used as a
+    /// string if it is not one. The configuration is not saved.
+    ///
+    /// # Errors
+    /// Returns a [`ConfigKeyError`] if there is no setting at `key`, or a [`toml::de::Error`] if `value`
+    /// is not valid for the setting.
+    pub fn set(&mut self, key: &str, value: &str) -> TrackerResult<()> {
+        let value = format!("value = {value}")
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| Value::String(value.to_string()));
+
+        let mut config = Value::try_from(&*self)?;
+        let (parents, last) = key.rsplit_once('.').unwrap_or(("", key));
+        let table = parents
+            .split('.')
+            .filter(|part| !part.is_empty())
+            .try_fold(&mut config, |table, part| table.get_mut(part))
+            .and_then(Value::as_table_mut)
+            .ok_or_else(|| ConfigKeyError {
+                message: format!("'{key}' is not a setting"),
+            })?;
+
+        table.insert(last.to_string(), value);
+
+        let updated: Config = config.try_into()?;
+
+        // Unknown keys are ignored when deserializing, so the key must be found again
+        if updated.get(key).is_err() {
+            return Err(ConfigKeyError {
+                message: format!("'{key}' is not a setting"),
+            }
+            .into());
+        }
+
+        *self = updated;
+
+        Ok(())
+    }
+
+    /// Changes the setting at `key` to `value` in the configuration file at `path` with [`Config::set`], and saves
+    /// it. A file which is not a valid configuration is replaced by the default configuration with the setting
+    /// changed, so a broken file can still be fixed with `config set`. The configuration is returned with the
+    /// path of the backup which keeps the replaced file, if it was not valid.
+    ///
+    /// # Errors
+    /// Returns an error if the setting could not be changed, or the file could not be read or written.
+    pub fn set_in_file(
+        path: &Path,
+        key: &str,
+        value: &str,
+    ) -> TrackerResult<(Config, Option<PathBuf>)> {
+        let (mut config, backup) = match Config::load_from(path) {
+            Ok(config) => (config, None),
+            Err(TrackerError::ConfigFormat(e)) => {
+                warn!("{} is not valid and is replaced: {e}", path.display());
+
+                // Saving moves the file which is not valid to the first backup
+                (Config::default(), Some(backup_path(path, 0)))
+            }
+            Err(e) => return Err(e),
+        };
+
+        config.set(key, value)?;
+        config.save_to(path)?;
+
+        Ok((config, backup))
+    }
+}
@@ -0,0 +1,64 @@
+//! This module contains the daemon mode, which runs the tracker without any interactive prompts.
+//!
+//! The daemon mode is enabled with the `--daemon` flag of the [`Cli`](crate::cli::Cli) or `daemon = true` in
+//! `config.toml`, so the tracker can run as a systemd user service or an autostart entry. The tracker keeps looping
+//! across sessions, and every question which would be asked on stdin is answered by its
+//! [`Prompt`](crate::config::Prompt) setting instead. When there is no display for the tray icon, the tracker runs
+//! without it.
+//!
+//! The tracker is ended by `SIGINT`, `SIGTERM` or `SIGHUP`, or by "Exit" in the tray menu. A session in progress is
+//! stopped and recorded before the program quits.
+//!
+//! "Stop Tracker" in the tray menu records the session in progress, and the daemon keeps running without starting a
+//! new session of the game until it is closed.
+use crate::{tracker::TrackerHandle, winit_tray_icon::UserEvent};
+use colour::yellow_ln_bold;
+use log::{error, info};
+use std::{
+    io::Write,
+    sync::{atomic::AtomicBool, OnceLock},
+};
+use winit::event_loop::EventLoopProxy;
+
+static DAEMON: OnceLock<bool> = OnceLock::new();
+
+/// Sets whether the program runs in daemon mode and returns it.
+///
+/// The mode can only be set once, so if it was already set, the existing mode is returned instead.
+pub fn init(daemon: bool) -> bool {
+    *DAEMON.get_or_init(|| daemon)
+}
+
+/// Returns `true` if the program runs in daemon mode. This is `false` if [`init`] was not called.
+pub fn is_daemon() -> bool {
+    DAEMON.get().copied().unwrap_or(false)
+}
+
+/// Requests the tracker to shut down. If a session is in progress, it is stopped and the tracker quits once the
+/// session is recorded, otherwise the event loop of the `proxy` is told to quit at once.
+pub fn request_shutdown(proxy: Option<&EventLoopProxy<UserEvent>>, tracker: &TrackerHandle) {
+    if tracker.shutdown().is_tracking() {
+        info!("Stopping the session in progress before shutting down");
+        return;
+    }
+
+    print!("{}[2K\r", 27 as char);
+    std::io::stdout().flush().unwrap_or_default();
+    yellow_ln_bold!("Goodbye!");
+
+    if let Some(proxy) = proxy {
+        let _ = proxy.send_event(UserEvent::QuitApp(AtomicBool::new(true)));
+    }
+}
+
+/// Installs the handler for `SIGINT`, `SIGTERM` and `SIGHUP` (or Ctrl+C on Windows), which calls
+/// [`request_shutdown`].
+pub fn install_signal_handler(proxy: Option<EventLoopProxy<UserEvent>>, tracker: TrackerHandle) {
+    ctrlc::set_handler(move || {
+        info!("Interrupting program");
+        request_shutdown(proxy.as_ref(), &tracker);
+    })
+    .unwrap_or_else(|e| {
+        error!("could not create handler for ctrlc: {e}");
+    });
+}
@@ -0,0 +1,186 @@
+//! This module contains the [`DataDir`], which is the folder where the tracker stores `hours.txt`,
+//! `sessions.jsonl`, the logs and the website files.
+//!
+//! The folder is resolved in the following order:
+//! 1. The `--data-dir <path>` flag of the [`Cli`](crate::cli::Cli).
+//! 2. The `RL_HOURS_TRACKER_DIR` environment variable.
+//! 3. The `data_dir` key in the [`Config`] file.
+//! 4. The platform default. On Windows this is `C:\RLHoursFolder` if it already exists, which keeps
+//!    existing installs working, otherwise the data directory from the [`directories`] crate is used.
+//!
+//! ```
+//! use rl_hours_tracker::data_dir::DataDir;
+//!
+//! let data_dir = DataDir::new("/tmp/RLHoursFolder");
+//!
+//! assert!(data_dir.hours_file().ends_with("hours.txt"));
+//! ```
+use crate::{
+    config::{project_dirs, Config},
+    error::TrackerResult,
+};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Environment variable which overrides the data directory.
+pub const DATA_DIR_ENV: &str = "RL_HOURS_TRACKER_DIR";
+
+/// The data directory which was used by versions of the program before it was configurable.
+#[cfg(target_os = "windows")]
+const LEGACY_WINDOWS_DIR: &str = "C:\\RLHoursFolder";
+
+static DATA_DIR: OnceLock<DataDir> = OnceLock::new();
+
+/// The folder where all of the tracker data is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    /// Creates a new instance with `root` as the data directory.
+    pub fn new(root: impl Into<PathBuf>) -> DataDir {
+        DataDir { root: root.into() }
+    }
+
+    /// Resolves the data directory from the command line `flag`, the environment variable,
+    /// the configuration file, or the platform default, in that order.
+    ///
+    /// # Errors
+    /// Returns an error if the configuration file exists but could not be loaded.
+    pub fn resolve(flag: Option<PathBuf>) -> TrackerResult<DataDir> {
+        if let Some(path) = flag {
+            return Ok(DataDir::new(path));
+        }
+
+        if let Some(path) = env::var_os(DATA_DIR_ENV).filter(|p| !p.is_empty()) {
+            return Ok(DataDir::new(path));
+        }
+
+        if let Some(path) = Config::load()?.data_dir {
+            return Ok(DataDir::new(path));
+        }
+
+        Ok(DataDir::platform_default())
+    }
+
+    /// Returns the platform default data directory.
+    pub fn platform_default() -> DataDir {
+        #[cfg(target_os = "windows")]
+        {
+            let legacy = Path::new(LEGACY_WINDOWS_DIR);
+
+            if legacy.is_dir() {
+                return DataDir::new(legacy);
+            }
+        }
+
+        match project_dirs() {
+            Some(dirs) => DataDir::new(dirs.data_dir()),
+            None => DataDir::new("RLHoursFolder"),
+        }
+    }
+
+    /// Sets the data directory used by the program and returns it.
+    ///
+    /// The data directory can only be set once, so if it was already set, the existing
+    /// data directory is returned instead.
+    pub fn init(data_dir: DataDir) -> &'static DataDir {
+        DATA_DIR.get_or_init(|| data_dir)
+    }
+
+    /// Returns the data directory used by the program. If [`DataDir::init`] was not called,
+    /// the data directory is resolved without a command line flag.
+    pub fn current() -> &'static DataDir {
+        DATA_DIR
+            .get_or_init(|| DataDir::resolve(None).unwrap_or_else(|_| DataDir::platform_default()))
+    }
+
+    /// Returns the root of the data directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the path of `hours.txt`.
+    pub fn hours_file(&self) -> PathBuf {
+        self.root.join("hours.txt")
+    }
+
+    /// Returns the path of `sessions.jsonl`, which stores every recorded [`crate::session::Session`].
+    pub fn sessions_file(&self) -> PathBuf {
+        self.root.join("sessions.jsonl")
+    }
+
+    /// Returns the path of `sessions.db`, which is used when the program is built with the `sqlite` feature.
+    pub fn database_file(&self) -> PathBuf {
+        self.root.join("sessions.db")
+    }
+
+    /// Returns the path of `checkpoint.json`, which stores the session in progress.
+    pub fn checkpoint_file(&self) -> PathBuf {
+        self.root.join("checkpoint.json")
+    }
+
+    /// Returns the path of `date.txt`, which stored the sessions before `sessions.jsonl` was introduced.
+    pub fn date_file(&self) -> PathBuf {
+        self.root.join("date.txt")
+    }
+
+    /// Returns the path of `schema.json`, which stores the version of the data directory layout.
+    pub fn schema_file(&self) -> PathBuf {
+        self.root.join("schema.json")
+    }
+
+    /// Returns the path of the games folder, which stores the files of every game other than Rocket League.
+    pub fn games_dir(&self) -> PathBuf {
+        self.root.join("games")
+    }
+
+    /// Returns the path of the backup folder.
+    pub fn backup_dir(&self) -> PathBuf {
+        self.root.join("backup")
+    }
+
+    /// Returns the path of the logs folder.
+    pub fn logs_dir(&self) -> PathBuf {
+        self.root.join("logs")
+    }
+
+    /// Returns the path of the website folder.
+    pub fn website_dir(&self) -> PathBuf {
+        self.root.join("website")
+    }
+
+    /// Returns the path of the website pages folder.
+    pub fn website_pages_dir(&self) -> PathBuf {
+        self.website_dir().join("pages")
+    }
+
+    /// Returns the path of the website CSS folder.
+    pub fn website_css_dir(&self) -> PathBuf {
+        self.website_dir().join("css")
+    }
+
+    /// Returns the path of the website JavaScript folder.
+    pub fn website_js_dir(&self) -> PathBuf {
+        self.website_dir().join("js")
+    }
+
+    /// Returns the path of the website images folder.
+    pub fn website_images_dir(&self) -> PathBuf {
+        self.website_dir().join("images")
+    }
+
+    /// Returns the path of the templates folder, which holds the user templates of the website.
+    pub fn templates_dir(&self) -> PathBuf {
+        self.root.join("templates")
+    }
+
+    /// Returns the path of the theme folder, whose files are copied over the website assets.
+    pub fn theme_dir(&self) -> PathBuf {
+        self.root.join("theme")
+    }
+}
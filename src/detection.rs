@@ -0,0 +1,176 @@
+//! This module contains the [`DetectionRules`], which decide if a running process is Rocket League.
+//!
+//! On Windows the game is a process called `RocketLeague.exe`. On Linux the game runs through Steam Proton, Lutris or
+//! Heroic, where the process is usually `wine-preloader` or `wine64-preloader` and the game only shows up in the
+//! executable path or the command line. A process is detected if any of these match:
+//! - Its name is one of the [`DetectionRules::process_names`]. Linux truncates names to 15 bytes, which is handled.
+//! - The file name of its executable, or of its first command line argument, is one of the process names.
+//! - Its executable path matches one of the [`DetectionRules::exe_patterns`].
+//! - Its command line matches one of the [`DetectionRules::cmdline_patterns`], or one of the built in
+//!   [`LAUNCHER_PATTERNS`] when [`DetectionRules::launcher_patterns`] is enabled.
+//!
+//! Patterns are matched case insensitively, `*` matches any text and `?` matches a single character, and `\` is
+//! treated as `/` so Windows paths under Wine can be written with either separator. The rules are configured in
+//! the `[detection]` table of `config.toml`:
+//!
+//! ```toml
+//! [detection]
+//! process_names = ["RocketLeague.exe"]
+//! exe_patterns = ["*/rocketleague/binaries/win64/*"]
+//! cmdline_patterns = ["*my-launcher*rocket*"]
+//! launcher_patterns = true
+//! ```
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The name of the Rocket League process.
+pub const PROCESS_NAME: &str = "RocketLeague.exe";
+
+/// Command line patterns of the launchers which run Rocket League on Linux.
+pub const LAUNCHER_PATTERNS: &[&str] = &[
+    // Proton, Wine, Lutris and Heroic all start the game through its path
+    "*/binaries/win64/rocketleague.exe*",
+    // The Steam reaper process which runs the game through Proton
+    "*steamlaunch appid=252950 *",
+    // Heroic launches the Epic Games version through legendary, where Rocket League is called "Sugar"
+    "*legendary* launch sugar*",
+];
+
+/// The longest process name Linux stores for a process.
+const LINUX_NAME_LEN: usize = 15;
+
+/// The information about a process which the [`DetectionRules`] are matched against.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessInfo {
+    /// The name of the process.
+    pub name: String,
+    /// The path of the executable, if it is known.
+    pub exe: Option<PathBuf>,
+    /// The command line arguments, starting with the program.
+    pub cmdline: Vec<String>,
+}
+
+/// The rules which decide if a process is Rocket League.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DetectionRules {
+    /// Names of the game process.
+    pub process_names: Vec<String>,
+    /// Patterns which are matched against the executable path.
+    pub exe_patterns: Vec<String>,
+    /// Patterns which are matched against the command line, with the arguments separated by spaces.
+    pub cmdline_patterns: Vec<String>,
+    /// Whether the built in [`LAUNCHER_PATTERNS`] are matched against the command line.
+    pub launcher_patterns: bool,
+}
+
+impl Default for DetectionRules {
+    fn default() -> Self {
+        DetectionRules {
+            process_names: vec![String::from(PROCESS_NAME)],
+            exe_patterns: Vec::new(),
+            cmdline_patterns: Vec::new(),
+            launcher_patterns: true,
+        }
+    }
+}
+
+impl DetectionRules {
+    /// Returns `true` if the `process` is Rocket League.
+    pub fn matches(&self, process: &ProcessInfo) -> bool {
+        if self.is_process_name(&process.name, true) {
+            return true;
+        }
+
+        if let Some(exe) = &process.exe {
+            let exe = exe.to_string_lossy();
+
+            if self.is_process_name(file_name(&exe), false)
+                || self.exe_patterns.iter().any(|p| glob_match(p, &exe))
+            {
+                return true;
+            }
+        }
+
+        if process
+            .cmdline
+            .first()
+            .is_some_and(|program| self.is_process_name(file_name(program), false))
+        {
+            return true;
+        }
+
+        let cmdline = process.cmdline.join(" ");
+        let launcher_patterns: &[&str] = if self.launcher_patterns {
+            LAUNCHER_PATTERNS
+        } else {
+            &[]
+        };
+
+        self.cmdline_patterns
+            .iter()
+            .map(String::as_str)
+            .chain(launcher_patterns.iter().copied())
+            .any(|pattern| glob_match(pattern, &cmdline))
+    }
+
+    /// Returns `true` if `name` is one of the process names. When `truncated` is `true`, a name which
+    /// Linux cut off at 15 bytes also matches.
+    fn is_process_name(&self, name: &str, truncated: bool) -> bool {
+        !name.is_empty()
+            && self.process_names.iter().any(|process_name| {
+                process_name.eq_ignore_ascii_case(name)
+                    || (truncated
+                        && name.len() == LINUX_NAME_LEN
+                        && process_name
+                            .get(..LINUX_NAME_LEN)
+                            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name)))
+            })
+    }
+}
+
+/// Returns the file name of a Unix or Windows `path`.
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or_default()
+}
+
+/// Returns `true` if `text` matches the `pattern`, where `*` matches any text and `?` matches a single character.
+/// The match is case insensitive, and `\` is treated as `/`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let normalize = |s: &str| -> Vec<char> {
+        s.chars()
+            .map(|c| if c == '\\' { '/' } else { c })
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+
+    let pattern = normalize(pattern);
+    let text = normalize(text);
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last '*' match one more character
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
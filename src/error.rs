@@ -0,0 +1,101 @@
+//! This module contains the [`TrackerError`], which is returned by the fallible functions of the library.
+//!
+//! The library never exits the process itself. Every failure is returned to the caller, so a program which
+//! embeds the tracker can decide what to do with it, and the binary reports the error before it exits:
+//!
+//! ```no_run
+//! use rl_hours_tracker::{data_dir::DataDir, error::TrackerError, hours::recalculate};
+//!
+//! match recalculate(&DataDir::platform_default()) {
+//!     Ok(report) => println!("{}", report.summary),
+//!     Err(TrackerError::Io(e)) => eprintln!("could not read the data folder: {e}"),
+//!     Err(e) => eprintln!("{e}"),
+//! }
+//! ```
+use crate::{config::ConfigKeyError, migrate::MigrationError, parser::ParseError};
+use std::io;
+
+/// The result of the fallible functions of the library.
+pub type TrackerResult<T> = Result<T, TrackerError>;
+
+/// The errors returned by the library.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum TrackerError {
+    /// A file operation failed.
+    #[error("IO Error: {0}")]
+    Io(#[from] io::Error),
+    /// A JSON file, such as `sessions.jsonl` or `schema.json`, could not be read or written.
+    #[error("JSON Error: {0}")]
+    Json(#[from] serde_json::Error),
+    /// A line of `date.txt` or `hours.txt` could not be parsed.
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    /// `config.toml` is not valid.
+    #[error("Config Error: {0}")]
+    ConfigFormat(#[from] toml::de::Error),
+    /// The configuration could not be written as TOML.
+    #[error("Config Error: {0}")]
+    ConfigWrite(#[from] toml::ser::Error),
+    /// A setting of the configuration does not exist or has an invalid value.
+    #[error(transparent)]
+    ConfigKey(#[from] ConfigKeyError),
+    /// The platform configuration directory could not be found.
+    #[error("Config Error: could not find the configuration directory")]
+    NoConfigDir,
+    /// The legacy `date.txt` layout could not be migrated.
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
+    /// No sessions were recorded in the period.
+    #[error(transparent)]
+    PastTwo(#[from] PastTwoError),
+    /// There is no game with the contained id.
+    #[error("Game Error: there is no game with the id '{0}'")]
+    UnknownGame(String),
+    /// The data folder has the contained number of invalid lines.
+    #[error("Validation Error: the data folder has {0} invalid lines")]
+    Invalid(usize),
+    /// A request to GitHub failed.
+    #[error("Network Error: {0}")]
+    Network(#[from] reqwest::Error),
+    /// The program could not be updated.
+    #[error("Update Error: {0}")]
+    Update(String),
+    /// The archive of an update could not be extracted.
+    #[error("Update Error: {0}")]
+    Archive(#[from] zip::result::ZipError),
+    /// The image of the tray icon could not be decoded.
+    #[error("Tray Error: {0}")]
+    Image(#[from] image::ImageError),
+    /// The tray icon could not be created from the image.
+    #[error("Tray Error: {0}")]
+    Icon(#[from] tray_icon::BadIcon),
+    /// The tray icon could not be created.
+    #[error("Tray Error: {0}")]
+    Tray(#[from] tray_icon::Error),
+    /// The event loop of the tray icon could not be run.
+    #[error("Tray Error: {0}")]
+    EventLoop(#[from] winit::error::EventLoopError),
+    /// A template of the website is not valid, or could not be rendered.
+    #[error("Template Error: {0}")]
+    Template(#[from] minijinja::Error),
+    /// The logging configuration could not be initialized.
+    #[error("Logging Error: {0}")]
+    Logging(String),
+    /// The command can not be run by the library.
+    #[error("Command Error: {0}")]
+    Command(String),
+    /// The blocking work of a dashboard request did not finish.
+    #[error("Server Error: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    /// The session database could not be read or written.
+    #[cfg(feature = "sqlite")]
+    #[error("Database Error: {0}")]
+    Database(#[from] rusqlite::Error),
+}
+
+/// Custom error for the [`calculate_past_two`](crate::calculate_past_two) functions, which is returned when no
+/// sessions were recorded in the period.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("no sessions were recorded in the past two weeks.")]
+pub struct PastTwoError;
@@ -0,0 +1,139 @@
+//! This module contains the registry of the games which are tracked.
+//!
+//! Rocket League is always registered, with the [`DetectionRules`] from the `[detection]` table of `config.toml`, and
+//! keeps its files in the root of the [`DataDir`] so existing data directories keep working. Other games are added as
+//! `[[games]]` entries, and each game stores its own `sessions.jsonl`, `hours.txt` and `checkpoint.json` in
+//! `games/<id>` inside the data directory.
+//!
+//! ```toml
+//! [[games]]
+//! id = "fall-guys"
+//! name = "Fall Guys"
+//! process_names = ["FallGuys_client_game.exe"]
+//! exe_patterns = []
+//! cmdline_patterns = []
+//! icon = "C:/Users/oneil/Pictures/fall-guys.png"
+//! ```
+use crate::{data_dir::DataDir, detection::DetectionRules};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The id of the Rocket League profile.
+pub const ROCKET_LEAGUE_ID: &str = "rocket-league";
+
+/// A game which is tracked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    /// The id of the game, which names its folder in the data directory.
+    pub id: String,
+    /// The name of the game which is displayed in `hours.txt` and the website.
+    pub name: String,
+    /// The rules which detect the game process.
+    pub rules: DetectionRules,
+    /// An image of the game which is displayed in the website.
+    pub icon: Option<PathBuf>,
+}
+
+impl Game {
+    /// Creates the Rocket League profile, which is detected by the `rules`.
+    pub fn rocket_league(rules: DetectionRules) -> Game {
+        Game {
+            id: String::from(ROCKET_LEAGUE_ID),
+            name: String::from("Rocket League"),
+            rules,
+            icon: None,
+        }
+    }
+
+    /// Returns `true` if this is the Rocket League profile.
+    pub fn is_rocket_league(&self) -> bool {
+        self.id == ROCKET_LEAGUE_ID
+    }
+
+    /// Returns the folder which stores the files of the game. This is the root of the `data_dir` for
+    /// Rocket League, and `games/<id>` for every other game.
+    pub fn data_dir(&self, data_dir: &DataDir) -> DataDir {
+        if self.is_rocket_league() {
+            data_dir.clone()
+        } else {
+            DataDir::new(data_dir.games_dir().join(&self.id))
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game::rocket_league(DetectionRules::default())
+    }
+}
+
+/// A game other than Rocket League, as it is configured in the `[[games]]` entries of `config.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomGame {
+    /// The id of the game, which may only contain lowercase letters, digits, `-` and `_`.
+    pub id: String,
+    /// The name of the game.
+    pub name: String,
+    /// Names of the game process.
+    #[serde(default)]
+    pub process_names: Vec<String>,
+    /// Patterns which are matched against the executable path.
+    #[serde(default)]
+    pub exe_patterns: Vec<String>,
+    /// Patterns which are matched against the command line.
+    #[serde(default)]
+    pub cmdline_patterns: Vec<String>,
+    /// An image of the game which is displayed in the website.
+    #[serde(default)]
+    pub icon: Option<PathBuf>,
+}
+
+impl CustomGame {
+    /// Returns `true` if the id can be used as a folder name, and is not the id of the Rocket League profile.
+    pub fn has_valid_id(&self) -> bool {
+        !self.id.is_empty()
+            && self.id != ROCKET_LEAGUE_ID
+            && self
+                .id
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'_')
+    }
+}
+
+impl From<CustomGame> for Game {
+    fn from(game: CustomGame) -> Self {
+        Game {
+            id: game.id,
+            name: game.name,
+            // The launcher patterns only detect Rocket League
+            rules: DetectionRules {
+                process_names: game.process_names,
+                exe_patterns: game.exe_patterns,
+                cmdline_patterns: game.cmdline_patterns,
+                launcher_patterns: false,
+            },
+            icon: game.icon,
+        }
+    }
+}
+
+/// Returns the registry of games, which is Rocket League detected by the `rules`, followed by the `custom_games`.
+/// Games with an invalid or duplicate id are left out.
+pub fn registry(rules: &DetectionRules, custom_games: &[CustomGame]) -> Vec<Game> {
+    let mut games = vec![Game::rocket_league(rules.clone())];
+
+    for game in custom_games {
+        if !game.has_valid_id() || games.iter().any(|g| g.id == game.id) {
+            warn!(
+                "the game '{}' was skipped because its id '{}' is invalid or already used",
+                game.name, game.id
+            );
+            continue;
+        }
+
+        games.push(game.clone().into());
+    }
+
+    games
+}
@@ -0,0 +1,255 @@
+//! This module contains the session history pages of the website, which list every recorded session.
+//!
+//! The sessions are paginated by month: `history.html` lists every month with its number of sessions and hours, and
+//! links to a `history-YYYY-MM.html` page for each month. Each month page shows the date, start and end time and
+//! duration of its sessions, newest first, with links to the newer and older months.
+//!
+//! The sessions of a month can be filtered by a date range and a minimum duration in the browser. The filters are run
+//! by `history.js`, which is bundled with the website, so the pages work without network access. Without JavaScript,
+//! every session of the month is shown.
+//!
+//! The pages are rendered with the `history.html` and `history_month.html` [`Templates`].
+use crate::{
+    data_dir::DataDir,
+    error::TrackerResult,
+    session::{EndReason, Session},
+    store::Grouping,
+    templates::Templates,
+    timezone::{self, Timezone},
+};
+use chrono::{Months, NaiveDate};
+use serde::Serialize;
+use std::{cmp::Reverse, fs};
+
+/// The sessions which were played in a month.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryMonth {
+    /// The first day of the month.
+    pub month: NaiveDate,
+    /// The sessions of the month with the name of their game, newest first.
+    pub sessions: Vec<(String, Session)>,
+}
+
+impl HistoryMonth {
+    /// Returns the file name of the page of the month, such as `history-2024-09.html`.
+    pub fn page_name(&self) -> String {
+        format!("history-{}.html", self.month.format("%Y-%m"))
+    }
+
+    /// Returns the name of the month, such as `September 2024`.
+    pub fn title(&self) -> String {
+        self.month.format("%B %Y").to_string()
+    }
+
+    /// Returns the total seconds played in the month.
+    pub fn total_seconds(&self) -> u64 {
+        self.sessions
+            .iter()
+            .map(|(_, session)| session.duration)
+            .sum()
+    }
+}
+
+/// This function groups the `sessions` into the month they ended in, in the `timezone`. The months are returned
+/// newest first, and the sessions of each month are ordered newest first.
+pub fn group_by_month(sessions: &[(String, Session)], timezone: &Timezone) -> Vec<HistoryMonth> {
+    let mut sessions = sessions.to_vec();
+    sessions.sort_by_key(|(_, session)| Reverse(session.end));
+
+    let mut months: Vec<HistoryMonth> = Vec::new();
+
+    for (game_name, session) in sessions {
+        let month = Grouping::Month.period_start(timezone.convert(session.end).date_naive());
+
+        match months.last_mut() {
+            Some(last) if last.month == month => last.sessions.push((game_name, session)),
+            _ => months.push(HistoryMonth {
+                month,
+                sessions: vec![(game_name, session)],
+            }),
+        }
+    }
+
+    months
+}
+
+/// A link to the page of a month.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MonthLink {
+    /// The file name of the page, such as `history-2024-09.html`.
+    pub page: String,
+    /// The name of the month, such as `September 2024`.
+    pub title: String,
+}
+
+/// A month which is listed in `history.html`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MonthSummary {
+    /// The file name of the page, such as `history-2024-09.html`.
+    pub page: String,
+    /// The name of the month, such as `September 2024`.
+    pub title: String,
+    /// The number of sessions in the month.
+    pub sessions: usize,
+    /// The hours played in the month, formatted to one decimal place.
+    pub hours: String,
+}
+
+/// A session which is listed in the page of a month. The times are formatted in the reporting timezone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionRow {
+    /// The name of the game of the session.
+    pub game: String,
+    /// The date the session ended on, such as `2024-09-02`.
+    pub date: String,
+    /// The time the session started, such as `19:55`, or `-` for legacy sessions.
+    pub start: String,
+    /// The time the session ended, such as `20:00`, or `-` for legacy sessions.
+    pub end: String,
+    /// The duration of the session, such as `00:05:00`.
+    pub duration: String,
+    /// The duration of the session in seconds, which is read by the filters.
+    pub seconds: u64,
+}
+
+/// The variables of the `history_month.html` template.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthContext {
+    /// The name of the month, such as `September 2024`.
+    pub title: String,
+    /// The next month with sessions, if there is one.
+    pub newer: Option<MonthLink>,
+    /// The previous month with sessions, if there is one.
+    pub older: Option<MonthLink>,
+    /// The first day of the month, which limits the date filters.
+    pub first_day: NaiveDate,
+    /// The last day of the month, which limits the date filters.
+    pub last_day: NaiveDate,
+    /// Whether more than one game is tracked, which shows the name of the game of each session.
+    pub multiple_games: bool,
+    /// The sessions of the month, newest first.
+    pub sessions: Vec<SessionRow>,
+}
+
+/// This function writes `history.html` and a page for every month of the `sessions` into the website pages folder of
+/// the `data_dir`, with the `templates`. The name of the game is shown for every session if `multiple_games` is set.
+///
+/// # Errors
+/// Returns a [`TrackerError::Io`](crate::error::TrackerError::Io) if a page could not be written, or a
+/// [`TrackerError::Template`](crate::error::TrackerError::Template) if it could not be rendered.
+pub fn write_history_pages(
+    templates: &Templates,
+    data_dir: &DataDir,
+    sessions: &[(String, Session)],
+    multiple_games: bool,
+) -> TrackerResult<()> {
+    let timezone = timezone::current();
+    let months = group_by_month(sessions, &timezone);
+    let pages_dir = data_dir.website_pages_dir();
+
+    fs::create_dir_all(&pages_dir)?;
+    fs::write(
+        pages_dir.join("history.html"),
+        history_index(templates, &months)?,
+    )?;
+
+    for idx in 0..months.len() {
+        fs::write(
+            pages_dir.join(months[idx].page_name()),
+            history_page(templates, &months, idx, multiple_games, &timezone)?,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// This function renders `history.html`, which links to the page of each of the `months`.
+///
+/// # Errors
+/// Returns a [`TrackerError::Template`](crate::error::TrackerError::Template) if the page could not be rendered.
+pub fn history_index(templates: &Templates, months: &[HistoryMonth]) -> TrackerResult<String> {
+    let months: Vec<MonthSummary> = months
+        .iter()
+        .map(|month| MonthSummary {
+            page: month.page_name(),
+            title: month.title(),
+            sessions: month.sessions.len(),
+            hours: format!("{:.1}", month.total_seconds() as f64 / 3600_f64),
+        })
+        .collect();
+
+    templates.render("history.html", minijinja::context! { months })
+}
+
+/// This function renders the page of the month at `idx` of the `months`, with links to the newer and older months.
+/// The start and end times are shown in the `timezone`.
+///
+/// # Errors
+/// Returns a [`TrackerError::Template`](crate::error::TrackerError::Template) if the page could not be rendered.
+pub fn history_page(
+    templates: &Templates,
+    months: &[HistoryMonth],
+    idx: usize,
+    multiple_games: bool,
+    timezone: &Timezone,
+) -> TrackerResult<String> {
+    let month = &months[idx];
+    let link = |month: &HistoryMonth| MonthLink {
+        page: month.page_name(),
+        title: month.title(),
+    };
+
+    let context = MonthContext {
+        title: month.title(),
+        newer: idx.checked_sub(1).and_then(|idx| months.get(idx)).map(link),
+        older: months.get(idx + 1).map(link),
+        first_day: month.month,
+        last_day: month
+            .month
+            .checked_add_months(Months::new(1))
+            .and_then(|next| next.pred_opt())
+            .unwrap_or(month.month),
+        multiple_games,
+        sessions: month
+            .sessions
+            .iter()
+            .map(|(game_name, session)| session_row(game_name, session, timezone))
+            .collect(),
+    };
+
+    templates.render("history_month.html", context)
+}
+
+/// Returns the row of the `session` in the page of its month.
+fn session_row(game_name: &str, session: &Session, timezone: &Timezone) -> SessionRow {
+    let start = timezone.convert(session.start);
+    let end = timezone.convert(session.end);
+
+    // Legacy sessions do not have a meaningful start and end time
+    let (start_time, end_time) = match session.end_reason {
+        EndReason::Legacy => (String::from("-"), String::from("-")),
+        _ => (
+            start.format("%H:%M").to_string(),
+            end.format("%H:%M").to_string(),
+        ),
+    };
+
+    SessionRow {
+        game: game_name.to_string(),
+        date: end.date_naive().to_string(),
+        start: start_time,
+        end: end_time,
+        duration: format_duration(session.duration),
+        seconds: session.duration,
+    }
+}
+
+/// Formats the `seconds` like the timer of the tracker, such as `01:02:05`.
+pub fn format_duration(seconds: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
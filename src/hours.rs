@@ -0,0 +1,259 @@
+//! This module contains the functionality for `hours.txt`, which summarises the total time played and the
+//! time played in the past two weeks, along with any other [`Window`] set by `windows` in `config.toml`.
+//!
+//! The totals are always derived from the integer seconds of the sessions in the [`SessionStore`], so they
+//! never drift from the session history. [`recalculate`] rebuilds `hours.txt` and reports any discrepancy
+//! between the old file and the session history. The totals only count the time the player was active, and the
+//! time the player was idle is reported on its own line. Every [`Game`] has its own `hours.txt`, which starts with the
+//! name of the game.
+use crate::{
+    atomic_file::write_atomic,
+    calculate_past_two::{past_two_seconds, window_seconds, Window},
+    config::Config,
+    data_dir::DataDir,
+    error::{TrackerError, TrackerResult},
+    games::Game,
+    migrate::Schema,
+    parser::{parse_hours, ParseMode},
+    session::Session,
+    store::{open_store, SessionStore},
+    timezone,
+};
+use chrono::NaiveDate;
+use colour::{green_ln_bold, yellow_ln_bold};
+use log::{info, warn};
+use std::{fmt::Display, fs, io::ErrorKind};
+
+/// The totals which are written to `hours.txt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoursSummary {
+    /// The total time played in seconds.
+    pub total_seconds: u64,
+    /// The time played in the past two weeks in seconds.
+    pub past_two_seconds: u64,
+    /// The time the player was idle in seconds, which is not counted in the total.
+    pub idle_seconds: u64,
+    /// The time played in seconds in each of the other windows, which are shown after the past two weeks.
+    pub windows: Vec<(Window, u64)>,
+}
+
+impl HoursSummary {
+    /// Calculates the summary from the sessions in the `store`. The `unrecorded_seconds` are seconds from the
+    /// legacy `hours.txt` which have no session, and are added to the total. The past two weeks are always
+    /// calculated, and every other window in `windows` is added to the summary in order.
+    ///
+    /// # Errors
+    /// Returns an error if the store could not be read.
+    pub fn from_store(
+        store: &dyn SessionStore,
+        unrecorded_seconds: u64,
+        today: NaiveDate,
+        windows: &[Window],
+    ) -> TrackerResult<HoursSummary> {
+        let sessions = store.sessions()?;
+        let recorded_seconds: u64 = sessions.iter().map(|session| session.duration).sum();
+        let idle_seconds: u64 = sessions.iter().map(Session::idle_seconds).sum();
+
+        let past_two_seconds = match past_two_seconds(store, today) {
+            Ok(seconds) => seconds,
+            Err(TrackerError::PastTwo(_)) => 0,
+            Err(e) => return Err(e),
+        };
+
+        let mut window_totals = Vec::new();
+
+        for &window in windows {
+            if window == Window::default() || window_totals.iter().any(|(w, _)| *w == window) {
+                continue;
+            }
+
+            let seconds = match window_seconds(store, window, today) {
+                Ok(seconds) => seconds,
+                Err(TrackerError::PastTwo(_)) => 0,
+                Err(e) => return Err(e),
+            };
+
+            window_totals.push((window, seconds));
+        }
+
+        Ok(HoursSummary {
+            total_seconds: recorded_seconds + unrecorded_seconds,
+            past_two_seconds,
+            idle_seconds,
+            windows: window_totals,
+        })
+    }
+
+    /// Returns the total time played in hours.
+    pub fn total_hours(&self) -> f64 {
+        self.total_seconds as f64 / 3600_f64
+    }
+
+    /// Returns the time played in the past two weeks in hours.
+    pub fn past_two_hours(&self) -> f64 {
+        self.past_two_seconds as f64 / 3600_f64
+    }
+
+    /// Returns the time the player was idle in hours.
+    pub fn idle_hours(&self) -> f64 {
+        self.idle_seconds as f64 / 3600_f64
+    }
+
+    /// Returns the contents of `hours.txt` for the game called `game_name`.
+    pub fn contents(&self, game_name: &str) -> String {
+        let mut contents = format!(
+            "{game_name} Hours\nTotal Seconds: {}s\nTotal Hours: {:.1}hrs\nHours Past Two Weeks: {:.1}hrs\n",
+            self.total_seconds,
+            self.total_hours(),
+            self.past_two_hours()
+        );
+
+        for (window, seconds) in &self.windows {
+            contents.push_str(&format!(
+                "Hours {}: {:.1}hrs\n",
+                window.label(),
+                *seconds as f64 / 3600_f64
+            ));
+        }
+
+        contents.push_str(&format!("Idle Hours: {:.1}hrs\n", self.idle_hours()));
+        contents
+    }
+}
+
+/// Formats the summary as the contents of the Rocket League `hours.txt`.
+impl Display for HoursSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.contents("Rocket League"))
+    }
+}
+
+/// This function calculates the [`HoursSummary`] of the sessions in the `data_dir`, without writing `hours.txt`.
+/// The windows are read from `config.toml`.
+///
+/// # Errors
+/// Returns an error if the schema or the session store could not be read.
+pub fn summarise(data_dir: &DataDir) -> TrackerResult<HoursSummary> {
+    let config = Config::load().unwrap_or_else(|e| {
+        warn!("could not load the configuration, only the past two weeks are shown: {e}");
+        Config::default()
+    });
+
+    summarise_windows(data_dir, &config.windows)
+}
+
+/// This function calculates the [`HoursSummary`] of the sessions in the `data_dir` with the `windows`, without
+/// writing `hours.txt`.
+///
+/// # Errors
+/// Returns an error if the schema or the session store could not be read.
+pub fn summarise_windows(data_dir: &DataDir, windows: &[Window]) -> TrackerResult<HoursSummary> {
+    let unrecorded_seconds = Schema::load(&data_dir.schema_file())?
+        .map(|schema| schema.legacy_unrecorded_seconds)
+        .unwrap_or(0);
+
+    let store = open_store(data_dir)?;
+    let summary = HoursSummary::from_store(
+        store.as_ref(),
+        unrecorded_seconds,
+        timezone::current().today(),
+        windows,
+    )?;
+
+    Ok(summary)
+}
+
+/// Returns the [`ParseMode`] set by `parsing` in `config.toml`.
+fn parse_mode() -> ParseMode {
+    Config::load()
+        .map(|config| config.parsing)
+        .unwrap_or_default()
+}
+
+/// The result of [`recalculate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecalculationReport {
+    /// The total seconds and hours in `hours.txt` before it was rebuilt, or [`None`] if the file did not exist.
+    pub previous: Option<(u64, f32)>,
+    /// The summary which was written to `hours.txt`.
+    pub summary: HoursSummary,
+}
+
+impl RecalculationReport {
+    /// Returns the seconds which were added to (or, if negative, removed from) the total in `hours.txt`.
+    pub fn seconds_corrected(&self) -> i64 {
+        match self.previous {
+            Some((seconds, _)) => self.summary.total_seconds as i64 - seconds as i64,
+            None => 0,
+        }
+    }
+
+    /// Returns `true` if the total seconds or the total hours in `hours.txt` did not match the session history.
+    pub fn has_discrepancy(&self) -> bool {
+        match self.previous {
+            Some((seconds, hours)) => {
+                seconds != self.summary.total_seconds
+                    || format!("{hours:.1}") != format!("{:.1}", self.summary.total_hours())
+            }
+            None => false,
+        }
+    }
+}
+
+/// This function rebuilds the Rocket League `hours.txt` in the `data_dir` from the session history, and returns a
+/// [`RecalculationReport`] which contains the totals before and after.
+///
+/// # Errors
+/// Returns an error if the session store could not be read, or `hours.txt` could not be written.
+pub fn recalculate(data_dir: &DataDir) -> TrackerResult<RecalculationReport> {
+    recalculate_game(data_dir, &Game::default())
+}
+
+/// This function rebuilds the `hours.txt` of the `game` from its session history, where `data_dir` is the
+/// data directory which contains the folder of the game.
+///
+/// # Errors
+/// Returns an error if the session store could not be read, or `hours.txt` could not be written.
+pub fn recalculate_game(data_dir: &DataDir, game: &Game) -> TrackerResult<RecalculationReport> {
+    info!("Recalculate Hours: START ({})\n", game.name);
+
+    let data_dir = &game.data_dir(data_dir);
+    fs::create_dir_all(data_dir.root())?;
+
+    let previous = match fs::read_to_string(data_dir.hours_file()) {
+        Ok(contents) => match parse_hours(&contents, parse_mode()) {
+            Ok(parsed) => {
+                for error in &parsed.skipped {
+                    warn!("skipped an invalid line of hours.txt: {error}");
+                }
+
+                Some((parsed.value.seconds, parsed.value.hours))
+            }
+            Err(e) => {
+                warn!("could not read the previous totals in hours.txt: {e}");
+                None
+            }
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let summary = summarise(data_dir)?;
+
+    yellow_ln_bold!("Writing to hours.txt...");
+    write_atomic(&data_dir.hours_file(), summary.contents(&game.name))?;
+    green_ln_bold!("Successful!\n");
+
+    let report = RecalculationReport { previous, summary };
+
+    if report.has_discrepancy() {
+        warn!(
+            "hours.txt of {} did not match the session history and was corrected by {}s",
+            game.name,
+            report.seconds_corrected()
+        );
+    }
+
+    info!("Recalculate Hours: FINISHED\n");
+    Ok(report)
+}
@@ -0,0 +1,371 @@
+//! This module contains the [`IdleDetector`] trait, which decides if the player is away from the game.
+//!
+//! While the player is idle, the session clock is paused and the idle span is recorded in the session, so leaving the
+//! game open in the menu does not inflate the hours. The player is idle once any of the enabled detectors reports
+//! that they have been idle for at least [`IdleConfig::timeout_minutes`]:
+//! - [`InputIdle`] measures the time since the last keyboard or mouse input. This uses `GetLastInputInfo` on Windows
+//!   and the X11 screen saver extension on Linux, and never reports idle time where neither is available. Neither
+//!   sees controller input, so a player who only uses a controller would be idle after the timeout. This detector is
+//!   off by default for that reason, and is meant for players who play with a keyboard and mouse.
+//! - [`FocusIdle`] measures the time since the game window was minimized or lost focus. This is only supported on
+//!   Windows.
+//! - [`CpuIdle`] measures the time since the CPU usage of the game process fell below
+//!   [`IdleConfig::cpu_threshold`].
+//!
+//! The detectors are configured in the `[idle]` table of `config.toml`:
+//!
+//! ```toml
+//! [idle]
+//! enabled = true
+//! timeout_minutes = 10
+//! input = false
+//! focus = true
+//! cpu = true
+//! cpu_threshold = 1.0
+//! ```
+use crate::{games::Game, process_watcher::process_info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+/// The settings of the idle detection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    /// Whether the session clock is paused while the player is idle.
+    pub enabled: bool,
+    /// The minutes without activity after which the player is idle.
+    pub timeout_minutes: u64,
+    /// Whether the time since the last keyboard or mouse input is checked. Controller input is not seen, so this is
+    /// off by default.
+    pub input: bool,
+    /// Whether the game window being minimized or unfocused is checked.
+    pub focus: bool,
+    /// Whether the CPU usage of the game process is checked.
+    pub cpu: bool,
+    /// The CPU usage in percent below which the game process is idle.
+    pub cpu_threshold: f32,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        IdleConfig {
+            enabled: true,
+            timeout_minutes: 10,
+            input: false,
+            focus: true,
+            cpu: true,
+            cpu_threshold: 1.0,
+        }
+    }
+}
+
+impl IdleConfig {
+    /// Returns the time without activity after which the player is idle, or [`Duration::ZERO`] if the idle
+    /// detection is disabled.
+    pub fn timeout(&self) -> Duration {
+        if self.enabled {
+            Duration::from_secs(self.timeout_minutes * 60)
+        } else {
+            Duration::ZERO
+        }
+    }
+}
+
+/// Measures how long the player has been idle.
+pub trait IdleDetector: Send {
+    /// Returns how long the player of `game` has been idle, or [`Duration::ZERO`] if they are active.
+    fn idle_time(&mut self, game: &Game) -> Duration;
+}
+
+/// Returns the [`IdleDetector`] used by the program, which combines the detectors enabled in the `config`.
+pub fn default_detector(config: &IdleConfig) -> Box<dyn IdleDetector> {
+    let mut detectors: Vec<Box<dyn IdleDetector>> = Vec::new();
+
+    if config.enabled {
+        if config.input {
+            detectors.push(Box::new(InputIdle::default()));
+        }
+        if config.focus {
+            detectors.push(Box::new(FocusIdle::default()));
+        }
+        if config.cpu {
+            detectors.push(Box::new(CpuIdle::new(config.cpu_threshold)));
+        }
+    }
+
+    Box::new(AnyIdle(detectors))
+}
+
+/// A detector which combines several detectors, and reports the longest idle time of any of them.
+#[derive(Default)]
+pub struct AnyIdle(pub Vec<Box<dyn IdleDetector>>);
+
+impl IdleDetector for AnyIdle {
+    fn idle_time(&mut self, game: &Game) -> Duration {
+        self.0
+            .iter_mut()
+            .map(|detector| detector.idle_time(game))
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Measures how long a condition has held, for the detectors which can only observe the current state.
+#[derive(Debug, Default, Clone, Copy)]
+struct IdleSince(Option<Instant>);
+
+impl IdleSince {
+    /// Updates whether the condition holds, and returns how long it has held.
+    fn update(&mut self, idle: bool) -> Duration {
+        if !idle {
+            self.0 = None;
+            return Duration::ZERO;
+        }
+
+        self.0.get_or_insert_with(Instant::now).elapsed()
+    }
+}
+
+/// A detector which measures the time since the last keyboard or mouse input of the user. The connection to the
+/// display is kept between checks.
+#[derive(Debug, Default)]
+pub struct InputIdle {
+    source: platform::InputSource,
+}
+
+impl IdleDetector for InputIdle {
+    fn idle_time(&mut self, _game: &Game) -> Duration {
+        self.source.idle_time().unwrap_or_default()
+    }
+}
+
+/// A detector which measures the time since the game window was minimized or lost focus.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FocusIdle {
+    since: IdleSince,
+}
+
+impl IdleDetector for FocusIdle {
+    fn idle_time(&mut self, game: &Game) -> Duration {
+        let unfocused = platform::foreground_process()
+            .is_some_and(|process| !process.is_some_and(|process| game.rules.matches(&process)));
+
+        self.since.update(unfocused)
+    }
+}
+
+/// A detector which measures the time since the CPU usage of the game process fell below a threshold.
+#[derive(Debug, Default)]
+pub struct CpuIdle {
+    system: System,
+    threshold: f32,
+    since: IdleSince,
+}
+
+impl CpuIdle {
+    /// Creates a new detector where the game is idle below `threshold` percent CPU usage.
+    pub fn new(threshold: f32) -> CpuIdle {
+        CpuIdle {
+            system: System::new(),
+            threshold,
+            since: IdleSince::default(),
+        }
+    }
+}
+
+impl IdleDetector for CpuIdle {
+    fn idle_time(&mut self, game: &Game) -> Duration {
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_cpu()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+
+        let usage: Option<f32> = self
+            .system
+            .processes()
+            .values()
+            .filter(|process| game.rules.matches(&process_info(process)))
+            .map(|process| process.cpu_usage())
+            .reduce(|a, b| a + b);
+
+        // The game is not idle if its process was not found
+        self.since
+            .update(usage.is_some_and(|usage| usage < self.threshold))
+    }
+}
+
+/// A detector which returns scripted idle times, for testing the tracker without a real game.
+///
+/// Each check returns the next idle time, and once they run out, every check returns [`Duration::ZERO`].
+#[derive(Debug, Default, Clone)]
+pub struct FakeIdle {
+    idle_times: VecDeque<Duration>,
+}
+
+impl FakeIdle {
+    /// Creates a new detector which returns the `idle_times` in seconds in order.
+    pub fn new(idle_times: impl IntoIterator<Item = u64>) -> FakeIdle {
+        FakeIdle {
+            idle_times: idle_times.into_iter().map(Duration::from_secs).collect(),
+        }
+    }
+}
+
+impl IdleDetector for FakeIdle {
+    fn idle_time(&mut self, _game: &Game) -> Duration {
+        self.idle_times.pop_front().unwrap_or_default()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use crate::{detection::ProcessInfo, process_watcher::process_info};
+    use std::{mem, time::Duration};
+    use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+    use windows_sys::Win32::{
+        System::SystemInformation::GetTickCount,
+        UI::{
+            Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+            WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId, IsIconic},
+        },
+    };
+
+    /// Reads the time since the last input from `GetLastInputInfo`, which needs no connection.
+    #[derive(Debug, Default)]
+    pub struct InputSource;
+
+    impl InputSource {
+        /// Returns the time since the last input.
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            input_idle_time()
+        }
+    }
+
+    /// Returns the time since the last input, from `GetLastInputInfo`.
+    fn input_idle_time() -> Option<Duration> {
+        let mut info = LASTINPUTINFO {
+            cbSize: mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+
+        // SAFETY: `info` is a valid LASTINPUTINFO with its size set
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+
+        // SAFETY: GetTickCount has no preconditions
+        let now = unsafe { GetTickCount() };
+
+        Some(Duration::from_millis(now.wrapping_sub(info.dwTime) as u64))
+    }
+
+    /// Returns the process of the focused window, [`Some(None)`] if no window is focused or it is minimized,
+    /// or [`None`] if the focused window could not be checked.
+    pub fn foreground_process() -> Option<Option<ProcessInfo>> {
+        // SAFETY: GetForegroundWindow has no preconditions
+        let window = unsafe { GetForegroundWindow() };
+
+        // SAFETY: `window` is checked for null before it is used
+        if window.is_null() || unsafe { IsIconic(window) } != 0 {
+            return Some(None);
+        }
+
+        let mut pid: u32 = 0;
+
+        // SAFETY: `window` is a valid window handle and `pid` is a valid pointer
+        unsafe { GetWindowThreadProcessId(window, &mut pid) };
+
+        if pid == 0 {
+            return None;
+        }
+
+        let pid = Pid::from_u32(pid);
+        let mut system = System::new();
+
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing()
+                .with_exe(UpdateKind::Always)
+                .with_cmd(UpdateKind::Always),
+        );
+
+        Some(Some(process_info(system.process(pid)?)))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use crate::detection::ProcessInfo;
+    use std::time::Duration;
+    use x11rb::{
+        connection::Connection,
+        protocol::{screensaver::ConnectionExt, xproto::Window},
+        rust_connection::RustConnection,
+    };
+
+    /// Reads the time since the last input from the X11 screen saver extension. The connection to the display is
+    /// opened on the first check, and opened again after a check failed.
+    #[derive(Debug, Default)]
+    pub struct InputSource {
+        connection: Option<(RustConnection, Window)>,
+    }
+
+    impl InputSource {
+        /// Returns the time since the last input, or [`None`] if the display could not be reached.
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            if self.connection.is_none() {
+                let (conn, screen) = x11rb::connect(None).ok()?;
+                let root = conn.setup().roots.get(screen)?.root;
+                self.connection = Some((conn, root));
+            }
+
+            let (conn, root) = self.connection.as_ref()?;
+            let info = conn
+                .screensaver_query_info(*root)
+                .ok()
+                .and_then(|cookie| cookie.reply().ok());
+
+            if info.is_none() {
+                self.connection = None;
+            }
+
+            Some(Duration::from_millis(info?.ms_since_user_input as u64))
+        }
+    }
+
+    /// The focused window cannot be checked on Linux.
+    pub fn foreground_process() -> Option<Option<ProcessInfo>> {
+        None
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+mod platform {
+    use crate::detection::ProcessInfo;
+    use std::time::Duration;
+
+    /// The time since the last input cannot be checked on this platform.
+    #[derive(Debug, Default)]
+    pub struct InputSource;
+
+    impl InputSource {
+        /// Returns [`None`], since there is no input to check.
+        pub fn idle_time(&mut self) -> Option<Duration> {
+            None
+        }
+    }
+
+    /// The focused window cannot be checked on this platform.
+    pub fn foreground_process() -> Option<Option<ProcessInfo>> {
+        None
+    }
+}
@@ -20,8 +20,8 @@
 //! module, which is the built in updater for the binary which retrieves the update from the GitHub
 //! repository.
 //!
-//! The website functionality takes adavantage of the [`build_html`] library, which allows us
-//! to generate the Html for the website, alongside the [`webbrowser`] library, which allows us
+//! The website functionality takes adavantage of the [`minijinja`] library, which renders the Html
+//! of the website from the [`templates`], alongside the [`webbrowser`] library, which allows us
 //! to open the website in a browser.
 //!
 //! The update module only operates when using the installed version of the program which can be found in the
@@ -32,20 +32,21 @@
 //!
 //! ### Use Case
 //! Within the [`website_files`] module, there is a public function [`website_files::generate_website_files`],
-//! which writes the files for the website in the website directory in `RlHoursFolder`. This function accepts a
+//! which writes the files for the website in the website directory of the [`data_dir::DataDir`]. This function accepts a
 //! [`bool`] value, which determines whether the option to open the website in a browser should appear when this
 //! function is called.
 //!
-//! ```
+//! ```no_run
 //! use rl_hours_tracker::website_files;
 //!
 //! // This will generate the website files and prompt you with the option to open the
 //! // webstie in a browser.
-//! website_files::generate_website_files(true);
+//! website_files::generate_website_files(true)?;
 //!
 //! // This will also generate the website but will not prompt the user to open the website
 //! // in a browser.
-//! website_files::generate_website_files(false);
+//! website_files::generate_website_files(false)?;
+//! # Ok::<(), rl_hours_tracker::error::TrackerError>(())
 //! ```
 //!
 //! The [`update`] module has two public asynchronous functions available: [`update::check_for_update`] and [`update::update`].
@@ -54,7 +55,7 @@
 //! updating the program by sending a HTTP request to the repository to retrieve the update zip from the latest release, and unzipping the
 //! zip files contents to replace the old program files with the newest version.
 //!
-//! ```
+//! ```no_run
 //! use rl_hours_tracker::update;
 //! use tokio::runtime::Runtime;
 //!
@@ -63,23 +64,30 @@
 //!
 //! // This runs our asynchronous function which checks for an update
 //! rt.block_on(update::check_for_update())?;
+//! # Ok::<(), rl_hours_tracker::error::TrackerError>(())
 //! ```
 //!
+//! The functions of the library return the [`error::TrackerError`] when they fail, and never exit the process
+//! themselves, so the library can be embedded in other programs.
+//!
 //! The [`update::check_for_update`] function does use the [`update::update`] function when it finds that there is a new release on the GitHub, however
 //! the update function can be used by itself in a different context if needed.
 //!
-//! ```
+//! ```no_run
 //! use rl_hours_tracker::update;
 //! use tokio::runtime::Runtime;
 //!
 //! // This creates a tokio runtime instance for running our function
 //! let rt = Runtime::new().unwrap();
 //!
-//! // This runs our asynchronous function which updates the program
-//! rt.block_on(update::update())?;
+//! // This runs our asynchronous functions which find the latest version and update the program to it
+//! let version = rt.block_on(update::latest_version())?;
+//! rt.block_on(update::update(&version))?;
+//! # Ok::<(), rl_hours_tracker::error::TrackerError>(())
 //! ```
-use chrono::Local;
-use colour::{black_bold, blue_ln_bold, cyan, green, green_ln_bold, red, white, yellow_ln_bold};
+use colour::{
+    black_bold, blue_ln_bold, cyan, green, green_ln_bold, red, white, yellow, yellow_ln_bold,
+};
 use log::{error, info, trace, warn, LevelFilter};
 use log4rs::{
     append::{console::ConsoleAppender, file::FileAppender},
@@ -88,90 +96,153 @@ use log4rs::{
     Config, Handle,
 };
 use std::{
-    error::Error,
-    fmt::Display,
-    fs::{self, File},
-    io::{self, Read, Write},
-    process,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc, Mutex,
-    },
+    borrow::Cow,
+    collections::HashSet,
+    fs,
+    io::{self, BufRead, Write},
+    sync::atomic::AtomicBool,
     thread,
-    time::{Duration, SystemTime},
+    time::Duration,
 };
-use stopwatch::Stopwatch;
-use sysinfo::System;
 use tokio::runtime::Runtime;
 use winit::event_loop::EventLoopProxy;
 
-use crate::{calculate_past_two::calculate_past_two, winit_tray_icon::UserEvent};
+use crate::{
+    checkpoint::Checkpoint,
+    config::Prompt,
+    daemon::is_daemon,
+    data_dir::DataDir,
+    error::{TrackerError, TrackerResult},
+    games::Game,
+    hours::{summarise, HoursSummary},
+    server::{LiveSubscriber, ServerState},
+    session::read_sessions_from,
+    state::TrackerState,
+    store::open_store,
+    tracker::{Subscriber, Tracker, TrackerEvent, TrackerHandle},
+    winit_tray_icon::UserEvent,
+};
 
+pub mod atomic_file;
 pub mod calculate_past_two;
+pub mod charts;
+pub mod checkpoint;
+pub mod cli;
+pub mod config;
+pub mod daemon;
+pub mod data_dir;
+pub mod detection;
+pub mod error;
+pub mod games;
+pub mod history;
+pub mod hours;
+pub mod idle;
+pub mod migrate;
+pub mod parser;
+pub mod process_watcher;
+pub mod recovery;
+pub mod server;
+pub mod session;
+pub mod state;
+pub mod store;
+pub mod templates;
 #[cfg(test)]
 mod tests;
+pub mod timezone;
+pub mod tracker;
 pub mod update;
 pub mod website_files;
 pub mod winit_tray_icon;
 
+pub use error::PastTwoError;
+
 /// Type alias for Results which only return [`std::io::Error`] as its error variant.
 pub type IoResult<T> = Result<T, io::Error>;
 
 /// Contains the relevant data for running the program
 struct ProgramRunVars {
-    proxy: EventLoopProxy<UserEvent>,
-    process_name: String,
+    proxy: Option<EventLoopProxy<UserEvent>>,
+    tracker: Tracker,
+    waiting: String,
     is_waiting: bool,
     option: String,
-    currently_tracking: Arc<Mutex<AtomicBool>>,
-    stop_tracker: Arc<Mutex<AtomicBool>>,
+    end_after_session: Prompt,
 }
 
 impl ProgramRunVars {
-    fn new(
-        proxy: EventLoopProxy<UserEvent>,
-        stop_tracker: Arc<Mutex<AtomicBool>>,
-        currently_tracking: Arc<Mutex<AtomicBool>>,
-    ) -> Self {
+    fn new(proxy: Option<EventLoopProxy<UserEvent>>, handle: TrackerHandle) -> Self {
+        let config = config::Config::load().unwrap_or_else(|e| {
+            warn!("could not load the configuration, using the default rules: {e}");
+            config::Config::default()
+        });
+
+        let end_after_session = config.end_after_session;
+        let server = config.server.clone();
+        let windows = config.windows.clone();
+        let live = LiveSubscriber::new();
+        let status = live.status();
+
+        let mut builder = Tracker::builder()
+            .config(config)
+            .subscribe(ConsoleSubscriber)
+            .handle(handle);
+
+        if server.enabled {
+            builder = builder.subscribe(live);
+        }
+
+        let tracker = builder.build();
+        let waiting = waiting_message(tracker.games());
+
+        // Serve the dashboard until the tracker shuts down
+        if server.enabled {
+            let state = ServerState::new(
+                tracker.data_dir().clone(),
+                tracker.games().to_vec(),
+                windows,
+                status,
+            );
+
+            if let Err(e) = server::spawn(&server, state, tracker.handle()) {
+                error!("could not start the dashboard server: {e}");
+            }
+        }
+
         Self {
-            process_name: String::from("RocketLeague.exe"),
+            tracker,
+            waiting,
             is_waiting: false,
             option: String::with_capacity(1),
             proxy,
-            stop_tracker,
-            currently_tracking,
+            end_after_session,
         }
     }
-}
 
-/// Custom error for [`calculate_past_two`] function
-#[derive(Debug, Clone)]
-pub struct PastTwoError;
-
-impl Display for PastTwoError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "next closest date to the date two weeks ago could not be found."
-        )
+    /// Tells the event loop to quit, if there is one.
+    fn quit(&self) {
+        if let Some(proxy) = &self.proxy {
+            proxy
+                .send_event(UserEvent::QuitApp(AtomicBool::new(true)))
+                .unwrap_or_else(|_| error!("event loop already closed"));
+        }
     }
 }
 
-impl Error for PastTwoError {}
-
 /// Initializes logging configuration for the program
 ///
-/// Logs are stored in `C:/RLHoursFolder/logs`
-pub fn initialize_logging() -> Result<Handle, Box<dyn Error>> {
+/// Logs are stored in the `logs` folder of the [`DataDir`]
+pub fn initialize_logging() -> TrackerResult<Handle> {
+    let logs_dir = DataDir::current().logs_dir();
+
     // Create appenders
     let stdout = ConsoleAppender::builder().build();
-    let general_logs = FileAppender::builder()
-        .build("C:/RLHoursFolder/logs/general_$TIME{%Y-%m-%d_%H-%M-%S}.log")?;
-    let wti_logs = FileAppender::builder()
-        .build("C:/RLHoursFolder/logs/tray-icon_$TIME{%Y-%m-%d_%H-%M-%S}.log")?;
+    let general_logs =
+        FileAppender::builder().build(logs_dir.join("general_$TIME{%Y-%m-%d_%H-%M-%S}.log"))?;
+    let wti_logs =
+        FileAppender::builder().build(logs_dir.join("tray-icon_$TIME{%Y-%m-%d_%H-%M-%S}.log"))?;
     let requests = FileAppender::builder()
         .encoder(Box::new(PatternEncoder::new("{d} - {m}{n}")))
-        .build("C:/RLHoursFolder/logs/requests.log")?;
+        .build(logs_dir.join("requests.log"))?;
 
     // Create loggers
     let rl_hours_tracker_logger = Logger::builder()
@@ -208,50 +279,197 @@ pub fn initialize_logging() -> Result<Handle, Box<dyn Error>> {
     let config = Config::builder()
         .appenders(appenders)
         .loggers(loggers)
-        .build(Root::builder().appender("stdout").build(LevelFilter::Warn))?;
+        .build(Root::builder().appender("stdout").build(LevelFilter::Warn))
+        .map_err(|e| TrackerError::Logging(e.to_string()))?;
 
     // Initialize logging configuration
-    let handle = log4rs::init_config(config)?;
+    let handle = log4rs::init_config(config).map_err(|e| TrackerError::Logging(e.to_string()))?;
 
     Ok(handle)
 }
 
-/// This runs the [`update::check_for_update`] function
-pub fn run_self_update() -> Result<(), Box<dyn Error>> {
+/// This runs the [`update::check_for_update`] function, and returns `true` if an update was installed, in which case
+/// the program should close so the new version can start.
+///
+/// # Errors
+/// Returns an error if the tokio runtime could not be created or the update failed.
+pub fn run_self_update() -> TrackerResult<bool> {
     let rt = Runtime::new()?;
 
-    rt.block_on(update::check_for_update())?;
+    rt.block_on(update::check_for_update())
+}
+
+/// This returns the version number of the latest release if it is newer than the running program, or [`None`]
+/// if the program is up to date. Unlike [`run_self_update`], nothing is installed.
+///
+/// # Errors
+/// Returns an error if the tokio runtime could not be created or the request to GitHub failed.
+pub fn check_latest_version() -> TrackerResult<Option<String>> {
+    let rt = Runtime::new()?;
+
+    let version = rt.block_on(update::latest_version())?;
+
+    if version == env!("CARGO_PKG_VERSION") {
+        Ok(None)
+    } else {
+        Ok(Some(version))
+    }
+}
+
+/// The state of a game, returned by [`status`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameStatus {
+    /// The game.
+    pub game: Game,
+    /// The hours of the game, calculated from its sessions.
+    pub summary: HoursSummary,
+    /// The last checkpoint of the session in progress, if the game is being tracked.
+    pub session: Option<Checkpoint>,
+}
+
+/// This function returns the [`GameStatus`] of every game in `games` which has any data in the `data_dir`.
+/// Rocket League is always included.
+///
+/// # Errors
+/// Returns an error if the sessions or the checkpoint of a game could not be read.
+pub fn status(data_dir: &DataDir, games: &[Game]) -> TrackerResult<Vec<GameStatus>> {
+    let mut statuses = Vec::new();
+
+    for game in games {
+        let game_dir = game.data_dir(data_dir);
+
+        if !game.is_rocket_league() && !game_dir.root().exists() {
+            continue;
+        }
+
+        statuses.push(GameStatus {
+            game: game.clone(),
+            summary: summarise(&game_dir)?,
+            session: Checkpoint::load(&game_dir.checkpoint_file())?,
+        });
+    }
+
+    Ok(statuses)
+}
 
-    Ok(())
+/// The formats which the sessions can be exported in by [`export_sessions`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON session per line, in the same format as `sessions.jsonl`.
+    #[default]
+    Jsonl,
+    /// Comma separated values with a header row. The idle spans are summed into a single column.
+    Csv,
 }
 
-/// This function runs the program
-pub fn run(
-    proxy: EventLoopProxy<UserEvent>,
-    stop_tracker: Arc<Mutex<AtomicBool>>,
-    currently_tracking: Arc<Mutex<AtomicBool>>,
-) {
-    let mut program = ProgramRunVars::new(proxy, stop_tracker, currently_tracking);
+/// This function writes every session in the `data_dir` to `writer` in the `format`, oldest first, and returns
+/// the number of sessions written.
+///
+/// # Errors
+/// Returns an error if the session store could not be read, or `writer` failed.
+pub fn export_sessions(
+    data_dir: &DataDir,
+    format: ExportFormat,
+    writer: &mut dyn Write,
+) -> TrackerResult<usize> {
+    let sessions = open_store(data_dir)?.sessions()?;
+
+    if format == ExportFormat::Csv {
+        writeln!(
+            writer,
+            "start,end,duration,idle_seconds,end_reason,app_version"
+        )?;
+    }
+
+    for session in &sessions {
+        match format {
+            ExportFormat::Jsonl => writeln!(writer, "{}", serde_json::to_string(session)?)?,
+            ExportFormat::Csv => writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                session.start.to_rfc3339(),
+                session.end.to_rfc3339(),
+                session.duration,
+                session.idle_seconds(),
+                csv_field(
+                    serde_json::to_value(session.end_reason)?
+                        .as_str()
+                        .unwrap_or_default()
+                ),
+                csv_field(&session.app_version)
+            )?,
+        }
+    }
+
+    Ok(sessions.len())
+}
+
+/// Quotes a CSV field which contains a comma, a quote or a line break, doubling the quotes inside it.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\r', '\n']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// This function adds the sessions read from `reader`, which contains one JSON session per line as written by
+/// [`export_sessions`], to the `data_dir`. Sessions which start and end at the same time as a recorded session
+/// are skipped, so the same file can be imported more than once. The number of sessions added is returned.
+///
+/// The `hours.txt` file is not updated, which is done by [`recalculate_game`].
+///
+/// # Errors
+/// Returns an error if a line is not a valid session, or the session store could not be read or written.
+pub fn import_sessions<R: BufRead>(data_dir: &DataDir, reader: R) -> TrackerResult<usize> {
+    let imported = read_sessions_from(reader)?;
+
+    fs::create_dir_all(data_dir.root())?;
+
+    let mut store = open_store(data_dir)?;
+    let mut recorded: HashSet<_> = store
+        .sessions()?
+        .into_iter()
+        .map(|session| (session.start, session.end))
+        .collect();
+    let mut added = 0;
+
+    for session in imported {
+        if recorded.insert((session.start, session.end)) {
+            store.add(&session)?;
+            added += 1;
+        }
+    }
+
+    Ok(added)
+}
+
+/// This function runs the program. The `proxy` of the tray icon event loop is [`None`] when the program runs
+/// without a tray icon. The program returns once the `tracker` is shut down, or the user chose to end it.
+pub fn run(proxy: Option<EventLoopProxy<UserEvent>>, tracker: TrackerHandle) {
+    let mut program = ProgramRunVars::new(proxy, tracker);
 
     // Run the main loop
     run_main_loop(&mut program);
 }
 
-/// This function creates the directories for the program. It creates a local [`Vec<Result>`]
-/// which stores [`fs::create_dir`] results.
+/// This function creates the directories for the program inside the [`DataDir`]. It creates a local [`Vec<Result>`]
+/// which stores [`fs::create_dir`] results. The root of the data directory is created along with any missing parents.
 ///
 /// This function then returns a [`Vec<Result>`] which stores any errors that may have occurred
 ///
 /// # Errors
 /// This function stores an [`io::Error`] in the output Vector if there was any issue creating a folder.
 pub fn create_directory() -> Vec<IoResult<()>> {
+    let data_dir = DataDir::current();
+
     // Create the folder directories for the program
-    let folder = fs::create_dir("C:\\RLHoursFolder");
-    let website_folder = fs::create_dir("C:\\RLHoursFolder\\website");
-    let website_pages = fs::create_dir("C:\\RLHoursFolder\\website\\pages");
-    let website_css = fs::create_dir("C:\\RLHoursFolder\\website\\css");
-    let website_js = fs::create_dir("C:\\RLHoursFolder\\website\\js");
-    let website_images = fs::create_dir("C:\\RLHoursFolder\\website\\images");
+    let folder = fs::create_dir_all(data_dir.root());
+    let website_folder = fs::create_dir(data_dir.website_dir());
+    let website_pages = fs::create_dir(data_dir.website_pages_dir());
+    let website_css = fs::create_dir(data_dir.website_css_dir());
+    let website_js = fs::create_dir(data_dir.website_js_dir());
+    let website_images = fs::create_dir(data_dir.website_images_dir());
 
     // Store the folder results in Vector
     let folder_vec: Vec<IoResult<()>> = vec![
@@ -269,24 +487,64 @@ pub fn create_directory() -> Vec<IoResult<()>> {
     result
 }
 
-/// This function runs the main loop of the program. This checks if the `RocketLeague.exe` process is running and
-/// runs the [`record_hours`] function if it is running, otherwise it will continue to wait for the process to start.
+/// This function runs the main loop of the program. This checks if the process of any registered [`Game`] is running
+/// through the [`Tracker`] and
+/// records its session with [`Tracker::track`] if it is running, otherwise it will continue to wait for the process to start.
 fn run_main_loop(program: &mut ProgramRunVars) {
     loop {
-        // Check if the process is running
-        if check_for_process(&program.process_name) {
-            record_hours(
-                &program.process_name,
-                program.stop_tracker.clone(),
-                program.currently_tracking.clone(),
-            );
+        // Quit if a signal or the tray asked the program to shut down
+        if program.tracker.handle().is_shutdown() {
+            info!("Shutting down");
+            program.quit();
+            break;
+        }
 
-            // Generate the website files
-            website_files::generate_website_files(true)
+        // Check if the process of a game is running
+        if let Some(game) = program.tracker.detect() {
+            if let Err(e) = program.tracker.track(&game) {
+                error!("error recording the session, it will be recovered on the next launch: {e}");
+            }
+
+            let shutdown = program.tracker.handle().is_shutdown();
+
+            // Generate the website files, without asking to open them when shutting down
+            website_files::generate_website_files(!shutdown)
                 .unwrap_or_else(|e| warn!("failed to generate website files: {e}"));
 
             program.is_waiting = false;
 
+            // The session was stopped to shut down
+            if shutdown {
+                yellow_ln_bold!("Goodbye!");
+                program.quit();
+                break;
+            }
+
+            // A session which was stopped is not started again until the game is closed, and the daemon keeps
+            // waiting rather than ending or starting a new session of the running game
+            if program.tracker.handle().state() == TrackerState::WaitingForExit {
+                info!("{} was stopped, waiting for it to be closed", game.name);
+                yellow_ln_bold!(
+                    "Tracking stopped, {} is tracked again once it is closed and started again",
+                    game.name
+                );
+
+                if is_daemon() {
+                    continue;
+                }
+            }
+
+            // Ask whether to end the program, unless it is answered by the configuration
+            match program.end_after_session.decide(false) {
+                Some(true) => {
+                    yellow_ln_bold!("Goodbye!");
+                    program.quit();
+                    break;
+                }
+                Some(false) => continue,
+                None => (),
+            }
+
             print!("End program (");
             green!("y");
             print!(" / ");
@@ -305,42 +563,41 @@ fn run_main_loop(program: &mut ProgramRunVars) {
                     .flush()
                     .expect("could not flush the output stream");
                 yellow_ln_bold!("Goodbye!");
-                program
-                    .proxy
-                    .send_event(UserEvent::QuitApp(AtomicBool::new(true)))
-                    .unwrap_or_else(|_| error!("event loop already closed"));
+                program.quit();
                 break;
             } else if program.option.trim() == "n" || program.option.trim() == "N" {
                 program.option = String::with_capacity(1);
                 continue;
             } else {
                 error!("Unexpected input! Ending program.");
-                program
-                    .proxy
-                    .send_event(UserEvent::QuitApp(AtomicBool::new(true)))
-                    .unwrap_or_else(|_| error!("event loop already closed"));
+                program.quit();
                 break;
             }
         } else {
-            // Print 'Waiting for Rocket League to start...' only once by changing the value of is_waiting to true
-            if !program.is_waiting {
-                green!("Waiting for Rocket League to start.\r");
+            // The waiting animation is left out of the daemon output
+            if is_daemon() {
+                thread::sleep(Duration::from_secs(2));
+            // Print 'Waiting for a game to start...' only once by changing the value of is_waiting to true
+            } else if !program.is_waiting {
+                let waiting = &program.waiting;
+
+                green!("{waiting}.\r");
                 io::stdout()
                     .flush()
                     .expect("could not flush the output stream");
                 thread::sleep(Duration::from_millis(500));
-                white!("Waiting for Rocket League to start..\r");
+                white!("{waiting}..\r");
                 io::stdout()
                     .flush()
                     .expect("could not flush the output stream");
                 thread::sleep(Duration::from_millis(500));
-                black_bold!("Waiting for Rocket League to start...\r");
+                black_bold!("{waiting}...\r");
                 io::stdout()
                     .flush()
                     .expect("could not flush the output stream");
                 thread::sleep(Duration::from_millis(500));
                 print!("{}[2K\r", 27 as char);
-                red!("Waiting for Rocket League to start\r");
+                red!("{waiting}\r");
                 io::stdout()
                     .flush()
                     .expect("could not flush the output stream");
@@ -350,366 +607,56 @@ fn run_main_loop(program: &mut ProgramRunVars) {
     }
 }
 
-/// This function takes in a reference string `process_name: &str` and starts a stopwatch
-/// which keeps track of the amount of seconds that pass whilst the process is running.
-/// The stopwatch is ended and the File operations are run at the end of the process.
-/// The date and elapsed time are stored in the `date.txt` file and the hours is stored in
-/// `hours.txt`
-fn record_hours(
-    process_name: &str,
-    stop_tracker: Arc<Mutex<AtomicBool>>,
-    currently_tracking: Arc<Mutex<AtomicBool>>,
-) {
-    let mut sw = Stopwatch::start_new();
-
-    blue_ln_bold!("\nRocket League is running\n");
-
-    currently_tracking
-        .try_lock()
-        .unwrap_or_else(|e| {
-            error!("error when attempting to access lock for currently_tracking: {e}");
-            panic!("could not access lock for currently_tracking");
-        })
-        .store(true, Ordering::SeqCst);
-
-    trace!(
-        "<< fn record_hours >> currently_tracking set to {} before live_stopwatch",
-        currently_tracking
-            .try_lock()
-            .unwrap()
-            .load(Ordering::Relaxed)
-    );
-    trace!(
-        "<< fn record_hours >> stop_tracker set to {} before live_stopwatch",
-        stop_tracker.try_lock().unwrap().load(Ordering::Relaxed)
-    );
-
-    // Start live stopwatch
-    live_stopwatch(process_name, stop_tracker.clone());
-
-    trace!(
-        "<< fn record_hours >> stop_tracker set to {} after live_stopwatch",
-        stop_tracker.try_lock().unwrap().load(Ordering::Relaxed)
-    );
-
-    currently_tracking
-        .try_lock()
-        .unwrap_or_else(|e| {
-            error!("error when attempting to access lock for currently_tracking: {e}");
-            panic!("could not access lock for currently_tracking");
-        })
-        .store(false, Ordering::SeqCst);
-
-    trace!(
-        "<< fn record_hours >> currently_tracking set to {} after live_stopwatch",
-        currently_tracking
-            .try_lock()
-            .unwrap()
-            .load(Ordering::Relaxed)
-    );
-
-    stop_tracker
-        .try_lock()
-        .unwrap_or_else(|e| {
-            error!("error when attempting to access lock for stop_tracking: {e}");
-            panic!("could not access lock for stop_tracking");
-        })
-        .store(false, Ordering::SeqCst);
-
-    // Stop the stopwatch
-    sw.stop();
-
-    info!("Record Hours: START\n");
-
-    let seconds: u64 = sw.elapsed_ms() as u64 / 1000;
-    let hours: f32 = (sw.elapsed_ms() as f32 / 1000_f32) / 3600_f32;
-
-    trace!("<< fn record_hours >> seconds: {seconds}, hours: {hours:.1}");
-
-    let hours_result = File::open("C:\\RLHoursFolder\\hours.txt");
-    let date_result = File::open("C:\\RLHoursFolder\\date.txt");
-
-    // Write date and seconds to date.txt
-    write_to_date(date_result, &seconds).unwrap_or_else(|e| {
-        error!("error writing to date.txt: {e}");
-        process::exit(1);
-    });
-
-    // Buffer which stores the hours in the past two weeks
-    let hours_buffer = calculate_past_two().unwrap_or_else(|e| {
-        warn!("failed to calculate past two: {e}");
-        0
-    });
-
-    trace!("<< fn record_hours >> hours_buffer is set to {hours_buffer}");
-
-    if hours_buffer != 0 {
-        let hours_past_two = hours_buffer as f32 / 3600_f32;
-
-        write_to_hours(hours_result, &seconds, &hours, &hours_past_two, &sw).unwrap_or_else(|e| {
-            error!("error writing to hours.txt: {e}");
-            process::exit(1);
-        });
-        info!("Record Hours: FINISHED\n")
-    } else {
-        warn!("past two returned zero seconds")
+/// Returns the text of the waiting animation, which names the game if only one [`Game`] is registered.
+fn waiting_message(games: &[Game]) -> String {
+    match games {
+        [game] => format!("Waiting for {} to start", game.name),
+        _ => String::from("Waiting for a game to start"),
     }
 }
 
-fn live_stopwatch(process_name: &str, stop_tracker: Arc<Mutex<AtomicBool>>) {
-    let mut timer_early = SystemTime::now();
-
-    let mut seconds: u8 = 0;
-    let mut minutes: u8 = 0;
-    let mut hours: u16 = 0;
-
-    while check_for_process(process_name)
-        && !stop_tracker
-            .try_lock()
-            .unwrap_or_else(|e| {
-                error!("error when attempting to access lock for stop_tracking: {e}");
-                panic!("could not access lock for stop_tracking");
-            })
-            .load(Ordering::SeqCst)
-    {
-        let timer_now = timer_early
-            .checked_add(Duration::from_millis(999))
-            .unwrap_or_else(|| {
-                error!("could not return system time");
-                SystemTime::now()
-            });
-
-        let delay = timer_now.duration_since(timer_early).unwrap_or_else(|e| {
-            warn!(
-                "system time is ahead of the timer. SystemTime difference: {:?}",
-                e.duration()
-            );
-            Duration::from_millis(1000)
-        });
-
-        // Check if current seconds are greater than or equal to 1 minute
-        if seconds == 59 {
-            seconds = 0;
-            minutes += 1;
-
-            // Check if current minutes are greater than or equal to 1 hour
-            if minutes == 60 {
-                minutes = 0;
-                hours += 1;
-            }
-        } else {
-            seconds += 1;
-        }
-        print!("{}[2K\r", 27 as char);
-
-        // Print the output for the timer
-        if hours < 10 && minutes < 10 && seconds < 10 {
-            cyan!("Time Elapsed: 0{}:0{}:0{}\r", hours, minutes, seconds);
-        } else if hours >= 10 {
-            if minutes < 10 && seconds < 10 {
-                cyan!("Time Elapsed: {}:0{}:0{}\r", hours, minutes, seconds);
-            } else if minutes < 10 && seconds >= 10 {
-                cyan!("Time Elapsed: {}:0{}:{}\r", hours, minutes, seconds);
-            } else if minutes >= 10 && seconds < 10 {
-                cyan!("Time Elapsed: {}:{}:0{}\r", hours, minutes, seconds);
-            } else {
-                cyan!("Time Elapsed: {}:{}:{}\r", hours, minutes, seconds);
-            }
-        } else if hours < 10 && minutes >= 10 && seconds < 10 {
-            cyan!("Time Elapsed: 0{}:{}:0{}\r", hours, minutes, seconds);
-        } else if hours < 10 && minutes < 10 && seconds >= 10 {
-            cyan!("Time Elapsed: 0{}:0{}:{}\r", hours, minutes, seconds);
-        } else {
-            cyan!("Time Elapsed: 0{}:{}:{}\r", hours, minutes, seconds);
-        }
-
-        // Flush the output
-        io::stdout()
-            .flush()
-            .unwrap_or_else(|_| warn!("could not flush output stream"));
-
-        thread::sleep(delay);
-
-        timer_early += Duration::from_millis(999)
-    }
-
-    trace!("<< fn live_stopwatch >> hours: {hours}, minutes: {minutes}, seconds: {seconds}");
-}
-
-/// This function takes the `contents: &str` parameter which contains the contents from the `hours.txt` file
-/// and returns a tuple of `(u64, f32)` which contains the seconds and hours from the file.
-fn retrieve_time(contents: &str) -> Result<(u64, f32), Box<dyn Error>> {
-    // Split the contents by newline character
-    let split_new_line: Vec<&str> = contents.split("\n").collect();
-
-    // Split the seconds and hours string references by whitspace
-    let split_whitspace_sec: Vec<&str> = split_new_line[1].split_whitespace().collect();
-    let split_whitespace_hrs: Vec<&str> = split_new_line[2].split_whitespace().collect();
-
-    // Split the seconds and hours string references by characters
-    let split_char_sec = split_whitspace_sec[2].chars();
-    let split_char_hrs = split_whitespace_hrs[2].chars();
-
-    let mut sec_vec: Vec<char> = vec![];
-    let mut hrs_vec: Vec<char> = vec![];
-
-    // Loop through Chars iterator to push only numeric characters to the seconds Vector
-    for num in split_char_sec {
-        if num.is_numeric() {
-            sec_vec.push(num);
-        }
-    }
-
-    trace!("<< fn retrieve_time >> seconds vector: {sec_vec:?}");
-
-    // Loop through the Chars iterator to push numeric characters (plus the period character for decimals) to the hours Vector
-    for num in split_char_hrs {
-        if num.is_numeric() || num == '.' {
-            hrs_vec.push(num);
-        }
-    }
-
-    trace!("<< fn retrieve_time >> hours vector: {hrs_vec:?}");
-
-    let seconds_str: String = sec_vec.iter().collect();
-    let hours_str: String = hrs_vec.iter().collect();
+/// Prints the [`TrackerEvent`]s of the tracker which is run by the binary to the console.
+struct ConsoleSubscriber;
+
+impl Subscriber for ConsoleSubscriber {
+    fn on_event(&mut self, event: &TrackerEvent) {
+        match event {
+            TrackerEvent::GameStarted { game, .. } => blue_ln_bold!("\n{} is running\n", game.name),
+            // The live timer is left out of the daemon output
+            TrackerEvent::Tick {
+                elapsed,
+                idle,
+                paused,
+                ..
+            } if !is_daemon() => {
+                let hours = elapsed / 3600;
+                let minutes = elapsed / 60 % 60;
+                let seconds = elapsed % 60;
 
-    let old_seconds: u64 = seconds_str.parse()?;
-    let old_hours: f32 = hours_str.parse()?;
-
-    trace!("<< fn retrieve_time >> old seconds: {old_seconds}, old_hours: {old_hours:.1}");
-
-    // Return a tuple of the old seconds and old hours
-    Ok((old_seconds, old_hours))
-}
-
-/// This function constructs a new [`String`] which will have the contents to write to `hours.txt` with new hours and seconds
-/// and returns it.
-fn return_new_hours(
-    contents: &str,
-    seconds: &u64,
-    hours: &f32,
-    past_two: &f32,
-) -> Result<String, Box<dyn Error>> {
-    yellow_ln_bold!("Getting old hours...");
-    // Retrieves the old hours and seconds from the contents String
-    let (old_seconds, old_hours) = retrieve_time(contents)?;
-
-    let added_seconds = old_seconds + *seconds;
-    let added_hours = old_hours + *hours;
-
-    trace!("<< fn return_new_hours >> added_seconds: {added_seconds}, added_hours: {added_hours:.1}");
-
-    Ok(format!(
-        "Rocket League Hours\nTotal Seconds: {}s\nTotal Hours: {:.1}hrs\nHours Past Two Weeks: {:.1}hrs\n",
-        added_seconds, added_hours, past_two
-    ))
-}
-
-/// This function writes the new contents to the `hours.txt` file. This includes the total `seconds`, `hours`, and `hours_past_two`.
-/// This function then returns a [`Result<()>`] when file operations were all successful.
-///
-/// # Errors
-/// This function returns an [`io::Error`] if any file operations failed.
-fn write_to_hours(
-    hours_result: IoResult<File>,
-    seconds: &u64,
-    hours: &f32,
-    hours_past_two: &f32,
-    sw: &Stopwatch,
-) -> Result<(), Box<dyn Error>> {
-    // Check if the file exists
-    if let Ok(mut file) = hours_result {
-        let mut contents = String::new();
-
-        // Attempt to read from the hours.txt file
-        file.read_to_string(&mut contents)?;
-
-        // Stores the new contents for the file as a String
-        let rl_hours_str = return_new_hours(&contents, seconds, hours, hours_past_two)?;
-
-        // Attempt to write to hours.txt
-        let mut truncated_file = File::create("C:\\RLHoursFolder\\hours.txt")?;
-
-        yellow_ln_bold!("Writing to hours.txt...");
-
-        // Check if the write was successful
-        truncated_file.write_all(rl_hours_str.as_bytes())?;
-
-        green_ln_bold!("Successful!\n");
-        Ok(())
-    } else {
-        // Check if the file was created successfully
-        let mut file = File::create("C:\\RLHoursFolder\\hours.txt")?;
-        let total_seconds = sw.elapsed_ms() / 1000;
-        let total_hours: f32 = (sw.elapsed_ms() as f32 / 1000_f32) / 3600_f32;
-        let rl_hours_str = format!(
-                                "Rocket League Hours\nTotal Seconds: {}s\nTotal Hours: {:.1}hrs\nHours Past Two Weeks: {:.1}hrs\n", total_seconds, total_hours, hours_past_two
-                            );
-
-        yellow_ln_bold!("Writing to hours.txt...");
-
-        // Checks if the write was successful
-        file.write_all(rl_hours_str.as_bytes())?;
-
-        green_ln_bold!("The hours file was successfully created");
-        Ok(())
-    }
-}
-
-/// This function writes new contents to the `date.txt` file. This uses the [`Local`] struct which allows us to use the [`Local::now()`]
-/// function to retrieve the local date and time as [`DateTime<Local>`]. The date is then turned into a [`NaiveDate`] by using [`DateTime<Local>::date_naive()`]
-/// which returns us the date by itself.
-///
-/// # Errors
-/// Returns an [`io::Error`] if there were any file operations which failed.
-fn write_to_date(date_result: IoResult<File>, seconds: &u64) -> IoResult<()> {
-    // Check if the date file exists
-    if date_result.is_ok() {
-        let mut append_date_result = File::options()
-            .append(true)
-            .open("C:\\RLHoursFolder\\date.txt")?;
-
-        // Attenot to open the date.txt file
-        let today = Local::now().date_naive();
-
-        let today_str = format!("{} {}s\n", today, seconds);
-
-        yellow_ln_bold!("Appending to date.txt...");
-
-        // Checks if the write was successful
-        append_date_result.write_all(today_str.as_bytes())?;
-
-        green_ln_bold!("Successful!\n");
-        Ok(())
-    } else {
-        // Check if the file was created
-        let mut file = File::create("C:\\RLHoursFolder\\date.txt")?;
-        let today = Local::now().date_naive();
-
-        let today_str = format!("{} {}s\n", today, seconds);
-
-        yellow_ln_bold!("Appending to date.txt...");
-
-        // Checks if the write was successful
-        file.write_all(today_str.as_bytes())?;
+                print!("{}[2K\r", 27 as char);
 
-        green_ln_bold!("The date file was successfully created");
-        Ok(())
-    }
-}
+                if *paused {
+                    yellow!("Paused, ");
+                } else if *idle {
+                    yellow!("Idle, ");
+                }
 
-/// This function checks if the process passed in via `name: &str` is running and returns a [`bool`] value
-fn check_for_process(name: &str) -> bool {
-    let sys = System::new_all();
-    let mut result = false;
+                // Print the output for the timer
+                cyan!("Time Elapsed: {hours:02}:{minutes:02}:{seconds:02}\r");
 
-    for process in sys.processes_by_exact_name(name.as_ref()) {
-        if process.name() == name {
-            result = true;
-            break;
+                // Flush the output
+                io::stdout()
+                    .flush()
+                    .unwrap_or_else(|_| warn!("could not flush output stream"));
+            }
+            TrackerEvent::SessionEnded { .. } => {
+                print!("{}[2K\r", 27 as char);
+                green_ln_bold!("Session appended to sessions.jsonl!\n");
+            }
+            TrackerEvent::PastTwoUpdated { summary, .. } => {
+                trace!("<< ConsoleSubscriber >> summary: {summary:?}")
+            }
+            _ => (),
         }
     }
-
-    result
 }
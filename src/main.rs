@@ -1,12 +1,24 @@
 use std::io::{ErrorKind, Write};
-use std::sync::atomic::AtomicBool;
-use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use std::{env, process, thread};
 
-use colour::{blue, blue_ln, cyan, e_red_ln, green_ln, green_ln_bold};
+use clap::Parser;
+use colour::{
+    blue, blue_ln, cyan, e_green_ln_bold, e_red_ln, e_yellow_ln_bold, green_ln, green_ln_bold,
+    yellow_ln_bold,
+};
 use log::{error, warn};
+use rl_hours_tracker::checkpoint::handle_unfinished_session;
+use rl_hours_tracker::cli::{self, Cli, Command};
+use rl_hours_tracker::config::Config;
+use rl_hours_tracker::daemon::{self, install_signal_handler};
+use rl_hours_tracker::data_dir::DataDir;
+use rl_hours_tracker::games::Game;
 use rl_hours_tracker::initialize_logging;
+use rl_hours_tracker::migrate::migrate_on_startup;
+use rl_hours_tracker::recovery::recover_on_startup;
+use rl_hours_tracker::timezone;
+use rl_hours_tracker::tracker::TrackerHandle;
 use rl_hours_tracker::winit_tray_icon::{UserEvent, initialize_tray_icon};
 use rl_hours_tracker::{
     calculate_past_two::update_past_two, create_directory, run, run_self_update,
@@ -14,23 +26,62 @@ use rl_hours_tracker::{
 use winit::event_loop::EventLoop;
 
 fn main() {
-    let event_loop = EventLoop::<UserEvent>::with_user_event()
-        .build()
-        .unwrap_or_else(|e| {
+    let cli = Cli::parse();
+
+    let config = Config::load().unwrap_or_default();
+
+    // Runs without prompts when the daemon flag is passed or enabled in the configuration
+    let is_daemon = daemon::init(cli.daemon || config.daemon);
+    timezone::init(config.timezone);
+
+    // Every command besides track runs once and exits without the tray icon
+    let command = cli.command();
+    if command != Command::Track {
+        // The config commands do not use the data folder, so they can fix a configuration which is not valid
+        let data_dir = match DataDir::resolve(cli.data_dir) {
+            Ok(data_dir) => data_dir,
+            Err(_) if matches!(command, Command::Config { .. }) => DataDir::platform_default(),
+            Err(e) => {
+                e_red_ln!("an error occurred when loading the configuration: {e}");
+                process::exit(1);
+            }
+        };
+        DataDir::init(data_dir);
+
+        if command.reads_data() {
+            prepare_data_dir(&config.games());
+        }
+
+        cli::execute(command).unwrap_or_else(|e| {
+            e_red_ln!("{e}");
+            process::exit(1);
+        });
+        return;
+    }
+
+    // A daemon keeps running without a tray icon if there is no display
+    let event_loop = match EventLoop::<UserEvent>::with_user_event().build() {
+        Ok(event_loop) => Some(event_loop),
+        Err(e) if is_daemon => {
+            yellow_ln_bold!("Running without a tray icon: {e}");
+            None
+        }
+        Err(e) => {
             error!("error occurred creating event loop: {e}");
             panic!("could not create event loop for tray icon");
-        });
+        }
+    };
 
-    let proxy = event_loop.create_proxy();
+    let proxy = event_loop.as_ref().map(EventLoop::create_proxy);
     
-    // Create booleans for sharing between multiple threads
-    let currently_tracking = Arc::new(Mutex::new(AtomicBool::new(false)));
-    let stop_tracker = Arc::new(Mutex::new(AtomicBool::new(false)));
+    // The state of the tracker is shared with the tray icon and the signal handler
+    let tracker_handle = TrackerHandle::new();
+    let main_th = tracker_handle.clone();
 
-    let main_ct = currently_tracking.clone();
-    let main_st = stop_tracker.clone();
+    // Signals stop the session in progress and end the program
+    install_signal_handler(proxy.clone(), tracker_handle.clone());
 
-    std::thread::spawn(move || {
+    let tracker = std::thread::spawn(move || {
         blue!(
             "
 
@@ -67,6 +118,16 @@ fn main() {
             )
         });
 
+        // Resolve the folder which stores the tracker data
+        let data_dir = DataDir::resolve(cli.data_dir).unwrap_or_else(|e| {
+            e_red_ln!("an error occurred when loading the configuration: {e}");
+            thread::sleep(Duration::from_secs(2));
+            e_red_ln!("this program will end in 3 seconds");
+            thread::sleep(Duration::from_secs(3));
+            process::exit(1);
+        });
+        DataDir::init(data_dir);
+
         // Initialize logging for the program
         initialize_logging().unwrap_or_else(|e| {
             e_red_ln!("an error occurred when initializing logging: {e}");
@@ -82,7 +143,12 @@ fn main() {
             let dir = path.to_str().unwrap_or_default();
 
             if dir.contains("AppData") {
-                run_self_update().unwrap_or_else(|e| error!("error running self update: {e}"));
+                match run_self_update() {
+                    // The new version starts once this process has closed
+                    Ok(true) => process::exit(0),
+                    Ok(false) => (),
+                    Err(e) => error!("error running self update: {e}"),
+                }
             }
         }
 
@@ -103,6 +169,23 @@ fn main() {
             green_ln!("All directories created successfully!");
         }
 
+        let config = Config::load().unwrap_or_default();
+        let games = config.games();
+
+        prepare_data_dir(&games);
+
+        // Records or discards a session which was interrupted before it ended
+        for game in &games {
+            let game_dir = game.data_dir(DataDir::current());
+
+            if let Err(e) = handle_unfinished_session(&game_dir, config.unfinished_session) {
+                error!(
+                    "There was an issue recovering the unfinished session of {}: {e}",
+                    game.name
+                );
+            }
+        }
+
         // Updates the hours in the past two weeks if it returns true
         if update_past_two().unwrap_or_else(|e| {
             warn!("past two could not be updated: {e}");
@@ -111,9 +194,60 @@ fn main() {
             green_ln_bold!("Past Two Updated!\n");
         }
 
-        run(proxy, stop_tracker, currently_tracking);
+        run(proxy, tracker_handle);
     });
 
-    // Initialize the tray icon
-    initialize_tray_icon(event_loop, main_st, main_ct);
+    match event_loop {
+        // Initialize the tray icon
+        Some(event_loop) => {
+            if let Err(e) = initialize_tray_icon(event_loop, main_th) {
+                error!("running without the tray icon: {e}");
+                tracker
+                    .join()
+                    .unwrap_or_else(|_| error!("the tracker thread panicked"));
+            }
+        }
+        None => tracker
+            .join()
+            .unwrap_or_else(|_| error!("the tracker thread panicked")),
+    }
+}
+
+/// Recovers any data files of the `games` which were left truncated or corrupt, and migrates the legacy date.txt
+/// file into the session log, before the data folder is read.
+fn prepare_data_dir(games: &[Game]) {
+    // Recovers any data files which were left truncated or corrupt
+    for game in games {
+        match recover_on_startup(&game.data_dir(DataDir::current())) {
+            Ok(recoveries) => {
+                for recovery in recoveries {
+                    e_yellow_ln_bold!("{recovery}");
+                }
+            }
+            Err(e) => {
+                error!(
+                    "There was an issue recovering the data files of {}: {e}",
+                    game.name
+                );
+                e_red_ln!(
+                    "There was an issue recovering the data files of {}: {e}",
+                    game.name
+                );
+            }
+        }
+    }
+
+    // Migrates the legacy date.txt file into the session log
+    match migrate_on_startup(DataDir::current()) {
+        Ok(Some(report)) => e_green_ln_bold!(
+            "Migrated {} sessions, backup stored in {}\n",
+            report.sessions,
+            report.backup_dir.display()
+        ),
+        Ok(None) => (),
+        Err(e) => {
+            error!("There was an issue migrating date.txt, it will be retried next launch: {e}");
+            e_red_ln!("There was an issue migrating date.txt, it will be retried next launch: {e}");
+        }
+    }
 }
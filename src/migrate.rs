@@ -0,0 +1,326 @@
+//! This module migrates the data directory from the legacy `date.txt` layout to the `sessions.jsonl` session log.
+//!
+//! Older versions of the program stored every session as a `YYYY-MM-DD Ns` line in `date.txt`. When the legacy
+//! layout is detected on startup, every line is converted into a [`Session`] with the [`EndReason::Legacy`] end reason,
+//! `date.txt` and `hours.txt` are backed up into the `backup` folder, and the schema version is recorded in
+//! `schema.json` so the migration never runs twice.
+use crate::{
+    atomic_file::write_atomic,
+    config::Config,
+    data_dir::DataDir,
+    error::TrackerResult,
+    parser::{parse_date_line, parse_dates, parse_hours, ParseError, ParseMode, Parsed},
+    session::{read_sessions, EndReason, Session, SESSION_VERSION},
+};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use colour::{green_ln_bold, yellow_ln_bold};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// The current version of the data directory layout.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The schema of the data directory, which is stored in `schema.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schema {
+    /// The version of the data directory layout.
+    pub version: u32,
+    /// When the legacy layout was migrated, or [`None`] if there was nothing to migrate.
+    pub migrated_at: Option<DateTime<FixedOffset>>,
+    /// Seconds in the legacy `hours.txt` total which had no matching line in `date.txt`.
+    pub legacy_unrecorded_seconds: u64,
+}
+
+impl Schema {
+    /// Loads the schema from the file at `path`, or returns [`None`] if the file does not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the file could not be read or is not valid JSON.
+    pub fn load(path: &Path) -> TrackerResult<Option<Schema>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Writes the schema to the file at `path`.
+    ///
+    /// # Errors
+    /// Returns an error if the file could not be written.
+    pub fn save(&self, path: &Path) -> TrackerResult<()> {
+        write_atomic(path, serde_json::to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// Custom error for the [`migrate`] function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    /// The data directory was already migrated to the contained schema version.
+    AlreadyMigrated(u32),
+    /// A line in `date.txt` could not be parsed.
+    InvalidLine { line: usize, text: String },
+}
+
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationError::AlreadyMigrated(version) => write!(
+                f,
+                "the data directory was already migrated to schema version {version}."
+            ),
+            MigrationError::InvalidLine { line, text } => {
+                write!(f, "date.txt line {line} is not a valid date: '{text}'.")
+            }
+        }
+    }
+}
+
+impl Error for MigrationError {}
+
+impl From<ParseError> for MigrationError {
+    fn from(error: ParseError) -> Self {
+        MigrationError::InvalidLine {
+            line: error.line,
+            text: error.text,
+        }
+    }
+}
+
+/// The result of a successful migration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// The number of sessions converted from `date.txt`.
+    pub sessions: usize,
+    /// The total seconds of the converted sessions.
+    pub total_seconds: u64,
+    /// Seconds in the legacy `hours.txt` total which had no matching line in `date.txt`.
+    pub unrecorded_seconds: u64,
+    /// The folder which contains the backup of the legacy files.
+    pub backup_dir: PathBuf,
+    /// The invalid lines of `date.txt` and `hours.txt` which were skipped in lenient mode.
+    pub skipped: Vec<ParseError>,
+}
+
+/// Returns `true` if the data directory still uses the legacy `date.txt` layout and has not been migrated.
+pub fn needs_migration(data_dir: &DataDir) -> bool {
+    let migrated = matches!(
+        Schema::load(&data_dir.schema_file()),
+        Ok(Some(schema)) if schema.version >= SCHEMA_VERSION
+    );
+
+    !migrated && data_dir.date_file().is_file()
+}
+
+/// Runs the migration if the legacy layout is detected, otherwise records the current schema version
+/// if the data directory does not have one yet. A [`MigrationReport`] is returned if a migration took place.
+///
+/// # Errors
+/// Returns an error if the migration failed or the schema could not be written.
+pub fn migrate_on_startup(data_dir: &DataDir) -> TrackerResult<Option<MigrationReport>> {
+    if needs_migration(data_dir) {
+        return migrate(data_dir).map(Some);
+    }
+
+    if Schema::load(&data_dir.schema_file())?.is_none() {
+        Schema {
+            version: SCHEMA_VERSION,
+            migrated_at: None,
+            legacy_unrecorded_seconds: 0,
+        }
+        .save(&data_dir.schema_file())?;
+    }
+
+    Ok(None)
+}
+
+/// This function migrates the legacy `date.txt` file into `sessions.jsonl`.
+///
+/// Every line of `date.txt` is converted with [`legacy_session`] and written before any sessions which are already
+/// in `sessions.jsonl`. `date.txt` is then moved, and `hours.txt` copied, into a timestamped folder in `backup`.
+/// Finally `schema.json` is written, which stops the migration from running again.
+///
+/// The files are parsed in the [`ParseMode`] set by `parsing` in `config.toml`.
+///
+/// # Errors
+/// Returns a [`MigrationError`] if the data directory was already migrated or `date.txt` contains an invalid line,
+/// or an [`std::io::Error`] if any file operations failed.
+pub fn migrate(data_dir: &DataDir) -> TrackerResult<MigrationReport> {
+    let mode = Config::load()
+        .map(|config| config.parsing)
+        .unwrap_or_default();
+
+    migrate_with(data_dir, mode)
+}
+
+/// This function migrates the legacy `date.txt` file into `sessions.jsonl` in the same way as [`migrate`], where
+/// the files are parsed in the `mode`. In lenient mode the invalid lines are skipped, and are still kept in the
+/// backup of `date.txt`.
+///
+/// # Errors
+/// Returns a [`MigrationError`] if the data directory was already migrated or `date.txt` contains an invalid line in
+/// strict mode, or an [`std::io::Error`] if any file operations failed.
+pub fn migrate_with(data_dir: &DataDir, mode: ParseMode) -> TrackerResult<MigrationReport> {
+    if let Some(schema) = Schema::load(&data_dir.schema_file())? {
+        if schema.version >= SCHEMA_VERSION {
+            return Err(MigrationError::AlreadyMigrated(schema.version).into());
+        }
+    }
+
+    info!("Migration: START\n");
+    yellow_ln_bold!("Migrating date.txt to sessions.jsonl...");
+
+    let date_contents = fs::read_to_string(data_dir.date_file())?;
+    let parsed = parse_legacy_dates_with(&date_contents, mode)?;
+    let mut sessions = parsed.value;
+    let mut skipped = parsed.skipped;
+
+    let total_seconds: u64 = sessions.iter().map(|session| session.duration).sum();
+    let converted = sessions.len();
+
+    // Keep any sessions which were recorded in the new format before the migration ran
+    sessions.extend(read_sessions(&data_dir.sessions_file())?);
+
+    // Seconds in hours.txt which were never written to date.txt
+    let unrecorded_seconds = match fs::read_to_string(data_dir.hours_file()) {
+        Ok(contents) => {
+            let parsed = parse_hours(&contents, mode)?;
+            skipped.extend(parsed.skipped);
+
+            parsed.value.seconds.saturating_sub(total_seconds)
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => 0,
+        Err(e) => return Err(e.into()),
+    };
+
+    // Back up the legacy files
+    let now = Local::now();
+    let backup_dir = data_dir
+        .backup_dir()
+        .join(format!("legacy-{}", now.format("%Y-%m-%d_%H-%M-%S")));
+
+    fs::create_dir_all(&backup_dir)?;
+    fs::copy(data_dir.date_file(), backup_dir.join("date.txt"))?;
+
+    if data_dir.hours_file().is_file() {
+        fs::copy(data_dir.hours_file(), backup_dir.join("hours.txt"))?;
+    }
+
+    if data_dir.sessions_file().is_file() {
+        fs::copy(data_dir.sessions_file(), backup_dir.join("sessions.jsonl"))?;
+    }
+
+    // Replace sessions.jsonl with the converted and existing sessions
+    let mut contents = String::new();
+
+    for session in &sessions {
+        contents.push_str(&serde_json::to_string(session)?);
+        contents.push('\n');
+    }
+
+    write_atomic(&data_dir.sessions_file(), contents)?;
+
+    // The schema is saved before date.txt is removed, so an interrupted migration is never repeated
+    Schema {
+        version: SCHEMA_VERSION,
+        migrated_at: Some(now.fixed_offset()),
+        legacy_unrecorded_seconds: unrecorded_seconds,
+    }
+    .save(&data_dir.schema_file())?;
+
+    fs::remove_file(data_dir.date_file())?;
+
+    for error in &skipped {
+        warn!("skipped an invalid line during the migration: {error}");
+    }
+
+    if !skipped.is_empty() {
+        yellow_ln_bold!(
+            "Skipped {} invalid lines, which are kept in the backup of date.txt",
+            skipped.len()
+        );
+    }
+
+    green_ln_bold!("Migrated {converted} sessions from date.txt\n");
+    info!(
+        "Migration: FINISHED. sessions: {converted}, seconds: {total_seconds}, unrecorded seconds: {unrecorded_seconds}, backup: {}\n",
+        backup_dir.display()
+    );
+
+    Ok(MigrationReport {
+        sessions: converted,
+        total_seconds,
+        unrecorded_seconds,
+        backup_dir,
+        skipped,
+    })
+}
+
+/// This function parses the contents of `date.txt` into [`Session`]s, in the order they appear in the file.
+/// Blank lines are ignored.
+///
+/// # Errors
+/// Returns a [`MigrationError::InvalidLine`] if a line is not in the `YYYY-MM-DD Ns` format.
+pub fn parse_legacy_dates(contents: &str) -> Result<Vec<Session>, MigrationError> {
+    Ok(parse_legacy_dates_with(contents, ParseMode::Strict)?.value)
+}
+
+/// This function parses the contents of `date.txt` into [`Session`]s in the same way as [`parse_legacy_dates`],
+/// where invalid lines are skipped and reported in lenient `mode`.
+///
+/// # Errors
+/// Returns a [`MigrationError::InvalidLine`] in strict `mode` if a line is not in the `YYYY-MM-DD Ns` format.
+pub fn parse_legacy_dates_with(
+    contents: &str,
+    mode: ParseMode,
+) -> Result<Parsed<Vec<Session>>, MigrationError> {
+    let parsed = parse_dates(contents, mode)?;
+
+    Ok(Parsed {
+        value: parsed
+            .value
+            .into_iter()
+            .map(|(date, seconds)| legacy_session(date, seconds))
+            .collect(),
+        skipped: parsed.skipped,
+    })
+}
+
+/// Parses a `YYYY-MM-DD Ns` line from `date.txt` into its date and seconds.
+pub fn parse_legacy_line(line: &str) -> Option<(NaiveDate, u64)> {
+    parse_date_line(line).ok()
+}
+
+/// Creates a [`Session`] from a `date.txt` entry.
+///
+/// The legacy format only stored the date the session ended on and its length, so the session is recorded as
+/// ending at the last second of `date` in the local timezone, and starting `seconds` before that. The date and
+/// the duration are kept exactly, so the conversion is lossless.
+pub fn legacy_session(date: NaiveDate, seconds: u64) -> Session {
+    let naive_end = date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default());
+
+    let end = Local
+        .from_local_datetime(&naive_end)
+        .earliest()
+        .map(|end| end.fixed_offset())
+        .unwrap_or_else(|| Utc.from_utc_datetime(&naive_end).fixed_offset());
+
+    Session {
+        version: SESSION_VERSION,
+        start: end - chrono::Duration::seconds(seconds as i64),
+        end,
+        duration: seconds,
+        end_reason: EndReason::Legacy,
+        app_version: String::from("legacy"),
+        idle: Vec::new(),
+    }
+}
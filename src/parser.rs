@@ -0,0 +1,336 @@
+//! This module contains the parsers of the text files in the data folder, `date.txt` and `hours.txt`.
+//!
+//! Every line is checked, and a line which can not be parsed is returned as a [`ParseError`] with the name of the
+//! file, the line number and the text of the line, instead of stopping the whole calculation. The [`ParseMode`] is set
+//! by `parsing` in `config.toml`:
+//! - `"strict"` stops at the first invalid line. This is the default.
+//! - `"lenient"` skips the invalid lines and reports them, so a hand-edited file can still be read.
+//!
+//! ```toml
+//! parsing = "lenient"
+//! ```
+//!
+//! The `validate` command checks the data folder of every game with [`validate_games`], and reports every invalid
+//! line.
+use crate::{data_dir::DataDir, error::TrackerResult, games::Game, session::Session};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt::Display, fs, io::ErrorKind, path::Path};
+
+/// Used for returning errors when a line of a data file could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The name of the file, such as `hours.txt`.
+    pub file: String,
+    /// The number of the line, starting at 1.
+    pub line: usize,
+    /// The text of the line.
+    pub text: String,
+    /// What is wrong with the line.
+    pub message: String,
+}
+
+impl ParseError {
+    fn new(file: &str, line: usize, text: &str, message: impl Into<String>) -> ParseError {
+        ParseError {
+            file: file.to_string(),
+            line,
+            text: text.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Parse Error: {} line {}: {}: '{}'",
+            self.file, self.line, self.message, self.text
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+/// What to do with a line which can not be parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParseMode {
+    /// Return an error for the first invalid line.
+    #[default]
+    Strict,
+    /// Skip the invalid lines, and report them in [`Parsed::skipped`].
+    Lenient,
+}
+
+impl ParseMode {
+    /// Returns the `error` in strict mode, or adds it to `skipped` in lenient mode.
+    fn skip(self, error: ParseError, skipped: &mut Vec<ParseError>) -> Result<(), ParseError> {
+        match self {
+            ParseMode::Strict => Err(error),
+            ParseMode::Lenient => {
+                skipped.push(error);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The result of a parser, with the lines which were skipped in lenient mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parsed<T> {
+    /// The parsed contents of the file.
+    pub value: T,
+    /// The invalid lines which were skipped, which is always empty in strict mode.
+    pub skipped: Vec<ParseError>,
+}
+
+/// The totals which are read from `hours.txt`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HoursTotals {
+    /// The total seconds played.
+    pub seconds: u64,
+    /// The total hours played.
+    pub hours: f32,
+}
+
+/// This function parses the contents of `date.txt` into the date and seconds of each line, in the order they appear
+/// in the file. Blank lines are ignored.
+///
+/// # Errors
+/// Returns a [`ParseError`] in strict mode if a line is not in the `YYYY-MM-DD Ns` format.
+pub fn parse_dates(
+    contents: &str,
+    mode: ParseMode,
+) -> Result<Parsed<Vec<(NaiveDate, u64)>>, ParseError> {
+    let mut dates = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_date_line(line) {
+            Ok(date) => dates.push(date),
+            Err(message) => mode.skip(
+                ParseError::new("date.txt", idx + 1, line, message),
+                &mut skipped,
+            )?,
+        }
+    }
+
+    Ok(Parsed {
+        value: dates,
+        skipped,
+    })
+}
+
+/// Parses a `YYYY-MM-DD Ns` line from `date.txt` into its date and seconds.
+pub(crate) fn parse_date_line(line: &str) -> Result<(NaiveDate, u64), String> {
+    let mut split_whitespace = line.split_whitespace();
+
+    let date = split_whitespace.next().unwrap_or_default();
+    let date = date
+        .parse()
+        .map_err(|_| format!("'{date}' is not a date in the YYYY-MM-DD format"))?;
+
+    let seconds = split_whitespace.next().ok_or("the seconds are missing")?;
+    let seconds = seconds
+        .strip_suffix('s')
+        .and_then(|seconds| seconds.parse().ok())
+        .ok_or_else(|| format!("'{seconds}' is not a number of seconds, such as 58s"))?;
+
+    if split_whitespace.next().is_some() {
+        return Err("there is text after the seconds".to_string());
+    }
+
+    Ok((date, seconds))
+}
+
+/// This function parses the contents of `hours.txt` into its [`HoursTotals`]. The first line is the name of the game,
+/// followed by `Label: value` lines, where `Total Seconds` is a number of seconds such as `100040s` and every other
+/// value is a number of hours such as `27.8hrs`. Blank lines are ignored. In lenient mode, a first line which is not
+/// the name of the game is skipped, and still read as a `Label: value` line.
+///
+/// # Errors
+/// Returns a [`ParseError`] if the `Total Seconds` or `Total Hours` line is missing, or in strict mode if any line is
+/// not valid.
+pub fn parse_hours(contents: &str, mode: ParseMode) -> Result<Parsed<HoursTotals>, ParseError> {
+    let mut seconds = None;
+    let mut hours = None;
+    let mut has_title = false;
+    let mut skipped = Vec::new();
+    let mut last_line = 0;
+
+    for (idx, line) in contents.lines().enumerate() {
+        last_line = idx + 1;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let error = |message: String| ParseError::new("hours.txt", idx + 1, line, message);
+
+        if !has_title {
+            has_title = true;
+
+            if line.trim_end().ends_with("Hours") {
+                continue;
+            }
+
+            mode.skip(
+                error(
+                    "the first line is not the name of the game, such as 'Rocket League Hours'"
+                        .into(),
+                ),
+                &mut skipped,
+            )?;
+
+            // A file without the name of the game starts with its totals, so the line is still read in lenient mode
+            if !line.contains(':') {
+                continue;
+            }
+        }
+
+        let Some((label, value)) = line.split_once(':') else {
+            mode.skip(
+                error("the line is not in the 'Label: value' format".into()),
+                &mut skipped,
+            )?;
+            continue;
+        };
+
+        let (label, value) = (label.trim(), value.trim());
+
+        let result = match label {
+            "Total Seconds" if seconds.is_some() => {
+                Err("Total Seconds appears more than once".to_string())
+            }
+            "Total Seconds" => parse_seconds_value(value).map(|value| seconds = Some(value)),
+            "Total Hours" if hours.is_some() => {
+                Err("Total Hours appears more than once".to_string())
+            }
+            "Total Hours" => parse_hours_value(value).map(|value| hours = Some(value)),
+            _ => parse_hours_value(value).map(|_| ()),
+        };
+
+        if let Err(message) = result {
+            mode.skip(error(message), &mut skipped)?;
+        }
+    }
+
+    let missing = |label: &str| {
+        ParseError::new(
+            "hours.txt",
+            last_line + 1,
+            "",
+            format!("the {label} line is missing"),
+        )
+    };
+
+    Ok(Parsed {
+        value: HoursTotals {
+            seconds: seconds.ok_or_else(|| missing("Total Seconds"))?,
+            hours: hours.ok_or_else(|| missing("Total Hours"))?,
+        },
+        skipped,
+    })
+}
+
+/// Parses a number of seconds such as `100040s`.
+fn parse_seconds_value(value: &str) -> Result<u64, String> {
+    value
+        .strip_suffix('s')
+        .and_then(|seconds| seconds.parse().ok())
+        .ok_or_else(|| format!("'{value}' is not a number of seconds, such as 100040s"))
+}
+
+/// Parses a number of hours such as `27.8hrs`.
+fn parse_hours_value(value: &str) -> Result<f32, String> {
+    value
+        .strip_suffix("hrs")
+        .and_then(|hours| hours.parse().ok())
+        .filter(|hours: &f32| hours.is_finite())
+        .ok_or_else(|| format!("'{value}' is not a number of hours, such as 27.8hrs"))
+}
+
+/// This function parses the contents of `sessions.jsonl`, which contains one JSON session per line. Blank lines are
+/// ignored.
+///
+/// # Errors
+/// Returns a [`ParseError`] in strict mode if a line is not a valid session.
+pub fn parse_sessions(contents: &str, mode: ParseMode) -> Result<Parsed<Vec<Session>>, ParseError> {
+    let mut sessions = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str(line) {
+            Ok(session) => sessions.push(session),
+            Err(e) => mode.skip(
+                ParseError::new("sessions.jsonl", idx + 1, line, e.to_string()),
+                &mut skipped,
+            )?,
+        }
+    }
+
+    Ok(Parsed {
+        value: sessions,
+        skipped,
+    })
+}
+
+/// This function checks `date.txt`, `hours.txt` and `sessions.jsonl` in the `data_dir`, and returns every line
+/// which could not be parsed. Files which do not exist are not checked.
+///
+/// # Errors
+/// Returns an error if a file exists but could not be read.
+pub fn validate(data_dir: &DataDir) -> TrackerResult<Vec<ParseError>> {
+    let mut errors = Vec::new();
+
+    if let Some(contents) = read_optional(&data_dir.date_file())? {
+        errors.extend(parse_dates(&contents, ParseMode::Lenient)?.skipped);
+    }
+
+    if let Some(contents) = read_optional(&data_dir.hours_file())? {
+        match parse_hours(&contents, ParseMode::Lenient) {
+            Ok(parsed) => errors.extend(parsed.skipped),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if let Some(contents) = read_optional(&data_dir.sessions_file())? {
+        errors.extend(parse_sessions(&contents, ParseMode::Lenient)?.skipped);
+    }
+
+    Ok(errors)
+}
+
+/// This function runs [`validate`] on the data folder of every game in `games`, including the `games/<id>` folders
+/// of the custom games, and returns the invalid lines of each game.
+///
+/// # Errors
+/// Returns an error if a file exists but could not be read.
+pub fn validate_games(
+    data_dir: &DataDir,
+    games: &[Game],
+) -> TrackerResult<Vec<(Game, Vec<ParseError>)>> {
+    games
+        .iter()
+        .map(|game| Ok((game.clone(), validate(&game.data_dir(data_dir))?)))
+        .collect()
+}
+
+/// Reads the file at `path`, or returns [`None`] if it does not exist.
+fn read_optional(path: &Path) -> TrackerResult<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
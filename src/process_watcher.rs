@@ -0,0 +1,263 @@
+//! This module contains the [`ProcessWatcher`] trait, which checks if the Rocket League process is running.
+//!
+//! There are several implementations:
+//! - [`SysinfoWatcher`] loads every piece of system information on each check, which is how the tracker always worked.
+//! - [`RefreshWatcher`] keeps a [`System`] and only refreshes the process list, which is much cheaper.
+//! - [`ProcWatcher`] reads the Linux `/proc` filesystem directly, which also finds the game when it runs
+//!   through Steam Proton or Wine.
+//! - [`FakeWatcher`] returns scripted results, so the tracker can be tested without a real game.
+//!
+//! [`default_watcher`] returns the implementation used by the program. The watchers which can read the
+//! executable path and command line of a process detect the game through the [`DetectionRules`].
+use crate::detection::{DetectionRules, ProcessInfo};
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+};
+use sysinfo::{Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+/// Checks if a process is running.
+pub trait ProcessWatcher: Send {
+    /// Returns `true` if a process called `process_name` is running.
+    fn is_running(&mut self, process_name: &str) -> bool;
+
+    /// Returns `true` if a process which matches the `rules` is running. By default, this checks if a process
+    /// with one of the [`DetectionRules::process_names`] is running.
+    fn detect(&mut self, rules: &DetectionRules) -> bool {
+        rules
+            .process_names
+            .iter()
+            .any(|process_name| self.is_running(process_name))
+    }
+}
+
+/// Returns the [`ProcessWatcher`] used by the program, which is a [`ProcWatcher`] on Linux and
+/// a [`RefreshWatcher`] on every other platform.
+pub fn default_watcher() -> Box<dyn ProcessWatcher> {
+    if cfg!(target_os = "linux") {
+        Box::new(ProcWatcher::new())
+    } else {
+        Box::new(RefreshWatcher::new())
+    }
+}
+
+/// A watcher which loads all of the system information through [`System::new_all`] on every check.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SysinfoWatcher;
+
+impl ProcessWatcher for SysinfoWatcher {
+    fn is_running(&mut self, process_name: &str) -> bool {
+        let sys = System::new_all();
+        let result = sys
+            .processes_by_exact_name(process_name.as_ref())
+            .any(|process| process.name() == process_name);
+
+        result
+    }
+
+    fn detect(&mut self, rules: &DetectionRules) -> bool {
+        let sys = System::new_all();
+        let result = sys
+            .processes()
+            .values()
+            .any(|process| rules.matches(&process_info(process)));
+
+        result
+    }
+}
+
+/// A watcher which keeps a [`System`] between checks and only refreshes the list of processes.
+#[derive(Debug, Default)]
+pub struct RefreshWatcher {
+    system: System,
+}
+
+impl RefreshWatcher {
+    /// Creates a new watcher with an empty process list, which is filled on the first check.
+    pub fn new() -> RefreshWatcher {
+        RefreshWatcher {
+            system: System::new(),
+        }
+    }
+}
+
+impl ProcessWatcher for RefreshWatcher {
+    fn is_running(&mut self, process_name: &str) -> bool {
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing(),
+        );
+
+        self.system
+            .processes_by_exact_name(process_name.as_ref())
+            .any(|process| process.name() == process_name)
+    }
+
+    fn detect(&mut self, rules: &DetectionRules) -> bool {
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_exe(UpdateKind::OnlyIfNotSet)
+                .with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+
+        self.system
+            .processes()
+            .values()
+            .any(|process| rules.matches(&process_info(process)))
+    }
+}
+
+/// Converts a [`sysinfo`] process into the [`ProcessInfo`] which is matched by the [`DetectionRules`].
+pub(crate) fn process_info(process: &Process) -> ProcessInfo {
+    ProcessInfo {
+        name: process.name().to_string_lossy().into_owned(),
+        exe: process.exe().map(Path::to_path_buf),
+        cmdline: process
+            .cmd()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+    }
+}
+
+/// A watcher which reads the processes from the Linux `/proc` filesystem.
+///
+/// The name of a process is read from `comm`, which the kernel truncates to 15 bytes, the executable from the `exe`
+/// link and the arguments from `cmdline`. Under Proton and Wine the process is `wine-preloader`, and the first
+/// argument is the Windows path of the game, such as `Z:\...\RocketLeague.exe`.
+#[derive(Debug, Clone)]
+pub struct ProcWatcher {
+    root: PathBuf,
+}
+
+impl ProcWatcher {
+    /// Creates a new watcher which reads `/proc`.
+    pub fn new() -> ProcWatcher {
+        ProcWatcher::with_root("/proc")
+    }
+
+    /// Creates a new watcher which reads the processes from the folder at `root` instead of `/proc`.
+    pub fn with_root(root: impl Into<PathBuf>) -> ProcWatcher {
+        ProcWatcher { root: root.into() }
+    }
+
+    /// Returns the [`ProcessInfo`] of every process, which are the numeric folders in the root.
+    fn processes(&self) -> Vec<ProcessInfo> {
+        let Ok(entries) = fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| name.bytes().all(|byte| byte.is_ascii_digit()))
+            })
+            .map(|entry| ProcWatcher::process_info(&entry.path()))
+            .collect()
+    }
+
+    /// Reads the [`ProcessInfo`] of the process in the folder at `path`. Files which cannot be read,
+    /// such as the `exe` link of a process owned by another user, are left empty.
+    fn process_info(path: &Path) -> ProcessInfo {
+        let name = fs::read_to_string(path.join("comm"))
+            .map(|comm| comm.trim_end_matches('\n').to_string())
+            .unwrap_or_default();
+
+        let cmdline = fs::read(path.join("cmdline"))
+            .map(|cmdline| {
+                cmdline
+                    .split(|byte| *byte == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        ProcessInfo {
+            name,
+            exe: fs::read_link(path.join("exe")).ok(),
+            cmdline,
+        }
+    }
+}
+
+impl Default for ProcWatcher {
+    fn default() -> Self {
+        ProcWatcher::new()
+    }
+}
+
+impl ProcessWatcher for ProcWatcher {
+    fn is_running(&mut self, process_name: &str) -> bool {
+        self.detect(&DetectionRules {
+            process_names: vec![process_name.to_string()],
+            exe_patterns: Vec::new(),
+            cmdline_patterns: Vec::new(),
+            launcher_patterns: false,
+        })
+    }
+
+    fn detect(&mut self, rules: &DetectionRules) -> bool {
+        self.processes()
+            .iter()
+            .any(|process| rules.matches(process))
+    }
+}
+
+/// A watcher which returns scripted results, for testing the tracker without a real game.
+///
+/// Each check returns the next result, and once they run out, every check returns `false`.
+///
+/// ```
+/// use rl_hours_tracker::process_watcher::{FakeWatcher, ProcessWatcher};
+///
+/// let mut watcher = FakeWatcher::new([true, true]);
+///
+/// assert!(watcher.is_running("RocketLeague.exe"));
+/// assert!(watcher.is_running("RocketLeague.exe"));
+/// assert!(!watcher.is_running("RocketLeague.exe"));
+/// assert_eq!(watcher.checks(), 3);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct FakeWatcher {
+    results: VecDeque<bool>,
+    checks: usize,
+}
+
+impl FakeWatcher {
+    /// Creates a new watcher which returns the `results` in order.
+    pub fn new(results: impl IntoIterator<Item = bool>) -> FakeWatcher {
+        FakeWatcher {
+            results: results.into_iter().collect(),
+            checks: 0,
+        }
+    }
+
+    /// Creates a new watcher which reports the process as running for `checks` checks.
+    pub fn running_for(checks: usize) -> FakeWatcher {
+        FakeWatcher::new(vec![true; checks])
+    }
+
+    /// Returns the number of checks which were made.
+    pub fn checks(&self) -> usize {
+        self.checks
+    }
+}
+
+impl ProcessWatcher for FakeWatcher {
+    fn is_running(&mut self, _process_name: &str) -> bool {
+        self.checks += 1;
+        self.results.pop_front().unwrap_or(false)
+    }
+
+    fn detect(&mut self, rules: &DetectionRules) -> bool {
+        self.is_running(rules.process_names.first().map_or("", String::as_str))
+    }
+}
@@ -0,0 +1,273 @@
+//! This module detects and recovers truncated or corrupt data files on startup.
+//!
+//! Every file is checked in turn. A stale temporary file from an interrupted [`crate::atomic_file::write_atomic`]
+//! is removed, since the original file was never replaced. A corrupt file is moved aside and replaced by its most
+//! recent valid backup, except for `sessions.jsonl`, which keeps every line which is still a valid session. If there
+//! is no valid backup, `hours.txt` and `schema.json` are recreated by the rest of the startup.
+//!
+//! Sessions are appended to `sessions.jsonl` in place, so a crash during an append can leave its last line cut off.
+//! Such a line is removed from the end of the file, which leaves every complete session where it was.
+use crate::{
+    atomic_file::{backups, quarantine, restore_backup, temp_path, write_atomic},
+    checkpoint::Checkpoint,
+    config::Config,
+    data_dir::DataDir,
+    error::TrackerResult,
+    migrate::Schema,
+    parser::{parse_hours, ParseMode},
+    session::read_sessions_from,
+};
+use log::{info, warn};
+use std::{
+    fmt::Display,
+    fs::{self, File},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// Checks if the contents of a data file are valid, when the files are parsed in the [`ParseMode`].
+type Validator = fn(&str, ParseMode) -> bool;
+
+/// What was done to recover a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// A temporary file left by an interrupted write was removed.
+    RemovedTemp,
+    /// The corrupt file was moved to the contained path and replaced by a backup.
+    Restored { backup: PathBuf, corrupt: PathBuf },
+    /// The corrupt lines of the file were dropped, and the original was moved to the contained path.
+    Salvaged { dropped: usize, corrupt: PathBuf },
+    /// The last line of the file was cut off, and the contained number of bytes were removed from its end.
+    TruncatedLastLine { bytes: usize },
+    /// The corrupt file was moved to the contained path, and will be recreated.
+    Quarantined { corrupt: PathBuf },
+}
+
+/// A file which was recovered by [`recover_on_startup`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recovery {
+    /// The file which was recovered.
+    pub file: PathBuf,
+    /// What was done to recover it.
+    pub action: RecoveryAction,
+}
+
+impl Display for Recovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let file = self.file.display();
+
+        match &self.action {
+            RecoveryAction::RemovedTemp => {
+                write!(f, "removed the unfinished write of {file}")
+            }
+            RecoveryAction::Restored { backup, corrupt } => write!(
+                f,
+                "{file} was corrupt and was restored from {}, the corrupt file was kept as {}",
+                backup.display(),
+                corrupt.display()
+            ),
+            RecoveryAction::Salvaged { dropped, corrupt } => write!(
+                f,
+                "{file} was corrupt and {dropped} invalid lines were dropped, the corrupt file was kept as {}",
+                corrupt.display()
+            ),
+            RecoveryAction::TruncatedLastLine { bytes } => write!(
+                f,
+                "{file} ended with a line which was cut off, and its last {bytes} bytes were removed"
+            ),
+            RecoveryAction::Quarantined { corrupt } => write!(
+                f,
+                "{file} was corrupt and will be recreated, the corrupt file was kept as {}",
+                corrupt.display()
+            ),
+        }
+    }
+}
+
+/// This function checks every data file in the `data_dir` and recovers the files which are truncated or corrupt.
+/// A [`Vec`] of the files which were recovered is returned, which is empty if every file was intact.
+///
+/// The files are parsed in the [`ParseMode`] set by `parsing` in `config.toml`, so a file which the rest of the
+/// program accepts is never recovered.
+///
+/// # Errors
+/// Returns an [`std::io::Error`] if a file could not be read, moved or restored.
+pub fn recover_on_startup(data_dir: &DataDir) -> TrackerResult<Vec<Recovery>> {
+    let mode = Config::load()
+        .map(|config| config.parsing)
+        .unwrap_or_default();
+
+    recover_with(data_dir, mode)
+}
+
+/// This function recovers the data files in the `data_dir` in the same way as [`recover_on_startup`], where the
+/// files are parsed in the `mode`.
+///
+/// # Errors
+/// Returns an [`std::io::Error`] if a file could not be read, moved or restored.
+pub fn recover_with(data_dir: &DataDir, mode: ParseMode) -> TrackerResult<Vec<Recovery>> {
+    info!("Recovery: START\n");
+
+    let mut recoveries = Vec::new();
+
+    let files: [(PathBuf, Validator); 4] = [
+        // The session log is always read in strict mode
+        (data_dir.sessions_file(), |contents, _| {
+            is_valid_sessions(contents)
+        }),
+        (data_dir.hours_file(), is_valid_hours),
+        (data_dir.schema_file(), |contents, _| {
+            is_valid_schema(contents)
+        }),
+        (data_dir.checkpoint_file(), |contents, _| {
+            is_valid_checkpoint(contents)
+        }),
+    ];
+
+    for (path, is_valid) in files {
+        // The original file is intact if the temporary file was never renamed
+        let tmp_path = temp_path(&path);
+
+        if tmp_path.is_file() {
+            fs::remove_file(&tmp_path)?;
+            recoveries.push(Recovery {
+                file: path.clone(),
+                action: RecoveryAction::RemovedTemp,
+            });
+        }
+
+        if let Some(action) = recover_file(&path, is_valid, mode)? {
+            recoveries.push(Recovery { file: path, action });
+        }
+    }
+
+    for recovery in &recoveries {
+        warn!("{recovery}");
+    }
+
+    info!("Recovery: FINISHED\n");
+    Ok(recoveries)
+}
+
+/// Recovers the file at `path` if `is_valid` returns `false` for its contents in the `mode`, and returns what was
+/// done, or [`None`] if the file is intact or does not exist.
+fn recover_file(
+    path: &Path,
+    is_valid: Validator,
+    mode: ParseMode,
+) -> TrackerResult<Option<RecoveryAction>> {
+    let contents = match fs::read(path) {
+        Ok(bytes) => String::from_utf8(bytes).ok(),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let backup = valid_backup(path, is_valid, mode);
+
+    match contents {
+        // An empty file is only valid if there is nothing to restore
+        Some(contents)
+            if is_valid(&contents, mode) && (!contents.is_empty() || backup.is_none()) =>
+        {
+            Ok(None)
+        }
+        contents => {
+            let is_log = path.extension().is_some_and(|ext| ext == "jsonl");
+
+            // A line which was cut off while it was appended is removed without touching the rest of the log
+            if let Some(len) = contents
+                .as_deref()
+                .filter(|_| is_log)
+                .and_then(complete_len)
+            {
+                let bytes = contents.as_deref().map_or(0, str::len) - len;
+                let file = File::options().write(true).open(path)?;
+
+                file.set_len(len as u64)?;
+                file.sync_all()?;
+
+                return Ok(Some(RecoveryAction::TruncatedLastLine { bytes }));
+            }
+
+            let corrupt = quarantine(path)?;
+            let contents = contents.unwrap_or_default();
+
+            // Keeping the valid sessions loses less than restoring an older backup of the log
+            if is_log {
+                let (salvaged, dropped) = salvage_sessions(&contents);
+
+                if !salvaged.is_empty() || backup.is_none() {
+                    write_atomic(path, salvaged)?;
+
+                    return Ok(Some(RecoveryAction::Salvaged { dropped, corrupt }));
+                }
+            }
+
+            if let Some(backup) = backup {
+                restore_backup(path, &backup)?;
+
+                return Ok(Some(RecoveryAction::Restored { backup, corrupt }));
+            }
+
+            Ok(Some(RecoveryAction::Quarantined { corrupt }))
+        }
+    }
+}
+
+/// Returns the most recent backup of the file at `path` which is valid in the `mode` and not empty.
+fn valid_backup(path: &Path, is_valid: Validator, mode: ParseMode) -> Option<PathBuf> {
+    backups(path).into_iter().find(|backup| {
+        fs::read_to_string(backup)
+            .is_ok_and(|contents| !contents.is_empty() && is_valid(&contents, mode))
+    })
+}
+
+/// Keeps the lines of a session log which are valid sessions, and returns them with the number of lines
+/// which were dropped.
+fn salvage_sessions(contents: &str) -> (String, usize) {
+    let mut salvaged = String::new();
+    let mut dropped = 0;
+
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        if is_valid_sessions(line) {
+            salvaged.push_str(line);
+            salvaged.push('\n');
+        } else {
+            dropped += 1;
+        }
+    }
+
+    (salvaged, dropped)
+}
+
+/// Returns the length of a session log whose only invalid line is its last line, which does not end with a newline,
+/// without that line. [`None`] is returned if any other line is invalid, or no complete line would be left.
+fn complete_len(contents: &str) -> Option<usize> {
+    if contents.ends_with('\n') {
+        return None;
+    }
+
+    let len = contents.rfind('\n')? + 1;
+
+    is_valid_sessions(&contents[..len]).then_some(len)
+}
+
+/// Returns `true` if `contents` is a valid session log.
+fn is_valid_sessions(contents: &str) -> bool {
+    read_sessions_from(contents.as_bytes()).is_ok()
+}
+
+/// Returns `true` if `contents` is a valid `hours.txt` file in the `mode`.
+fn is_valid_hours(contents: &str, mode: ParseMode) -> bool {
+    parse_hours(contents, mode).is_ok()
+}
+
+/// Returns `true` if `contents` is a valid `checkpoint.json` file.
+fn is_valid_checkpoint(contents: &str) -> bool {
+    serde_json::from_str::<Checkpoint>(contents).is_ok()
+}
+
+/// Returns `true` if `contents` is a valid `schema.json` file.
+fn is_valid_schema(contents: &str) -> bool {
+    serde_json::from_str::<Schema>(contents).is_ok()
+}
@@ -0,0 +1,507 @@
+//! This module contains the dashboard server, which serves the website on localhost along with a JSON API and the
+//! live status of the tracker.
+//!
+//! The server is off by default, and is started alongside the tracker when it is enabled in `config.toml`. It only
+//! listens on `127.0.0.1`, so the dashboard can not be reached from other machines:
+//!
+//! ```toml
+//! [server]
+//! enabled = true
+//! port = 8787
+//! ```
+//!
+//! The server has the following routes:
+//! - `/` redirects to the website, which is served from the website folder of the data folder.
+//! - `/live` shows the elapsed time of the current session in large text, and updates itself from `/api/live`.
+//! - `/api/totals` returns the totals of every game as JSON.
+//! - `/api/sessions` returns the sessions of every game as JSON, newest first. They can be filtered by the `game` id,
+//!   the `from` and `to` dates, and the number of sessions can be limited by `limit`, such as
+//!   `/api/sessions?game=rocket-league&from=2024-09-01&limit=10`.
+//! - `/api/status` returns the [`LiveStatus`] of the tracker as JSON.
+//! - `/api/live` streams the [`LiveStatus`] as Server-Sent Events, with a `status` event every time it changes, so a
+//!   page on a second monitor can show the elapsed time of the current session.
+use crate::{
+    calculate_past_two::Window,
+    data_dir::DataDir,
+    error::{TrackerError, TrackerResult},
+    games::Game,
+    hours::summarise_windows,
+    session::Session,
+    state::TrackerState,
+    store::open_store,
+    tracker::{Subscriber, TrackerEvent, TrackerHandle},
+};
+use axum::{
+    extract::{Path as UrlPath, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Redirect, Response,
+    },
+    routing::get,
+    Json, Router,
+};
+use chrono::NaiveDate;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    convert::Infallible,
+    fs,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Component, Path},
+    thread::{self, JoinHandle},
+};
+use tokio::{net::TcpListener, runtime::Runtime, sync::watch, task};
+use tokio_stream::{wrappers::WatchStream, Stream, StreamExt};
+
+/// The page which shows the live status of the tracker, such as on a second monitor.
+const LIVE_PAGE: &str = include_str!("../website/live.html");
+
+/// The settings of the dashboard server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// Whether the server is started alongside the tracker.
+    pub enabled: bool,
+    /// The port which the server listens on.
+    pub port: u16,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            enabled: false,
+            port: 8787,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Returns the address which the server listens on, which is always on localhost.
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, self.port))
+    }
+
+    /// Returns the url of the website on the server.
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.address())
+    }
+}
+
+/// The live status of the tracker, which is shown by the dashboard.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LiveStatus {
+    /// The id of the game which is being tracked, if a session is in progress.
+    pub game_id: Option<String>,
+    /// The name of the game which is being tracked, if a session is in progress.
+    pub game: Option<String>,
+    /// The time counted in the current session in seconds.
+    pub elapsed: u64,
+    /// Whether the player is idle.
+    pub idle: bool,
+    /// Whether the tracker is paused.
+    pub paused: bool,
+}
+
+/// A [`Subscriber`] which keeps the [`LiveStatus`] of the tracker up to date.
+#[derive(Debug, Clone)]
+pub struct LiveSubscriber {
+    sender: watch::Sender<LiveStatus>,
+}
+
+impl Default for LiveSubscriber {
+    fn default() -> Self {
+        LiveSubscriber::new()
+    }
+}
+
+impl LiveSubscriber {
+    /// Creates a new subscriber, whose status has no session in progress.
+    pub fn new() -> LiveSubscriber {
+        LiveSubscriber {
+            sender: watch::Sender::new(LiveStatus::default()),
+        }
+    }
+
+    /// Returns a receiver of the status, which is woken up every time it changes.
+    pub fn status(&self) -> watch::Receiver<LiveStatus> {
+        self.sender.subscribe()
+    }
+}
+
+impl Subscriber for LiveSubscriber {
+    fn on_event(&mut self, event: &TrackerEvent) {
+        self.sender.send_if_modified(|status| {
+            let next = match event {
+                TrackerEvent::GameStarted { game, .. } => LiveStatus {
+                    game_id: Some(game.id.clone()),
+                    game: Some(game.name.clone()),
+                    ..LiveStatus::default()
+                },
+                TrackerEvent::Tick {
+                    game,
+                    elapsed,
+                    idle,
+                    paused,
+                } => LiveStatus {
+                    game_id: Some(game.id.clone()),
+                    game: Some(game.name.clone()),
+                    elapsed: *elapsed,
+                    idle: *idle,
+                    paused: *paused,
+                },
+                TrackerEvent::Paused { .. } => LiveStatus {
+                    paused: true,
+                    ..status.clone()
+                },
+                TrackerEvent::Resumed { .. } => LiveStatus {
+                    paused: false,
+                    ..status.clone()
+                },
+                TrackerEvent::SessionEnded { .. } => LiveStatus::default(),
+                _ => return false,
+            };
+
+            let changed = *status != next;
+            *status = next;
+            changed
+        });
+    }
+}
+
+/// The totals of a game, which are returned by `/api/totals`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameTotals {
+    /// The id of the game.
+    pub id: String,
+    /// The name of the game.
+    pub name: String,
+    /// The total time played in seconds.
+    pub total_seconds: u64,
+    /// The time the player was idle in seconds.
+    pub idle_seconds: u64,
+    /// The time played in the past two weeks in seconds.
+    pub past_two_seconds: u64,
+    /// The time played in seconds in each of the other windows in `config.toml`.
+    pub windows: Vec<WindowTotal>,
+}
+
+/// The time played in a window, such as `"7d"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WindowTotal {
+    /// The window.
+    pub window: Window,
+    /// The name of the window, such as "Past 7 Days".
+    pub label: String,
+    /// The time played in the window in seconds.
+    pub seconds: u64,
+}
+
+/// A session with its game, which is returned by `/api/sessions`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GameSession {
+    /// The id of the game.
+    pub game: String,
+    /// The session.
+    pub session: Session,
+}
+
+/// The query of `/api/sessions`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct SessionsQuery {
+    /// Only returns the sessions of the game with the id.
+    pub game: Option<String>,
+    /// Only returns the sessions which were played on or after the date.
+    pub from: Option<NaiveDate>,
+    /// Only returns the sessions which were played on or before the date.
+    pub to: Option<NaiveDate>,
+    /// The most sessions which are returned.
+    pub limit: Option<usize>,
+}
+
+/// The data which the routes of the server read.
+#[derive(Debug, Clone)]
+pub struct ServerState {
+    data_dir: DataDir,
+    games: Vec<Game>,
+    windows: Vec<Window>,
+    live: watch::Receiver<LiveStatus>,
+}
+
+impl ServerState {
+    /// Creates the state of a server which serves the `games` in the `data_dir` with their `windows`, and the `live`
+    /// status of the tracker.
+    pub fn new(
+        data_dir: DataDir,
+        games: Vec<Game>,
+        windows: Vec<Window>,
+        live: watch::Receiver<LiveStatus>,
+    ) -> ServerState {
+        ServerState {
+            data_dir,
+            games,
+            windows,
+            live,
+        }
+    }
+}
+
+/// The errors of the API, which are returned as JSON with a status code.
+struct ApiError(TrackerError);
+
+impl From<TrackerError> for ApiError {
+    fn from(e: TrackerError) -> Self {
+        ApiError(e)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match self.0 {
+            TrackerError::UnknownGame(_) => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (
+            status,
+            Json(serde_json::json!({ "error": self.0.to_string() })),
+        )
+            .into_response()
+    }
+}
+
+/// Returns the router of the server, with every route.
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(|| async { Redirect::temporary("/pages/index.html") }),
+        )
+        .route("/live", get(|| async { Html(LIVE_PAGE) }))
+        .route("/api/totals", get(totals))
+        .route("/api/sessions", get(sessions))
+        .route("/api/status", get(status))
+        .route("/api/live", get(live))
+        .route("/{*path}", get(website_file))
+        .with_state(state)
+}
+
+/// This function serves the router on the `listener` until the `tracker` shuts down.
+///
+/// # Errors
+/// Returns a [`TrackerError::Io`] if the server failed.
+pub async fn serve(
+    listener: TcpListener,
+    state: ServerState,
+    tracker: TrackerHandle,
+) -> TrackerResult<()> {
+    let mut tracker_state = tracker.subscribe();
+
+    axum::serve(listener, router(state))
+        .with_graceful_shutdown(async move {
+            let _ = tracker_state
+                .wait_for(|state| *state == TrackerState::Stopped)
+                .await;
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// This function starts the server of the `config` on a new thread, which runs until the `tracker` shuts down.
+///
+/// # Errors
+/// Returns a [`TrackerError::Io`] if the runtime could not be created, or the port is already in use.
+pub fn spawn(
+    config: &ServerConfig,
+    state: ServerState,
+    tracker: TrackerHandle,
+) -> TrackerResult<JoinHandle<()>> {
+    let rt = Runtime::new()?;
+    let listener = rt.block_on(TcpListener::bind(config.address()))?;
+
+    info!("serving the dashboard on {}", config.url());
+
+    Ok(thread::spawn(move || {
+        rt.block_on(serve(listener, state, tracker))
+            .unwrap_or_else(|e| error!("the dashboard server stopped: {e}"));
+    }))
+}
+
+/// Runs the blocking file and database work `f` of a request on the blocking thread pool, so it does not hold up the
+/// live status stream.
+async fn blocking<T, F>(f: F) -> TrackerResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> TrackerResult<T> + Send + 'static,
+{
+    task::spawn_blocking(f).await?
+}
+
+/// Returns the totals of every game which has a data folder.
+async fn totals(State(state): State<ServerState>) -> Result<Json<Vec<GameTotals>>, ApiError> {
+    Ok(Json(blocking(move || game_totals(&state)).await?))
+}
+
+/// Reads the totals of every game which has a data folder.
+fn game_totals(state: &ServerState) -> TrackerResult<Vec<GameTotals>> {
+    let mut totals = Vec::new();
+
+    for game in &state.games {
+        let game_dir = game.data_dir(&state.data_dir);
+
+        if !game_dir.root().is_dir() {
+            continue;
+        }
+
+        let summary = summarise_windows(&game_dir, &state.windows)?;
+
+        totals.push(GameTotals {
+            id: game.id.clone(),
+            name: game.name.clone(),
+            total_seconds: summary.total_seconds,
+            idle_seconds: summary.idle_seconds,
+            past_two_seconds: summary.past_two_seconds,
+            windows: summary
+                .windows
+                .iter()
+                .map(|(window, seconds)| WindowTotal {
+                    window: *window,
+                    label: window.label(),
+                    seconds: *seconds,
+                })
+                .collect(),
+        });
+    }
+
+    Ok(totals)
+}
+
+/// Returns the sessions of the games which match the `query`, newest first.
+async fn sessions(
+    State(state): State<ServerState>,
+    Query(query): Query<SessionsQuery>,
+) -> Result<Json<Vec<GameSession>>, ApiError> {
+    Ok(Json(blocking(move || game_sessions(&state, &query)).await?))
+}
+
+/// Reads the sessions of the games which match the `query`, newest first.
+fn game_sessions(state: &ServerState, query: &SessionsQuery) -> TrackerResult<Vec<GameSession>> {
+    if let Some(id) = &query.game {
+        if !state.games.iter().any(|game| &game.id == id) {
+            return Err(TrackerError::UnknownGame(id.clone()));
+        }
+    }
+
+    let mut sessions = Vec::new();
+
+    for game in &state.games {
+        let game_dir = game.data_dir(&state.data_dir);
+
+        if query.game.as_ref().is_some_and(|id| id != &game.id) || !game_dir.root().is_dir() {
+            continue;
+        }
+
+        let store = open_store(&game_dir)?;
+
+        // The store only narrows down the sessions by date when both dates are set
+        let game_sessions = match (query.from, query.to) {
+            (Some(from), Some(to)) => store.sessions_between(from, to)?,
+            (from, to) => store
+                .sessions()?
+                .into_iter()
+                .filter(|session| {
+                    session.played_between(
+                        from.unwrap_or(NaiveDate::MIN),
+                        to.unwrap_or(NaiveDate::MAX),
+                    )
+                })
+                .collect(),
+        };
+
+        for session in game_sessions {
+            sessions.push(GameSession {
+                game: game.id.clone(),
+                session,
+            });
+        }
+    }
+
+    sessions.sort_by_key(|game_session| Reverse(game_session.session.end));
+
+    if let Some(limit) = query.limit {
+        sessions.truncate(limit);
+    }
+
+    Ok(sessions)
+}
+
+/// Returns the current [`LiveStatus`].
+async fn status(State(state): State<ServerState>) -> Json<LiveStatus> {
+    Json(state.live.borrow().clone())
+}
+
+/// Streams the [`LiveStatus`], starting with the current status.
+async fn live(
+    State(state): State<ServerState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = WatchStream::new(state.live).map(|status| {
+        Ok(Event::default()
+            .event("status")
+            .json_data(status)
+            .unwrap_or_default())
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Serves a file of the website folder. Paths which leave the website folder are not found.
+async fn website_file(
+    State(state): State<ServerState>,
+    UrlPath(path): UrlPath<String>,
+) -> Response {
+    let relative = Path::new(&path);
+
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let file = state.data_dir.website_dir().join(relative);
+    let read = blocking(move || {
+        if !file.is_file() {
+            return Ok(None);
+        }
+
+        Ok(Some(fs::read(file)?))
+    });
+
+    match read.await {
+        Ok(Some(bytes)) => {
+            ([(header::CONTENT_TYPE, content_type(relative))], bytes).into_response()
+        }
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => ApiError(e).into_response(),
+    }
+}
+
+/// Returns the content type of the file at `path` from its extension.
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("ico") => "image/x-icon",
+        Some("woff2") => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
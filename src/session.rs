@@ -0,0 +1,403 @@
+//! This module contains the [`Session`] record, which describes a single play session of Rocket League.
+//!
+//! Sessions are stored in `sessions.jsonl` inside the data directory, with one JSON object per line.
+//! Each record is versioned, so the format can change in the future without breaking older files.
+//! Sessions from the legacy `date.txt` file are converted by the [`crate::migrate`] module.
+//!
+//! The timestamps are stored in UTC, and `offset` is the offset of the timezone the session was recorded in. Records
+//! from version 1 have no `offset`, and their timestamps are stored with the offset instead.
+//!
+//! The spans in which the player was idle are stored in `idle`, and are not counted in the `duration`. The field is
+//! left out of sessions without any idle time.
+//!
+//! A session which spans midnight is credited to every calendar day it covered by [`Session::days`], in proportion
+//! to the time the player was active on each day, so a session from 22:00 to 02:00 counts two hours on each day. The
+//! days are taken in the reporting [`Timezone`].
+//!
+//! ```json
+//! {"version":2,"start":"2024-09-15T17:02:11Z","end":"2024-09-15T18:30:45Z","offset":"+01:00","duration":5314,"end_reason":"process_exit","app_version":"0.4.4"}
+//! {"version":2,"start":"2024-09-16T17:00:00Z","end":"2024-09-16T19:00:00Z","offset":"+01:00","duration":3600,"end_reason":"process_exit","app_version":"0.4.4","idle":[{"start":"2024-09-16T18:00:00Z","end":"2024-09-16T19:00:00Z"}]}
+//! ```
+use crate::{
+    atomic_file::append_synced,
+    error::TrackerResult,
+    timezone::{self, Timezone},
+};
+use chrono::{DateTime, FixedOffset, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader, ErrorKind},
+    path::Path,
+};
+
+/// The current version of the [`Session`] record.
+pub const SESSION_VERSION: u32 = 2;
+
+/// The reason a session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    /// The game process was closed.
+    ProcessExit,
+    /// The user clicked "Stop Tracker" in the tray menu.
+    StopTracker,
+    /// The tracker was closed before the session ended, and the session was recovered from its last checkpoint.
+    Recovered,
+    /// The session was migrated from the legacy `date.txt` file, which only stored the date and the duration.
+    Legacy,
+}
+
+/// A span of a session in which the player was idle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdleSpan {
+    /// When the player became idle.
+    #[serde(with = "utc")]
+    pub start: DateTime<FixedOffset>,
+    /// When the player was active again, or the session ended.
+    #[serde(with = "utc")]
+    pub end: DateTime<FixedOffset>,
+}
+
+impl IdleSpan {
+    /// Creates a new span from `start` to `end`.
+    pub fn new(start: DateTime<Local>, end: DateTime<Local>) -> IdleSpan {
+        IdleSpan {
+            start: start.fixed_offset(),
+            end: end.fixed_offset(),
+        }
+    }
+
+    /// Returns the length of the span in seconds.
+    pub fn seconds(&self) -> u64 {
+        (self.end - self.start).num_seconds().max(0) as u64
+    }
+}
+
+/// A single play session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "SessionRecord", into = "SessionRecord")]
+pub struct Session {
+    /// The version of the record format.
+    pub version: u32,
+    /// When the session started, with the offset of the timezone it was recorded in.
+    pub start: DateTime<FixedOffset>,
+    /// When the session ended, with the offset of the timezone it was recorded in.
+    pub end: DateTime<FixedOffset>,
+    /// The time played in seconds.
+    pub duration: u64,
+    /// Why the session ended.
+    pub end_reason: EndReason,
+    /// The version of the program which recorded the session.
+    pub app_version: String,
+    /// The spans in which the player was idle, which are not counted in the `duration`.
+    pub idle: Vec<IdleSpan>,
+}
+
+/// The format of a [`Session`] in `sessions.jsonl`, where the timestamps are in UTC.
+#[derive(Serialize, Deserialize)]
+struct SessionRecord {
+    version: u32,
+    #[serde(with = "utc")]
+    start: DateTime<FixedOffset>,
+    #[serde(with = "utc")]
+    end: DateTime<FixedOffset>,
+    /// The offset the session was recorded in, which is left out of version 1 records.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "offset")]
+    offset: Option<FixedOffset>,
+    duration: u64,
+    end_reason: EndReason,
+    app_version: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    idle: Vec<IdleSpan>,
+}
+
+impl From<Session> for SessionRecord {
+    fn from(session: Session) -> Self {
+        // The record is always written in the current format
+        SessionRecord {
+            version: SESSION_VERSION,
+            offset: Some(*session.start.offset()),
+            start: session.start,
+            end: session.end,
+            duration: session.duration,
+            end_reason: session.end_reason,
+            app_version: session.app_version,
+            idle: session.idle,
+        }
+    }
+}
+
+impl From<SessionRecord> for Session {
+    fn from(record: SessionRecord) -> Self {
+        // Version 1 records keep the offsets in their timestamps
+        let at_offset = |instant: DateTime<FixedOffset>| match record.offset {
+            Some(offset) => instant.with_timezone(&offset),
+            None => instant,
+        };
+
+        Session {
+            version: record.version,
+            start: at_offset(record.start),
+            end: at_offset(record.end),
+            duration: record.duration,
+            end_reason: record.end_reason,
+            app_version: record.app_version.clone(),
+            idle: record
+                .idle
+                .iter()
+                .map(|span| IdleSpan {
+                    start: at_offset(span.start),
+                    end: at_offset(span.end),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Writes the timestamps of the records in UTC, and reads timestamps with any offset.
+pub(crate) mod utc {
+    use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        instant: &DateTime<FixedOffset>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(
+            &instant
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<FixedOffset>, D::Error> {
+        DateTime::<FixedOffset>::deserialize(deserializer)
+    }
+}
+
+/// Writes an offset as `+01:00`.
+mod offset {
+    use chrono::FixedOffset;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        offset: &Option<FixedOffset>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match offset {
+            Some(offset) => serializer.serialize_str(&offset.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<FixedOffset>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|offset| offset.parse().map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+impl Session {
+    /// Creates a new session recorded by the current version of the program.
+    pub fn new(
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        duration: u64,
+        end_reason: EndReason,
+    ) -> Session {
+        Session {
+            version: SESSION_VERSION,
+            start: start.fixed_offset(),
+            end: end.fixed_offset(),
+            duration,
+            end_reason,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            idle: Vec::new(),
+        }
+    }
+
+    /// Returns the date the session ended on, in the timezone it was recorded in. The days of the session in the
+    /// reporting timezone are returned by [`Session::days`].
+    pub fn date(&self) -> NaiveDate {
+        self.end.date_naive()
+    }
+
+    /// Returns the time the player was idle in seconds.
+    pub fn idle_seconds(&self) -> u64 {
+        self.idle.iter().map(IdleSpan::seconds).sum()
+    }
+
+    /// Returns the time played on each calendar day the session covered in the reporting timezone, which is
+    /// set by [`timezone::init`], ordered by date. See [`Session::days_in`].
+    pub fn days(&self) -> Vec<(NaiveDate, u64)> {
+        self.days_in(&timezone::current())
+    }
+
+    /// Returns the time played on each calendar day the session covered in the `timezone`, ordered by date. The
+    /// `duration` is split in proportion to the time the player was active on each day, and the seconds always add
+    /// up to the `duration`. Legacy sessions only know their date, so they are credited to the date they ended on.
+    pub fn days_in(&self, timezone: &Timezone) -> Vec<(NaiveDate, u64)> {
+        if self.end_reason == EndReason::Legacy {
+            return vec![(self.date(), self.duration)];
+        }
+
+        let end_date = timezone.convert(self.end).date_naive();
+
+        if self.end <= self.start {
+            return vec![(end_date, self.duration)];
+        }
+
+        // Split the session at every midnight between its start and end
+        let mut active = Vec::new();
+        let mut day_start = self.start;
+
+        while day_start < self.end {
+            let date = timezone.convert(day_start).date_naive();
+            let midnight = timezone.next_midnight(day_start).min(self.end);
+
+            let idle: i64 = self
+                .idle
+                .iter()
+                .map(|span| {
+                    (span.end.min(midnight) - span.start.max(day_start))
+                        .num_seconds()
+                        .max(0)
+                })
+                .sum();
+
+            active.push((
+                date,
+                ((midnight - day_start).num_seconds() - idle).max(0) as u64,
+            ));
+            day_start = midnight;
+        }
+
+        let active_seconds: u64 = active.iter().map(|(_, seconds)| seconds).sum();
+
+        if active_seconds == 0 {
+            return vec![(end_date, self.duration)];
+        }
+
+        // Split the duration in proportion, where the last day with any activity gets the remainder
+        let mut days: Vec<(NaiveDate, u64)> = active
+            .into_iter()
+            .filter(|(_, seconds)| *seconds > 0)
+            .map(|(date, seconds)| {
+                let share = self.duration as u128 * seconds as u128 / active_seconds as u128;
+
+                (date, share as u64)
+            })
+            .collect();
+
+        let credited: u64 = days.iter().map(|(_, seconds)| seconds).sum();
+
+        if let Some((_, seconds)) = days.last_mut() {
+            *seconds += self.duration - credited;
+        }
+
+        days
+    }
+
+    /// Returns the time played between `from` and `to` inclusive, as split between the days by [`Session::days`].
+    pub fn seconds_between(&self, from: NaiveDate, to: NaiveDate) -> u64 {
+        self.seconds_between_in(from, to, &timezone::current())
+    }
+
+    /// Returns the time played between `from` and `to` inclusive, as split between the days in the `timezone` by
+    /// [`Session::days_in`].
+    pub fn seconds_between_in(&self, from: NaiveDate, to: NaiveDate, timezone: &Timezone) -> u64 {
+        self.days_in(timezone)
+            .into_iter()
+            .filter(|(date, _)| (from..=to).contains(date))
+            .map(|(_, seconds)| seconds)
+            .sum()
+    }
+
+    /// Returns `true` if any of the days the session covered are between `from` and `to` inclusive.
+    pub fn played_between(&self, from: NaiveDate, to: NaiveDate) -> bool {
+        self.days()
+            .iter()
+            .any(|(date, _)| (from..=to).contains(date))
+    }
+}
+
+impl Display for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Legacy sessions do not have a meaningful start and end time
+        if self.end_reason == EndReason::Legacy {
+            return write!(f, "{} {}s", self.date(), self.duration);
+        }
+
+        write!(
+            f,
+            "{} {} - {} {}s",
+            self.date(),
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.duration
+        )?;
+
+        if !self.idle.is_empty() {
+            write!(f, " ({}s idle)", self.idle_seconds())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Appends `session` as a new line to the session log at `path` through [`append_synced`], which only writes the
+/// new line. A line which was cut off by a crash is removed on the next startup by [`crate::recovery`]. The file is
+/// created if it does not exist.
+///
+/// # Errors
+/// Returns an [`std::io::Error`] if the file could not be opened or written to, or a [`serde_json::Error`]
+/// if the session could not be serialized.
+pub fn append_session(path: &Path, session: &Session) -> TrackerResult<()> {
+    let mut line = serde_json::to_string(session)?;
+    line.push('\n');
+
+    append_synced(path, line)?;
+
+    Ok(())
+}
+
+/// Reads every session from the session log at `path`. An empty [`Vec`] is returned if the file
+/// does not exist.
+///
+/// # Errors
+/// Returns an [`std::io::Error`] if the file could not be read, or a [`serde_json::Error`] if a line
+/// is not a valid session.
+pub fn read_sessions(path: &Path) -> TrackerResult<Vec<Session>> {
+    match File::open(path) {
+        Ok(file) => read_sessions_from(BufReader::new(file)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Reads every session from `reader`, which contains one JSON session per line. Blank lines are ignored.
+///
+/// # Errors
+/// Returns an [`std::io::Error`] if the reader failed, or a [`serde_json::Error`] if a line is not a
+/// valid session.
+pub fn read_sessions_from<R: BufRead>(reader: R) -> TrackerResult<Vec<Session>> {
+    let mut sessions = Vec::new();
+
+    for line in reader.lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        sessions.push(serde_json::from_str(&line)?);
+    }
+
+    Ok(sessions)
+}
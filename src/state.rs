@@ -0,0 +1,164 @@
+//! This module contains the state machine of the tracker, which is shared between the tracker thread, the tray icon
+//! and the signal handler.
+//!
+//! The [`TrackerState`] is only changed by a [`Trigger`], through [`TrackerState::next`]:
+//!
+//! | State                 | Trigger           | Next state                   |
+//! |-----------------------|-------------------|------------------------------|
+//! | `Waiting`             | `GameStarted`     | `Tracking`                   |
+//! | `Waiting`             | `Shutdown`        | `Stopped`                    |
+//! | `Tracking`            | `Pause`           | `Paused`                     |
+//! | `Paused`              | `Resume`          | `Tracking`                   |
+//! | `Tracking`, `Paused`  | `GameClosed`      | `Stopping`                   |
+//! | `Tracking`, `Paused`  | `Stop`            | `Ending`                     |
+//! | `Tracking`, `Paused`  | `Shutdown`        | `Stopping`, then shut down   |
+//! | `Stopping`, `Ending`  | `Shutdown`        | `Stopping`, then shut down   |
+//! | `Stopping`            | `SessionRecorded` | `Waiting`, or `Stopped`      |
+//! | `Ending`              | `SessionRecorded` | `WaitingForExit`             |
+//! | `WaitingForExit`      | `GameClosed`      | `Waiting`                    |
+//! | `WaitingForExit`      | `Shutdown`        | `Stopped`                    |
+//!
+//! Every other trigger leaves the state as it is. The process watcher triggers `GameStarted` and `GameClosed`, the
+//! "Stop Tracker" item of the tray menu triggers `Stop`, and "Exit" or a signal such as Ctrl+C triggers `Shutdown`.
+//! A session which was stopped while the game is still running does not start again straight away: the tracker
+//! waits in `WaitingForExit` until the game is closed.
+//! The state is shared through a [`watch`] channel, so every [`SharedState`] sees the same state, and a
+//! [`watch::Receiver`] is woken up when it changes.
+use log::trace;
+use tokio::sync::watch;
+
+/// The states of the tracker.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrackerState {
+    /// No game is running, and the tracker is waiting for one to start.
+    #[default]
+    Waiting,
+    /// A session is in progress.
+    Tracking,
+    /// A session is in progress, but its clock is paused.
+    Paused,
+    /// The session ended and is being recorded. The tracker shuts down afterwards if `shutdown` is set.
+    Stopping { shutdown: bool },
+    /// The session was stopped with [`Trigger::Stop`] and is being recorded, while the game may still be running.
+    Ending,
+    /// The session was stopped while the game is still running. No session is started until the game is closed.
+    WaitingForExit,
+    /// The tracker shut down. This state is never left.
+    Stopped,
+}
+
+/// The triggers which change the [`TrackerState`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// The process watcher found a running game.
+    GameStarted,
+    /// The process watcher found that the game was closed.
+    GameClosed,
+    /// The clock of the session was paused.
+    Pause,
+    /// The clock of the session was resumed.
+    Resume,
+    /// The session was stopped from the tray menu.
+    Stop,
+    /// The program was asked to quit from the tray menu or by a signal.
+    Shutdown,
+    /// The session which was stopped was recorded.
+    SessionRecorded,
+}
+
+impl TrackerState {
+    /// Returns the state after the `trigger`. A trigger which does not apply to the state leaves it as it is.
+    pub fn next(self, trigger: Trigger) -> TrackerState {
+        use TrackerState::*;
+
+        match (self, trigger) {
+            (Waiting, Trigger::GameStarted) => Tracking,
+            (Waiting, Trigger::Shutdown) => Stopped,
+            (Tracking, Trigger::Pause) => Paused,
+            (Paused, Trigger::Resume) => Tracking,
+            (Tracking | Paused, Trigger::GameClosed) => Stopping { shutdown: false },
+            (Tracking | Paused, Trigger::Stop) => Ending,
+            (Tracking | Paused | Stopping { .. } | Ending, Trigger::Shutdown) => {
+                Stopping { shutdown: true }
+            }
+            (Stopping { shutdown: false }, Trigger::SessionRecorded) => Waiting,
+            (Stopping { shutdown: true }, Trigger::SessionRecorded) => Stopped,
+            (Ending, Trigger::SessionRecorded) => WaitingForExit,
+            (WaitingForExit, Trigger::GameClosed) => Waiting,
+            (WaitingForExit, Trigger::Shutdown) => Stopped,
+            (state, _) => state,
+        }
+    }
+
+    /// Returns whether a session is in progress, which is the case until it was recorded.
+    pub fn is_tracking(self) -> bool {
+        matches!(
+            self,
+            TrackerState::Tracking
+                | TrackerState::Paused
+                | TrackerState::Stopping { .. }
+                | TrackerState::Ending
+        )
+    }
+
+    /// Returns whether the clock of the session is running or paused, rather than the session being stopped.
+    pub fn is_active(self) -> bool {
+        matches!(self, TrackerState::Tracking | TrackerState::Paused)
+    }
+
+    /// Returns whether the tracker shut down, or shuts down once the session is recorded.
+    pub fn is_shutting_down(self) -> bool {
+        matches!(
+            self,
+            TrackerState::Stopping { shutdown: true } | TrackerState::Stopped
+        )
+    }
+}
+
+/// The [`TrackerState`] which is shared between threads. Every clone shares the same state.
+#[derive(Debug, Clone)]
+pub struct SharedState {
+    sender: watch::Sender<TrackerState>,
+}
+
+impl Default for SharedState {
+    fn default() -> Self {
+        SharedState::new()
+    }
+}
+
+impl SharedState {
+    /// Creates a new state, which is [`TrackerState::Waiting`].
+    pub fn new() -> SharedState {
+        SharedState {
+            sender: watch::Sender::new(TrackerState::Waiting),
+        }
+    }
+
+    /// Returns the current state.
+    pub fn get(&self) -> TrackerState {
+        *self.sender.borrow()
+    }
+
+    /// Changes the state by the `trigger`, and returns the new state. The receivers are only notified if the state
+    /// changed.
+    pub fn fire(&self, trigger: Trigger) -> TrackerState {
+        let mut next = TrackerState::Waiting;
+
+        self.sender.send_if_modified(|state| {
+            next = state.next(trigger);
+            trace!("<< fn fire >> {state:?} + {trigger:?} -> {next:?}");
+
+            let changed = *state != next;
+            *state = next;
+            changed
+        });
+
+        next
+    }
+
+    /// Returns a receiver which is woken up every time the state changes.
+    pub fn subscribe(&self) -> watch::Receiver<TrackerState> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,492 @@
+//! This module contains the [`SessionStore`] trait, which provides typed queries over the recorded sessions.
+//!
+//! The sessions are always appended to `sessions.jsonl`, which is read by the [`JsonlStore`]. When the program
+//! is built with the `sqlite` feature, the [`SqliteStore`] is used instead, which keeps the sessions in
+//! `sessions.db` with indexed timestamps. The database is kept in sync with `sessions.jsonl`, and is rebuilt
+//! from it whenever the two differ.
+//!
+//! The database narrows down the sessions of a period by their indexed timestamps, but the totals are still added
+//! up in Rust by [`group_totals_between`]. A session which spans midnight is split between the days it covered in
+//! the reporting timezone, which the UTC timestamps of the database can not group by.
+//!
+//! ```no_run
+//! use chrono::NaiveDate;
+//! use rl_hours_tracker::{data_dir::DataDir, store::{open_store, Grouping}};
+//!
+//! let store = open_store(DataDir::current())?;
+//!
+//! let from = NaiveDate::from_ymd_opt(2024, 9, 1).unwrap();
+//! let to = NaiveDate::from_ymd_opt(2024, 9, 30).unwrap();
+//!
+//! for total in store.totals(Grouping::Week, from, to)? {
+//!     println!("{}: {}s", total.period_start, total.seconds);
+//! }
+//! # Ok::<(), rl_hours_tracker::error::TrackerError>(())
+//! ```
+use crate::{
+    data_dir::DataDir,
+    error::TrackerResult,
+    session::{append_session, read_sessions, Session},
+};
+use chrono::{Datelike, Duration as CDuration, NaiveDate};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// How session totals are grouped by [`SessionStore::totals`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// One total per calendar day.
+    Day,
+    /// One total per week, starting on Monday.
+    Week,
+    /// One total per calendar month.
+    Month,
+}
+
+impl Grouping {
+    /// Returns the first day of the period which contains `date`.
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Grouping::Day => date,
+            Grouping::Week => date - CDuration::days(date.weekday().num_days_from_monday() as i64),
+            Grouping::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+}
+
+/// The time played in a single period, returned by [`SessionStore::totals`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Total {
+    /// The first day of the period.
+    pub period_start: NaiveDate,
+    /// The time played in seconds.
+    pub seconds: u64,
+    /// The number of sessions in the period.
+    pub sessions: usize,
+}
+
+/// Storage for the recorded sessions. A session which spans midnight counts towards every day it covered, as split
+/// by [`Session::days`].
+pub trait SessionStore {
+    /// Adds a new session to the store.
+    fn add(&mut self, session: &Session) -> TrackerResult<()>;
+
+    /// Returns every session, oldest first.
+    fn sessions(&self) -> TrackerResult<Vec<Session>>;
+
+    /// Returns the sessions which were played on any day between `from` and `to` inclusive, oldest first.
+    /// Only part of the time of a session which spans midnight may be in the period, which is found with
+    /// [`Session::seconds_between`].
+    fn sessions_between(&self, from: NaiveDate, to: NaiveDate) -> TrackerResult<Vec<Session>> {
+        Ok(self
+            .sessions()?
+            .into_iter()
+            .filter(|session| session.played_between(from, to))
+            .collect())
+    }
+
+    /// Returns the latest `count` sessions, newest first.
+    fn latest(&self, count: usize) -> TrackerResult<Vec<Session>> {
+        Ok(self.sessions()?.into_iter().rev().take(count).collect())
+    }
+
+    /// Returns the time played between `from` and `to` inclusive, grouped by `grouping`.
+    /// Periods without any sessions are left out.
+    fn totals(
+        &self,
+        grouping: Grouping,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> TrackerResult<Vec<Total>> {
+        Ok(group_totals_between(
+            &self.sessions_between(from, to)?,
+            grouping,
+            from,
+            to,
+        ))
+    }
+}
+
+/// Groups the `sessions` into [`Total`]s, ordered by period. A session which covered several periods counts
+/// towards each of them.
+pub fn group_totals(sessions: &[Session], grouping: Grouping) -> Vec<Total> {
+    group_totals_between(sessions, grouping, NaiveDate::MIN, NaiveDate::MAX)
+}
+
+/// Groups the time the `sessions` were played between `from` and `to` inclusive into [`Total`]s, ordered by period.
+/// A session which covered several periods counts towards each of them.
+pub fn group_totals_between(
+    sessions: &[Session],
+    grouping: Grouping,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<Total> {
+    let mut periods: BTreeMap<NaiveDate, Total> = BTreeMap::new();
+
+    for session in sessions {
+        let mut last_period = None;
+
+        for (date, seconds) in session.days() {
+            if !(from..=to).contains(&date) {
+                continue;
+            }
+
+            let period_start = grouping.period_start(date);
+            let total = periods.entry(period_start).or_insert(Total {
+                period_start,
+                seconds: 0,
+                sessions: 0,
+            });
+
+            total.seconds += seconds;
+
+            // Count the session once in every period it covered
+            if last_period != Some(period_start) {
+                total.sessions += 1;
+                last_period = Some(period_start);
+            }
+        }
+    }
+
+    periods.into_values().collect()
+}
+
+/// An in-memory store, which is useful for tests.
+impl SessionStore for Vec<Session> {
+    fn add(&mut self, session: &Session) -> TrackerResult<()> {
+        self.push(session.clone());
+
+        Ok(())
+    }
+
+    fn sessions(&self) -> TrackerResult<Vec<Session>> {
+        Ok(self.clone())
+    }
+}
+
+/// A store which reads and appends to `sessions.jsonl`.
+#[derive(Debug, Clone)]
+pub struct JsonlStore {
+    path: PathBuf,
+}
+
+impl JsonlStore {
+    /// Creates a new instance for the session log at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> JsonlStore {
+        JsonlStore { path: path.into() }
+    }
+}
+
+impl SessionStore for JsonlStore {
+    fn add(&mut self, session: &Session) -> TrackerResult<()> {
+        append_session(&self.path, session)
+    }
+
+    fn sessions(&self) -> TrackerResult<Vec<Session>> {
+        read_sessions(&self.path)
+    }
+}
+
+/// Opens the session store for the `data_dir`. This is a [`SqliteStore`] when the program is built
+/// with the `sqlite` feature, otherwise it is a [`JsonlStore`].
+///
+/// # Errors
+/// Returns an error if the store could not be opened.
+pub fn open_store(data_dir: &DataDir) -> TrackerResult<Box<dyn SessionStore>> {
+    #[cfg(feature = "sqlite")]
+    {
+        Ok(Box::new(SqliteStore::open(data_dir)?))
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    {
+        Ok(Box::new(JsonlStore::new(data_dir.sessions_file())))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::SessionStore;
+    use crate::{
+        data_dir::DataDir,
+        error::TrackerResult,
+        session::{append_session, read_sessions, Session},
+    };
+    use chrono::{NaiveDate, NaiveTime};
+    use log::info;
+    use rusqlite::{params, Connection};
+    use std::{
+        fs::{self, File},
+        io::{Read, Seek, SeekFrom},
+        path::{Path, PathBuf},
+        time::UNIX_EPOCH,
+    };
+
+    /// The version of the tables, which are dropped and rebuilt from the session log when it changes.
+    const TABLES_VERSION: i64 = 3;
+
+    const CREATE_META: &str = "
+        CREATE TABLE IF NOT EXISTS meta (
+            key TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
+        );";
+
+    const CREATE_TABLES: &str = "
+        CREATE TABLE IF NOT EXISTS sessions (
+            id INTEGER PRIMARY KEY,
+            start_utc INTEGER NOT NULL,
+            end_utc INTEGER NOT NULL,
+            duration INTEGER NOT NULL,
+            record TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS sessions_end_utc ON sessions (end_utc);";
+
+    /// How far the query of [`SessionStore::sessions_between`] is widened either side of the dates, in seconds. The
+    /// timestamps are indexed in UTC, and a date in any timezone is less than a day away from the same date in UTC.
+    const QUERY_MARGIN: i64 = 2 * 24 * 60 * 60;
+
+    /// The most of the end of the session log which is read to find its last line.
+    const TAIL_LEN: u64 = 64 * 1024;
+
+    /// A store which keeps the sessions in the `sessions.db` SQLite database.
+    ///
+    /// New sessions are still appended to `sessions.jsonl`, and the database is rebuilt from it when its
+    /// length, modified time or last line differs from when it was last imported. [`SessionStore::totals`] reads
+    /// the sessions of the period from the database and groups them in the same way as the [`JsonlStore`](super::JsonlStore).
+    pub struct SqliteStore {
+        conn: Connection,
+        log_path: PathBuf,
+    }
+
+    impl SqliteStore {
+        /// Opens the database in the `data_dir`, creating it and importing `sessions.jsonl` if needed. A data folder
+        /// which does not exist yet has no sessions, so an empty database is kept in memory instead of creating it.
+        ///
+        /// # Errors
+        /// Returns a [`rusqlite::Error`] if the database could not be opened, or an error if
+        /// `sessions.jsonl` could not be read.
+        pub fn open(data_dir: &DataDir) -> TrackerResult<SqliteStore> {
+            let conn = if data_dir.root().is_dir() {
+                Connection::open(data_dir.database_file())?
+            } else {
+                Connection::open_in_memory()?
+            };
+
+            SqliteStore::with_connection(conn, data_dir.sessions_file())
+        }
+
+        /// Creates a store from an open connection, which stays in sync with the session log at `log_path`.
+        ///
+        /// # Errors
+        /// Returns a [`rusqlite::Error`] if the tables could not be created, or an error if
+        /// the session log could not be read.
+        pub fn with_connection(conn: Connection, log_path: PathBuf) -> TrackerResult<SqliteStore> {
+            conn.execute_batch(CREATE_META)?;
+
+            // Tables from an older version are rebuilt from the session log
+            if meta_value(&conn, "version")? != Some(TABLES_VERSION) {
+                conn.execute_batch("DROP TABLE IF EXISTS sessions; DELETE FROM meta;")?;
+            }
+
+            conn.execute_batch(CREATE_TABLES)?;
+            set_meta_value(&conn, "version", TABLES_VERSION)?;
+
+            let store = SqliteStore { conn, log_path };
+
+            if imported_stamp(&store.conn)? != LogStamp::read(&store.log_path) {
+                store.rebuild()?;
+            }
+
+            Ok(store)
+        }
+
+        /// Replaces every session in the database with the sessions in the session log.
+        ///
+        /// # Errors
+        /// Returns a [`rusqlite::Error`] if the database could not be written, or an error if
+        /// the session log could not be read.
+        pub fn rebuild(&self) -> TrackerResult<()> {
+            info!("rebuilding sessions.db from {}", self.log_path.display());
+
+            let sessions = read_sessions(&self.log_path)?;
+            let tx = self.conn.unchecked_transaction()?;
+
+            tx.execute("DELETE FROM sessions", [])?;
+
+            for session in &sessions {
+                insert(&tx, session)?;
+            }
+
+            set_imported_stamp(&tx, &LogStamp::read(&self.log_path))?;
+            tx.commit()?;
+
+            Ok(())
+        }
+
+        fn query_sessions(
+            &self,
+            sql: &str,
+            params: impl rusqlite::Params,
+        ) -> TrackerResult<Vec<Session>> {
+            let mut statement = self.conn.prepare(sql)?;
+            let records = statement.query_map(params, |row| row.get::<_, String>(0))?;
+
+            let mut sessions = Vec::new();
+
+            for record in records {
+                sessions.push(serde_json::from_str(&record?)?);
+            }
+
+            Ok(sessions)
+        }
+    }
+
+    fn insert(conn: &Connection, session: &Session) -> TrackerResult<()> {
+        conn.execute(
+            "INSERT INTO sessions (start_utc, end_utc, duration, record)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                session.start.timestamp(),
+                session.end.timestamp(),
+                session.duration as i64,
+                serde_json::to_string(session)?
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// The state of the session log when it was last imported, which tells whether it has been changed since.
+    #[derive(Debug, PartialEq, Eq)]
+    struct LogStamp {
+        /// The length of the log in bytes.
+        len: i64,
+        /// When the log was last modified, in nanoseconds since the Unix epoch.
+        modified: i64,
+        /// The hash of the last line of the log.
+        last_line: i64,
+    }
+
+    impl LogStamp {
+        /// Reads the stamp of the session log at `path`, which is zeroed if the log does not exist.
+        fn read(path: &Path) -> LogStamp {
+            let Ok(metadata) = fs::metadata(path) else {
+                return LogStamp {
+                    len: 0,
+                    modified: 0,
+                    last_line: 0,
+                };
+            };
+
+            LogStamp {
+                len: metadata.len() as i64,
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|since| since.as_nanos() as i64)
+                    .unwrap_or(0),
+                last_line: last_line_hash(path, metadata.len()).unwrap_or(0),
+            }
+        }
+    }
+
+    /// Returns the FNV-1a hash of the last line of the session log at `path`, which is `len` bytes long. Only
+    /// the end of the log is read, so a line longer than [`TAIL_LEN`] is hashed from its end.
+    fn last_line_hash(path: &Path, len: u64) -> std::io::Result<i64> {
+        let mut file = File::open(path)?;
+        let mut tail = Vec::new();
+
+        file.seek(SeekFrom::Start(len.saturating_sub(TAIL_LEN)))?;
+        file.take(TAIL_LEN).read_to_end(&mut tail)?;
+
+        let tail = tail.trim_ascii_end();
+        let line = match tail.iter().rposition(|byte| *byte == b'\n') {
+            Some(idx) => &tail[idx + 1..],
+            None => tail,
+        };
+
+        let hash = line.iter().fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        });
+
+        Ok(hash as i64)
+    }
+
+    fn imported_stamp(conn: &Connection) -> Result<LogStamp, rusqlite::Error> {
+        Ok(LogStamp {
+            len: meta_value(conn, "log_len")?.unwrap_or(-1),
+            modified: meta_value(conn, "log_modified")?.unwrap_or(-1),
+            last_line: meta_value(conn, "log_last_line")?.unwrap_or(-1),
+        })
+    }
+
+    fn set_imported_stamp(conn: &Connection, stamp: &LogStamp) -> Result<(), rusqlite::Error> {
+        set_meta_value(conn, "log_len", stamp.len)?;
+        set_meta_value(conn, "log_modified", stamp.modified)?;
+        set_meta_value(conn, "log_last_line", stamp.last_line)
+    }
+
+    fn meta_value(conn: &Connection, key: &str) -> Result<Option<i64>, rusqlite::Error> {
+        let mut statement = conn.prepare("SELECT value FROM meta WHERE key = ?1")?;
+        let mut rows = statement.query(params![key])?;
+
+        match rows.next()? {
+            Some(row) => row.get(0).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn set_meta_value(conn: &Connection, key: &str, value: i64) -> Result<(), rusqlite::Error> {
+        conn.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+
+        Ok(())
+    }
+
+    impl SessionStore for SqliteStore {
+        fn add(&mut self, session: &Session) -> TrackerResult<()> {
+            append_session(&self.log_path, session)?;
+
+            let tx = self.conn.unchecked_transaction()?;
+            insert(&tx, session)?;
+            set_imported_stamp(&tx, &LogStamp::read(&self.log_path))?;
+            tx.commit()?;
+
+            Ok(())
+        }
+
+        fn sessions(&self) -> TrackerResult<Vec<Session>> {
+            self.query_sessions("SELECT record FROM sessions ORDER BY id", [])
+        }
+
+        fn sessions_between(&self, from: NaiveDate, to: NaiveDate) -> TrackerResult<Vec<Session>> {
+            // The UTC timestamps narrow down the sessions, so the index does not depend on the reporting timezone,
+            // and the days they were played on are checked exactly
+            let since = from.and_time(NaiveTime::MIN).and_utc().timestamp() - QUERY_MARGIN;
+            let until = to.and_time(NaiveTime::MIN).and_utc().timestamp() + QUERY_MARGIN;
+
+            let sessions = self.query_sessions(
+                "SELECT record FROM sessions WHERE end_utc >= ?1 AND start_utc <= ?2 ORDER BY id",
+                params![since, until],
+            )?;
+
+            Ok(sessions
+                .into_iter()
+                .filter(|session| session.played_between(from, to))
+                .collect())
+        }
+
+        fn latest(&self, count: usize) -> TrackerResult<Vec<Session>> {
+            self.query_sessions(
+                "SELECT record FROM sessions ORDER BY id DESC LIMIT ?1",
+                params![count as i64],
+            )
+        }
+    }
+}
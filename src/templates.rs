@@ -0,0 +1,121 @@
+//! This module contains the [`Templates`] which the pages of the website are rendered with.
+//!
+//! The pages are rendered by [`minijinja`], a template engine with the syntax of Jinja2. The default templates are
+//! bundled in the binary, and match the layout of the website:
+//! - `base.html` is the layout of every page, with the `title`, `head`, `body_class`, `body` and `footer` blocks.
+//! - `index.html` is the home page, which extends `base.html`.
+//! - `history.html` lists the months of the session history.
+//! - `history_month.html` lists the sessions of a month.
+//!
+//! Any `.html` file in the `templates` folder of the data folder replaces the default template with the same name,
+//! or adds a new template which the others can extend or include. For example, a `base.html` with a different footer
+//! brands every page without changing the others. The default templates are in the `website/templates` folder of
+//! the repository, and are the best starting point for a user template.
+//!
+//! The files in the `theme` folder of the data folder are copied over the website assets, so `theme/css/home.css`
+//! replaces the bundled `home.css`. Any other CSS file of the theme, such as `theme/css/brand.css`, is linked from
+//! every page through the `theme_stylesheets` variable of `base.html`.
+//!
+//! Values are escaped unless they are marked with the `safe` filter, which the default templates only use for the
+//! SVG charts.
+use crate::{data_dir::DataDir, error::TrackerResult};
+use log::info;
+use minijinja::{Environment, Value};
+use serde::Serialize;
+use std::{fs, io::ErrorKind};
+
+/// The default templates, which are bundled in the binary.
+pub const DEFAULT_TEMPLATES: &[(&str, &str)] = &[
+    ("base.html", include_str!("../website/templates/base.html")),
+    (
+        "index.html",
+        include_str!("../website/templates/index.html"),
+    ),
+    (
+        "history.html",
+        include_str!("../website/templates/history.html"),
+    ),
+    (
+        "history_month.html",
+        include_str!("../website/templates/history_month.html"),
+    ),
+];
+
+/// The templates which the pages of the website are rendered with.
+#[derive(Debug)]
+pub struct Templates {
+    env: Environment<'static>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Templates::new()
+    }
+}
+
+impl Templates {
+    /// Creates the [`DEFAULT_TEMPLATES`].
+    pub fn new() -> Templates {
+        let mut env = Environment::new();
+
+        for (name, source) in DEFAULT_TEMPLATES {
+            env.add_template(name, source)
+                .expect("the default templates are valid");
+        }
+
+        env.add_global("theme_stylesheets", Value::from(Vec::<String>::new()));
+
+        Templates { env }
+    }
+
+    /// Creates the [`DEFAULT_TEMPLATES`], and adds the templates in the templates folder of the `data_dir`, which
+    /// replace the default templates with the same name.
+    ///
+    /// # Errors
+    /// Returns a [`TrackerError::Template`](crate::error::TrackerError::Template) if a user template is not valid, or
+    /// a [`TrackerError::Io`](crate::error::TrackerError::Io) if it could not be read.
+    pub fn load(data_dir: &DataDir) -> TrackerResult<Templates> {
+        let mut templates = Templates::new();
+
+        let entries = match fs::read_dir(data_dir.templates_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(templates),
+            Err(e) => return Err(e.into()),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "html") {
+                continue;
+            }
+
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            info!("using the user template {name}");
+            templates
+                .env
+                .add_template_owned(name, fs::read_to_string(&path)?)?;
+        }
+
+        Ok(templates)
+    }
+
+    /// Sets the extra `stylesheets` of the theme, which are linked from every page.
+    pub fn set_stylesheets(&mut self, stylesheets: &[String]) {
+        self.env
+            .add_global("theme_stylesheets", Value::from_serialize(stylesheets));
+    }
+
+    /// Renders the template with the `name` with the `context`.
+    ///
+    /// # Errors
+    /// Returns a [`TrackerError::Template`](crate::error::TrackerError::Template) if there is no template with the
+    /// `name`, or it could not be rendered.
+    pub fn render(&self, name: &str, context: impl Serialize) -> TrackerResult<String> {
+        Ok(self.env.get_template(name)?.render(context)?)
+    }
+}
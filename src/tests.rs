@@ -1,9 +1,59 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Duration as CDuration, Local, NaiveDate};
+
+#[allow(deprecated)]
+use crate::calculate_past_two::{closest_date_in, date_binary_search};
+#[cfg(feature = "sqlite")]
+use crate::store::SqliteStore;
 use crate::{
-    calculate_past_two::date_binary_search,
-    initialize_logging,
+    atomic_file::{append_synced, backup_path, backups, temp_path, write_atomic, BACKUP_COUNT},
+    calculate_past_two::{
+        past_two_seconds, report, window_seconds, window_seconds_in, Window, MAX_WINDOW_DAYS,
+    },
+    charts::{bar_chart, calendar_heatmap, filled_totals, heat_level, Charts},
+    checkpoint::{handle_unfinished_session, Checkpoint},
+    cli::{Cli, Command, ConfigAction},
+    config::{Config, Prompt, UnfinishedSession},
+    daemon::request_shutdown,
+    data_dir::DataDir,
+    detection::{glob_match, DetectionRules, ProcessInfo},
+    error::TrackerError,
+    export_sessions,
+    games::{registry, CustomGame, Game, ROCKET_LEAGUE_ID},
+    history::{format_duration, group_by_month, history_index, write_history_pages},
+    hours::{recalculate, recalculate_game, HoursSummary},
+    idle::{FakeIdle, IdleConfig},
+    import_sessions, initialize_logging,
+    migrate::{
+        legacy_session, migrate, migrate_on_startup, migrate_with, needs_migration,
+        parse_legacy_dates, MigrationError, Schema, SCHEMA_VERSION,
+    },
+    parser::{parse_dates, parse_hours, validate, validate_games, ParseError, ParseMode},
+    process_watcher::{FakeWatcher, ProcWatcher, ProcessWatcher, RefreshWatcher},
+    recovery::{recover_on_startup, recover_with, RecoveryAction},
+    server::{serve, LiveStatus, LiveSubscriber, ServerConfig, ServerState},
+    session::{
+        append_session, read_sessions, read_sessions_from, EndReason, IdleSpan, Session,
+        SESSION_VERSION,
+    },
+    state::{SharedState, TrackerState, Trigger},
+    status,
+    store::{group_totals, Grouping, JsonlStore, SessionStore, Total},
+    templates::Templates,
+    timezone::Timezone,
+    tracker::{Subscriber, Tracker, TrackerEvent, TrackerHandle},
+    waiting_message,
     website_files::*,
     winit_tray_icon::{load_image, IMAGE_BYTES},
+    ExportFormat, PastTwoError,
 };
+use clap::Parser;
 
 #[test]
 fn t_builds_raw_url() {
@@ -36,7 +86,7 @@ fn t_builds_image_url() {
 }
 
 #[tokio::test]
-async fn t_sends_request() -> Result<(), reqwest::Error> {
+async fn t_sends_request() -> Result<(), TrackerError> {
     let mut instance = Github::new(
         "OneilNvM",
         "rl-hours-tracker",
@@ -46,7 +96,7 @@ async fn t_sends_request() -> Result<(), reqwest::Error> {
     );
     instance.build_url();
 
-    let response = send_request(&instance.get_url()).await;
+    let response = send_request(&instance.get_url()).await?;
 
     let text = response.text().await?;
 
@@ -61,7 +111,7 @@ async fn t_handle_raw_response() {
 
     instance.build_url();
 
-    let response = send_request(&instance.get_url()).await;
+    let response = send_request(&instance.get_url()).await.unwrap();
 
     let text = response.text().await;
 
@@ -80,7 +130,7 @@ async fn t_handle_image_response() {
 
     instance.build_image_url();
 
-    let response = send_request(&instance.get_url()).await;
+    let response = send_request(&instance.get_url()).await.unwrap();
 
     let text = response.bytes().await;
 
@@ -88,6 +138,7 @@ async fn t_handle_image_response() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn t_date_binary_search() {
     let split_newline: Vec<&str> = vec![
         "2024-09-15 58s",
@@ -124,3 +175,2877 @@ fn t_load_image() {
 
     assert!(image.is_ok());
 }
+
+#[test]
+fn t_data_dir_paths() {
+    let data_dir = DataDir::new("RLHoursFolder");
+
+    assert_eq!(
+        data_dir.hours_file(),
+        PathBuf::from("RLHoursFolder").join("hours.txt")
+    );
+    assert_eq!(
+        data_dir.date_file(),
+        PathBuf::from("RLHoursFolder").join("date.txt")
+    );
+    assert_eq!(
+        data_dir.website_pages_dir(),
+        PathBuf::from("RLHoursFolder").join("website").join("pages")
+    );
+}
+
+#[test]
+fn t_data_dir_resolve_prefers_flag() {
+    let data_dir = DataDir::resolve(Some(PathBuf::from("/tmp/rl-hours"))).unwrap();
+
+    assert_eq!(data_dir.root(), Path::new("/tmp/rl-hours"));
+}
+
+#[test]
+fn t_config_round_trip() {
+    let path = env::temp_dir()
+        .join("rl-hours-tracker-t-config")
+        .join("config.toml");
+    let config = Config {
+        data_dir: Some(PathBuf::from("/tmp/rl-hours")),
+        checkpoint_interval: 30,
+        unfinished_session: UnfinishedSession::Recover,
+        daemon: true,
+        open_website: Prompt::No,
+        install_updates: Prompt::Yes,
+        end_after_session: Prompt::Ask,
+        windows: vec![Window::Days(7), Window::CalendarMonth],
+        timezone: Timezone::Named(chrono_tz::Europe::London),
+        parsing: ParseMode::Lenient,
+        detection: DetectionRules::default(),
+        idle: IdleConfig::default(),
+        website: WebsiteConfig {
+            theme: Theme::Github,
+        },
+        server: ServerConfig {
+            enabled: true,
+            port: 9000,
+        },
+        games: Vec::new(),
+    };
+
+    config.save_to(&path).unwrap();
+
+    assert_eq!(Config::load_from(&path).unwrap(), config);
+}
+
+fn session_on(date: &str, duration: u64) -> Session {
+    let end = DateTime::parse_from_rfc3339(&format!("{date}T20:00:00+01:00")).unwrap();
+
+    Session {
+        version: SESSION_VERSION,
+        start: end - CDuration::seconds(duration as i64),
+        end,
+        duration,
+        end_reason: EndReason::ProcessExit,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        idle: Vec::new(),
+    }
+}
+
+#[test]
+fn t_session_round_trip() {
+    let sessions = vec![session_on("2024-09-15", 58), session_on("2024-09-16", 2890)];
+    let mut contents = String::new();
+
+    for session in &sessions {
+        contents.push_str(&serde_json::to_string(session).unwrap());
+        contents.push_str("\n\n");
+    }
+
+    let read = read_sessions_from(contents.as_bytes()).unwrap();
+
+    assert_eq!(read, sessions);
+}
+
+#[test]
+fn t_session_json_format() {
+    let session = session_on("2024-09-15", 3600);
+
+    assert_eq!(
+        serde_json::to_string(&session).unwrap(),
+        format!(
+            "{{\"version\":2,\"start\":\"2024-09-15T18:00:00Z\",\"end\":\"2024-09-15T19:00:00Z\",\"offset\":\"+01:00\",\"duration\":3600,\"end_reason\":\"process_exit\",\"app_version\":\"{}\"}}",
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+}
+
+#[test]
+fn t_past_two_seconds() {
+    let today = NaiveDate::from_ymd_opt(2024, 9, 29).unwrap();
+    let sessions = vec![
+        session_on("2024-09-14", 100),
+        session_on("2024-09-15", 58),
+        session_on("2024-09-16", 2890),
+        session_on("2024-09-29", 621),
+        session_on("2024-09-30", 5000),
+    ];
+
+    assert_eq!(past_two_seconds(&sessions, today).unwrap(), 58 + 2890 + 621);
+
+    let error = past_two_seconds(&sessions[..1].to_vec(), today).unwrap_err();
+    assert!(matches!(error, TrackerError::PastTwo(_)));
+}
+
+fn totals_fixture() -> Vec<Session> {
+    vec![
+        session_on("2024-08-31", 100),
+        session_on("2024-09-01", 200),
+        session_on("2024-09-02", 300),
+        session_on("2024-09-02", 400),
+        session_on("2024-09-09", 500),
+    ]
+}
+
+#[test]
+fn t_group_totals() {
+    let sessions = totals_fixture();
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+
+    let days = group_totals(&sessions[1..], Grouping::Day);
+    assert_eq!(
+        days,
+        vec![
+            Total {
+                period_start: date(1),
+                seconds: 200,
+                sessions: 1
+            },
+            Total {
+                period_start: date(2),
+                seconds: 700,
+                sessions: 2
+            },
+            Total {
+                period_start: date(9),
+                seconds: 500,
+                sessions: 1
+            },
+        ]
+    );
+
+    // 2024-09-02 and 2024-09-09 are Mondays
+    let weeks: Vec<(NaiveDate, u64)> = group_totals(&sessions, Grouping::Week)
+        .iter()
+        .map(|total| (total.period_start, total.seconds))
+        .collect();
+    assert_eq!(
+        weeks,
+        vec![
+            (NaiveDate::from_ymd_opt(2024, 8, 26).unwrap(), 300),
+            (date(2), 700),
+            (date(9), 500)
+        ]
+    );
+
+    let months: Vec<(NaiveDate, u64)> = group_totals(&sessions, Grouping::Month)
+        .iter()
+        .map(|total| (total.period_start, total.seconds))
+        .collect();
+    assert_eq!(
+        months,
+        vec![
+            (NaiveDate::from_ymd_opt(2024, 8, 1).unwrap(), 100),
+            (date(1), 1400)
+        ]
+    );
+}
+
+#[test]
+fn t_filled_totals() {
+    let sessions = totals_fixture();
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+
+    // The days without any sessions are included with 0 seconds
+    let days = filled_totals(&sessions, Grouping::Day, date(1), date(4));
+    assert_eq!(
+        days,
+        vec![(date(1), 200), (date(2), 700), (date(3), 0), (date(4), 0)]
+    );
+
+    // The first week starts on the Monday before `from`
+    let weeks = filled_totals(&sessions, Grouping::Week, date(1), date(20));
+    assert_eq!(
+        weeks,
+        vec![
+            (NaiveDate::from_ymd_opt(2024, 8, 26).unwrap(), 200),
+            (date(2), 700),
+            (date(9), 500),
+            (date(16), 0)
+        ]
+    );
+
+    let months = filled_totals(&sessions, Grouping::Month, date(1), date(30));
+    assert_eq!(months, vec![(date(1), 1400)]);
+}
+
+#[test]
+fn t_bar_chart_draws_every_day() {
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+    let chart = bar_chart(&[(date(1), 3600), (date(2), 0), (date(3), 1800)]);
+
+    assert!(chart.starts_with("<svg") && chart.ends_with("</svg>"));
+    assert_eq!(chart.matches("<rect class=\"chart-bar\"").count(), 3);
+    assert!(chart.contains("<title>2024-09-01: 1.0hrs</title>"));
+    assert!(chart.contains("<title>2024-09-03: 0.5hrs</title>"));
+}
+
+#[test]
+fn t_calendar_heatmap_levels() {
+    assert_eq!(heat_level(0, 100), 0);
+    assert_eq!(heat_level(1, 100), 1);
+    assert_eq!(heat_level(50, 100), 2);
+    assert_eq!(heat_level(75, 100), 3);
+    assert_eq!(heat_level(100, 100), 4);
+
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+    let chart = calendar_heatmap(&[(date(2), 0), (date(3), 100), (date(4), 25)]);
+
+    assert!(chart.contains("class=\"heat-0\""));
+    assert!(chart.contains("class=\"heat-4\""));
+    assert!(chart.contains("class=\"heat-1\""));
+}
+
+#[test]
+fn t_charts_are_offline() {
+    let today = NaiveDate::from_ymd_opt(2024, 9, 10).unwrap();
+    let charts = Charts::new(&totals_fixture(), Window::Days(14), today);
+
+    // The window includes today, and the calendar starts on the Monday 52 weeks before this week
+    assert_eq!(
+        charts.daily.matches("<rect class=\"chart-bar\"").count(),
+        15
+    );
+    assert_eq!(charts.weekly.matches("<circle").count(), 12);
+    assert_eq!(charts.monthly.matches("<circle").count(), 12);
+    assert_eq!(charts.calendar.matches("<rect class=\"heat-").count(), 366);
+
+    for chart in [
+        &charts.daily,
+        &charts.weekly,
+        &charts.monthly,
+        &charts.calendar,
+    ] {
+        assert!(!chart.contains("<script"));
+        assert!(!chart.contains("http"));
+    }
+}
+
+#[test]
+fn t_group_by_month() {
+    let sessions: Vec<(String, Session)> = totals_fixture()
+        .into_iter()
+        .map(|session| (String::from("Rocket League"), session))
+        .collect();
+
+    let months = group_by_month(&sessions, &Timezone::Recorded);
+
+    // Newest month first, and newest session first within a month
+    let summary: Vec<(String, usize, u64)> = months
+        .iter()
+        .map(|month| {
+            (
+                month.page_name(),
+                month.sessions.len(),
+                month.total_seconds(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (String::from("history-2024-09.html"), 4, 1400),
+            (String::from("history-2024-08.html"), 1, 100)
+        ]
+    );
+    assert_eq!(months[0].sessions[0].1.duration, 500);
+    assert_eq!(months[0].title(), "September 2024");
+}
+
+#[test]
+fn t_history_pages() {
+    let data_dir = temp_data_dir("t-history-pages");
+    let sessions: Vec<(String, Session)> = totals_fixture()
+        .into_iter()
+        .map(|session| (String::from("Rocket League"), session))
+        .collect();
+
+    write_history_pages(&Templates::new(), &data_dir, &sessions, false).unwrap();
+
+    let pages = data_dir.website_pages_dir();
+    let index = fs::read_to_string(pages.join("history.html")).unwrap();
+    assert!(
+        index.contains(r#"<a href="history-2024-09.html">September 2024</a>: 4 sessions, 0.4hrs"#)
+    );
+    assert!(index.contains(r#"<a href="history-2024-08.html">August 2024</a>: 1 sessions, 0.0hrs"#));
+
+    let september = fs::read_to_string(pages.join("history-2024-09.html")).unwrap();
+    assert_eq!(september.matches("<tr data-date=").count(), 4);
+    assert!(september.contains(r#"<tr data-date="2024-09-09" data-duration="500">"#));
+    assert!(september.contains("<td>00:08:20</td>"));
+    assert!(september.contains(r#"min="2024-09-01" max="2024-09-30""#));
+    assert!(september.contains("../js/history.js"));
+
+    // September links to the older month, and August to the newer month
+    assert!(september.contains(r#"href="history-2024-08.html""#));
+    let august = fs::read_to_string(pages.join("history-2024-08.html")).unwrap();
+    assert!(august.contains(r#"href="history-2024-09.html""#));
+    assert!(!august.contains("<th>Game</th>"));
+
+    fs::remove_dir_all(data_dir.root()).unwrap();
+}
+
+#[test]
+fn t_format_duration() {
+    assert_eq!(format_duration(0), "00:00:00");
+    assert_eq!(format_duration(3725), "01:02:05");
+    assert_eq!(format_duration(360000), "100:00:00");
+}
+
+#[test]
+fn t_jsonl_store_queries() {
+    let data_dir = temp_data_dir("t-jsonl-store-queries");
+    let mut store = JsonlStore::new(data_dir.sessions_file());
+
+    for session in totals_fixture() {
+        store.add(&session).unwrap();
+    }
+
+    let from = NaiveDate::from_ymd_opt(2024, 9, 1).unwrap();
+    let to = NaiveDate::from_ymd_opt(2024, 9, 2).unwrap();
+
+    assert_eq!(
+        store.sessions_between(from, to).unwrap(),
+        totals_fixture()[1..4]
+    );
+    assert_eq!(
+        store.latest(2).unwrap(),
+        vec![totals_fixture()[4].clone(), totals_fixture()[3].clone()]
+    );
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn t_sqlite_store_matches_jsonl_store() {
+    let data_dir = temp_data_dir("t-sqlite-store-matches-jsonl-store");
+    let mut sqlite = SqliteStore::open(&data_dir).unwrap();
+
+    for session in totals_fixture() {
+        sqlite.add(&session).unwrap();
+    }
+
+    let jsonl = JsonlStore::new(data_dir.sessions_file());
+    let from = NaiveDate::from_ymd_opt(2024, 8, 1).unwrap();
+    let to = NaiveDate::from_ymd_opt(2024, 9, 30).unwrap();
+
+    assert_eq!(sqlite.sessions().unwrap(), jsonl.sessions().unwrap());
+    assert_eq!(sqlite.latest(2).unwrap(), jsonl.latest(2).unwrap());
+    assert_eq!(
+        sqlite.sessions_between(from, to).unwrap(),
+        jsonl.sessions_between(from, to).unwrap()
+    );
+
+    for grouping in [Grouping::Day, Grouping::Week, Grouping::Month] {
+        assert_eq!(
+            sqlite.totals(grouping, from, to).unwrap(),
+            jsonl.totals(grouping, from, to).unwrap()
+        );
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn t_sqlite_store_without_data_folder() {
+    let data_dir = DataDir::new(
+        temp_data_dir("t-sqlite-store-without-data-folder")
+            .games_dir()
+            .join("new-game"),
+    );
+    let store = SqliteStore::open(&data_dir).unwrap();
+    let from = NaiveDate::from_ymd_opt(2024, 9, 1).unwrap();
+    let to = NaiveDate::from_ymd_opt(2024, 9, 30).unwrap();
+
+    // The folder of a game without sessions reads as an empty history, like the session log
+    assert_eq!(store.sessions().unwrap(), []);
+    assert_eq!(store.totals(Grouping::Day, from, to).unwrap(), []);
+    assert!(!data_dir.root().exists());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn t_sqlite_store_rebuilds_from_log() {
+    let data_dir = temp_data_dir("t-sqlite-store-rebuilds-from-log");
+
+    SqliteStore::open(&data_dir).unwrap();
+
+    // Sessions written to the log without the database are imported when it is next opened
+    for session in totals_fixture() {
+        append_session(&data_dir.sessions_file(), &session).unwrap();
+    }
+
+    let sqlite = SqliteStore::open(&data_dir).unwrap();
+
+    assert_eq!(sqlite.sessions().unwrap(), totals_fixture());
+    drop(sqlite);
+
+    // A rewrite which keeps the length and modified time of the log is found by its last line
+    let log = data_dir.sessions_file();
+    let modified = fs::metadata(&log).unwrap().modified().unwrap();
+    let mut rewritten = totals_fixture();
+    rewritten[4].duration = 600;
+
+    let lines: Vec<String> = rewritten
+        .iter()
+        .map(|session| serde_json::to_string(session).unwrap() + "\n")
+        .collect();
+    fs::write(&log, lines.concat()).unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&log)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    let sqlite = SqliteStore::open(&data_dir).unwrap();
+
+    assert_eq!(sqlite.sessions().unwrap(), rewritten);
+    drop(sqlite);
+
+    // A rewrite which keeps the length and last line of the log is found by its modified time
+    rewritten[0].duration = 900;
+
+    let lines: Vec<String> = rewritten
+        .iter()
+        .map(|session| serde_json::to_string(session).unwrap() + "\n")
+        .collect();
+    fs::write(&log, lines.concat()).unwrap();
+
+    assert_eq!(
+        SqliteStore::open(&data_dir).unwrap().sessions().unwrap(),
+        rewritten
+    );
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn t_sqlite_store_sessions_across_midnight() {
+    let data_dir = temp_data_dir("t-sqlite-store-sessions-across-midnight");
+    let mut sqlite = SqliteStore::open(&data_dir).unwrap();
+
+    sqlite
+        .add(&session_between(
+            "2024-09-14T22:00:00+01:00",
+            "2024-09-16T02:00:00+01:00",
+        ))
+        .unwrap();
+
+    let jsonl = JsonlStore::new(data_dir.sessions_file());
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+
+    // The session started before and ended after the 15th
+    assert_eq!(
+        sqlite.sessions_between(date(15), date(15)).unwrap().len(),
+        1
+    );
+    assert_eq!(
+        sqlite.totals(Grouping::Day, date(15), date(16)).unwrap(),
+        jsonl.totals(Grouping::Day, date(15), date(16)).unwrap()
+    );
+
+    // Sessions near midnight in the furthest offsets from UTC are found on the same days as in the log
+    for (start, end) in [
+        ("2024-09-20T00:10:00+14:00", "2024-09-20T00:40:00+14:00"),
+        ("2024-09-20T23:10:00-12:00", "2024-09-20T23:50:00-12:00"),
+    ] {
+        sqlite.add(&session_between(start, end)).unwrap();
+    }
+
+    for day in 18..=22 {
+        assert_eq!(
+            sqlite.sessions_between(date(day), date(day)).unwrap(),
+            jsonl.sessions_between(date(day), date(day)).unwrap()
+        );
+    }
+
+    assert_eq!(
+        sqlite.sessions_between(date(20), date(20)).unwrap().len(),
+        2
+    );
+}
+
+const LEGACY_DATE_FIXTURE: &str = include_str!("../tests/fixtures/legacy/date.txt");
+const LEGACY_HOURS_FIXTURE: &str = include_str!("../tests/fixtures/legacy/hours.txt");
+
+/// Creates an empty data directory in the temp folder for the test called `name`.
+fn temp_data_dir(name: &str) -> DataDir {
+    let root = env::temp_dir().join(format!("rl-hours-tracker-{name}"));
+
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+
+    DataDir::new(root)
+}
+
+/// Creates a data directory with the legacy `date.txt` and `hours.txt` fixtures.
+fn legacy_data_dir(name: &str) -> DataDir {
+    let data_dir = temp_data_dir(name);
+
+    fs::write(data_dir.date_file(), LEGACY_DATE_FIXTURE).unwrap();
+    fs::write(data_dir.hours_file(), LEGACY_HOURS_FIXTURE).unwrap();
+
+    data_dir
+}
+
+#[test]
+fn t_parse_legacy_dates_is_lossless() {
+    let sessions = parse_legacy_dates(LEGACY_DATE_FIXTURE).unwrap();
+
+    let lines: Vec<String> = sessions
+        .iter()
+        .map(|session| format!("{} {}s", session.date(), session.duration))
+        .collect();
+
+    assert_eq!(lines, LEGACY_DATE_FIXTURE.lines().collect::<Vec<&str>>());
+    assert!(sessions
+        .iter()
+        .all(|session| session.end_reason == EndReason::Legacy));
+}
+
+#[test]
+fn t_parse_legacy_dates_invalid_line() {
+    let result = parse_legacy_dates("2024-09-15 58s\n2024-09-16\n");
+
+    assert_eq!(
+        result,
+        Err(MigrationError::InvalidLine {
+            line: 2,
+            text: String::from("2024-09-16")
+        })
+    );
+}
+
+#[test]
+fn t_migrate_legacy_layout() {
+    let data_dir = legacy_data_dir("t-migrate-legacy-layout");
+
+    assert!(needs_migration(&data_dir));
+
+    let report = migrate(&data_dir).unwrap();
+
+    assert_eq!(report.sessions, 14);
+    assert_eq!(report.total_seconds, 97040);
+    assert_eq!(report.unrecorded_seconds, 3000);
+
+    // The legacy files are backed up and date.txt is removed
+    assert_eq!(
+        fs::read_to_string(report.backup_dir.join("date.txt")).unwrap(),
+        LEGACY_DATE_FIXTURE
+    );
+    assert_eq!(
+        fs::read_to_string(report.backup_dir.join("hours.txt")).unwrap(),
+        LEGACY_HOURS_FIXTURE
+    );
+    assert!(!data_dir.date_file().exists());
+
+    let sessions = read_sessions(&data_dir.sessions_file()).unwrap();
+    assert_eq!(sessions, parse_legacy_dates(LEGACY_DATE_FIXTURE).unwrap());
+
+    let schema = Schema::load(&data_dir.schema_file()).unwrap().unwrap();
+    assert_eq!(schema.version, SCHEMA_VERSION);
+    assert_eq!(schema.legacy_unrecorded_seconds, 3000);
+}
+
+#[test]
+fn t_migrate_keeps_new_sessions() {
+    let data_dir = legacy_data_dir("t-migrate-keeps-new-sessions");
+    let new_session = session_on("2024-10-01", 1200);
+
+    append_session(&data_dir.sessions_file(), &new_session).unwrap();
+    migrate(&data_dir).unwrap();
+
+    let sessions = read_sessions(&data_dir.sessions_file()).unwrap();
+
+    assert_eq!(sessions.len(), 15);
+    assert_eq!(sessions.last(), Some(&new_session));
+}
+
+#[test]
+fn t_migrate_refuses_to_run_twice() {
+    let data_dir = legacy_data_dir("t-migrate-refuses-to-run-twice");
+
+    migrate(&data_dir).unwrap();
+
+    // Restoring date.txt does not cause the sessions to be imported again
+    fs::write(data_dir.date_file(), LEGACY_DATE_FIXTURE).unwrap();
+
+    assert!(!needs_migration(&data_dir));
+    assert_eq!(migrate_on_startup(&data_dir).unwrap(), None);
+
+    let error = migrate(&data_dir).unwrap_err();
+    assert!(matches!(
+        error,
+        TrackerError::Migration(MigrationError::AlreadyMigrated(SCHEMA_VERSION))
+    ));
+    assert_eq!(read_sessions(&data_dir.sessions_file()).unwrap().len(), 14);
+}
+
+#[test]
+fn t_migrate_on_startup_fresh_install() {
+    let data_dir = temp_data_dir("t-migrate-on-startup-fresh-install");
+
+    assert_eq!(migrate_on_startup(&data_dir).unwrap(), None);
+
+    let schema = Schema::load(&data_dir.schema_file()).unwrap().unwrap();
+    assert_eq!(schema.version, SCHEMA_VERSION);
+    assert_eq!(schema.migrated_at, None);
+}
+
+#[test]
+fn t_hours_summary_from_store() {
+    let store = totals_fixture();
+    let today = NaiveDate::from_ymd_opt(2024, 9, 9).unwrap();
+
+    let summary = HoursSummary::from_store(&store, 1000, today, &[]).unwrap();
+
+    assert_eq!(summary.total_seconds, 2500);
+    assert_eq!(summary.past_two_seconds, 1500);
+    assert_eq!(
+        summary.to_string(),
+        "Rocket League Hours\nTotal Seconds: 2500s\nTotal Hours: 0.7hrs\nHours Past Two Weeks: 0.4hrs\nIdle Hours: 0.0hrs\n"
+    );
+
+    // No sessions in the past two weeks is not an error
+    let later = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    let summary = HoursSummary::from_store(&store, 0, later, &[]).unwrap();
+
+    assert_eq!(summary.total_seconds, 1500);
+    assert_eq!(summary.past_two_seconds, 0);
+}
+
+#[test]
+fn t_recalculate_corrects_drift() {
+    let data_dir = legacy_data_dir("t-recalculate-corrects-drift");
+
+    migrate(&data_dir).unwrap();
+
+    // The seconds are correct, but the hours drifted from rounding errors
+    fs::write(
+        data_dir.hours_file(),
+        "Rocket League Hours\nTotal Seconds: 100040s\nTotal Hours: 31.4hrs\nHours Past Two Weeks: 0.0hrs\n",
+    )
+    .unwrap();
+
+    let report = recalculate(&data_dir).unwrap();
+
+    assert_eq!(report.previous, Some((100040, 31.4)));
+    assert!(report.has_discrepancy());
+    assert_eq!(report.seconds_corrected(), 0);
+    assert_eq!(report.summary.total_seconds, 100040);
+    assert_eq!(
+        fs::read_to_string(data_dir.hours_file()).unwrap(),
+        report.summary.to_string()
+    );
+    assert!(report.summary.to_string().contains("Total Hours: 27.8hrs"));
+
+    // Running it again finds nothing to correct
+    assert!(!recalculate(&data_dir).unwrap().has_discrepancy());
+}
+
+#[test]
+fn t_recalculate_creates_hours_file() {
+    let data_dir = temp_data_dir("t-recalculate-creates-hours-file");
+
+    append_session(&data_dir.sessions_file(), &session_on("2024-09-15", 5400)).unwrap();
+
+    let report = recalculate(&data_dir).unwrap();
+
+    assert_eq!(report.previous, None);
+    assert!(!report.has_discrepancy());
+    assert_eq!(report.summary.total_seconds, 5400);
+    assert!(data_dir.hours_file().is_file());
+}
+
+#[test]
+fn t_write_atomic_rotates_backups() {
+    let data_dir = temp_data_dir("t-write-atomic-rotates-backups");
+    let path = data_dir.hours_file();
+
+    for version in 0..=BACKUP_COUNT + 1 {
+        write_atomic(&path, version.to_string()).unwrap();
+    }
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "4");
+    assert!(!temp_path(&path).exists());
+    assert_eq!(backups(&path).len(), BACKUP_COUNT);
+    assert_eq!(fs::read_to_string(backup_path(&path, 0)).unwrap(), "3");
+    assert_eq!(fs::read_to_string(backup_path(&path, 2)).unwrap(), "1");
+}
+
+#[test]
+fn t_append_synced_repairs_cut_off_line() {
+    let data_dir = temp_data_dir("t-append-synced-repairs-cut-off-line");
+    let path = data_dir.sessions_file();
+
+    append_synced(&path, "first\n").unwrap();
+    fs::write(&path, "first\nsecond").unwrap();
+    append_synced(&path, "third\n").unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "first\nsecond\nthird\n");
+
+    // The log is appended to in place, without a temporary file or backups
+    assert!(!temp_path(&path).exists());
+    assert!(backups(&path).is_empty());
+}
+
+/// Returns the total seconds and hours of the `hours.txt` `contents`, parsed in strict mode.
+fn hours_totals(contents: &str) -> Result<(u64, f32), ParseError> {
+    parse_hours(contents, ParseMode::Strict)
+        .map(|parsed| (parsed.value.seconds, parsed.value.hours))
+}
+
+#[test]
+fn t_hours_totals_truncated() {
+    assert_eq!(hours_totals(LEGACY_HOURS_FIXTURE).unwrap(), (100040, 27.8));
+    assert!(hours_totals("").is_err());
+    assert!(hours_totals("Rocket League Hours\nTotal Seconds: 100040s\n").is_err());
+    assert!(hours_totals("Rocket League Hours\nTotal Seconds: \nTotal Hours: 27.8hrs").is_err());
+}
+
+#[test]
+fn t_recover_on_startup_intact() {
+    let data_dir = legacy_data_dir("t-recover-on-startup-intact");
+
+    migrate_on_startup(&data_dir).unwrap();
+    recalculate(&data_dir).unwrap();
+
+    assert_eq!(recover_on_startup(&data_dir).unwrap(), Vec::new());
+}
+
+#[test]
+fn t_recover_on_startup_restores_backup() {
+    let data_dir = temp_data_dir("t-recover-on-startup-restores-backup");
+
+    append_session(&data_dir.sessions_file(), &session_on("2024-09-15", 5400)).unwrap();
+    recalculate(&data_dir).unwrap();
+    recalculate(&data_dir).unwrap();
+
+    // A crash while writing the old way left hours.txt truncated and a temporary file behind
+    fs::write(data_dir.hours_file(), "Rocket League Hours\nTotal Sec").unwrap();
+    fs::write(temp_path(&data_dir.hours_file()), "Rocket").unwrap();
+
+    let recoveries = recover_on_startup(&data_dir).unwrap();
+
+    assert_eq!(recoveries.len(), 2);
+    assert_eq!(recoveries[0].action, RecoveryAction::RemovedTemp);
+    assert!(matches!(
+        &recoveries[1].action,
+        RecoveryAction::Restored { backup, corrupt }
+            if *backup == backup_path(&data_dir.hours_file(), 0) && corrupt.is_file()
+    ));
+    assert_eq!(
+        hours_totals(&fs::read_to_string(data_dir.hours_file()).unwrap())
+            .unwrap()
+            .0,
+        5400
+    );
+    assert!(!temp_path(&data_dir.hours_file()).exists());
+}
+
+#[test]
+fn t_recover_on_startup_salvages_sessions() {
+    let data_dir = temp_data_dir("t-recover-on-startup-salvages-sessions");
+    let sessions = vec![
+        session_on("2024-09-14", 3600),
+        session_on("2024-09-15", 5400),
+    ];
+
+    for session in &sessions {
+        append_session(&data_dir.sessions_file(), session).unwrap();
+    }
+
+    let log = fs::read_to_string(data_dir.sessions_file()).unwrap();
+
+    // The last line was cut off while it was appended
+    let cut_off = "{\"version\":1,\"start\":\"2024-09-16T";
+    fs::write(data_dir.sessions_file(), log.clone() + cut_off).unwrap();
+
+    let recoveries = recover_on_startup(&data_dir).unwrap();
+
+    assert_eq!(recoveries.len(), 1);
+    assert_eq!(
+        recoveries[0].action,
+        RecoveryAction::TruncatedLastLine {
+            bytes: cut_off.len()
+        }
+    );
+    assert_eq!(fs::read_to_string(data_dir.sessions_file()).unwrap(), log);
+
+    // A corrupt line in the middle of the log is dropped
+    let (first, second) = log.split_once('\n').unwrap();
+    fs::write(
+        data_dir.sessions_file(),
+        format!("{first}\n{{\"version\":1,\n{second}"),
+    )
+    .unwrap();
+
+    let recoveries = recover_on_startup(&data_dir).unwrap();
+
+    assert_eq!(recoveries.len(), 1);
+    assert!(matches!(
+        recoveries[0].action,
+        RecoveryAction::Salvaged { dropped: 1, .. }
+    ));
+    assert_eq!(read_sessions(&data_dir.sessions_file()).unwrap(), sessions);
+}
+
+#[test]
+fn t_recover_with_lenient_hours() {
+    let data_dir = temp_data_dir("t-recover-with-lenient-hours");
+
+    append_session(&data_dir.sessions_file(), &session_on("2024-09-15", 5400)).unwrap();
+    recalculate(&data_dir).unwrap();
+
+    let mut hours = fs::read_to_string(data_dir.hours_file()).unwrap();
+    hours.push_str("Favourite Car: Octane\n");
+    fs::write(data_dir.hours_file(), &hours).unwrap();
+
+    // The file is accepted in lenient mode, so it is left alone
+    assert!(recover_with(&data_dir, ParseMode::Lenient)
+        .unwrap()
+        .is_empty());
+    assert_eq!(fs::read_to_string(data_dir.hours_file()).unwrap(), hours);
+
+    assert_eq!(recover_with(&data_dir, ParseMode::Strict).unwrap().len(), 1);
+}
+
+#[test]
+fn t_recover_on_startup_quarantines_schema() {
+    let data_dir = temp_data_dir("t-recover-on-startup-quarantines-schema");
+
+    fs::write(data_dir.schema_file(), "{\"version\": 1, \"migr").unwrap();
+
+    let recoveries = recover_on_startup(&data_dir).unwrap();
+
+    assert!(matches!(
+        &recoveries[0].action,
+        RecoveryAction::Quarantined { corrupt } if corrupt.is_file()
+    ));
+    assert!(!data_dir.schema_file().exists());
+
+    // The schema is recreated by the rest of the startup
+    migrate_on_startup(&data_dir).unwrap();
+    assert!(recover_on_startup(&data_dir).unwrap().is_empty());
+}
+
+/// Writes a checkpoint for a session which started at 18:00 and was checkpointed after `duration` seconds.
+fn write_checkpoint(data_dir: &DataDir, duration: u64) -> Checkpoint {
+    let start = DateTime::parse_from_rfc3339("2024-09-15T18:00:00+01:00").unwrap();
+    let checkpoint = Checkpoint {
+        version: SESSION_VERSION,
+        start,
+        checkpointed_at: start + CDuration::seconds(duration as i64),
+        duration,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        idle: Vec::new(),
+    };
+
+    checkpoint.save(&data_dir.checkpoint_file()).unwrap();
+    checkpoint
+}
+
+#[test]
+fn t_checkpoint_round_trip() {
+    let data_dir = temp_data_dir("t-checkpoint-round-trip");
+    let checkpoint = write_checkpoint(&data_dir, 1800);
+
+    assert_eq!(
+        Checkpoint::load(&data_dir.checkpoint_file()).unwrap(),
+        Some(checkpoint.clone())
+    );
+
+    let session = checkpoint.into_session();
+
+    assert_eq!(session.end_reason, EndReason::Recovered);
+    assert_eq!(session.duration, 1800);
+    assert_eq!(session.end - session.start, CDuration::seconds(1800));
+
+    // Removing the checkpoint also removes its backups
+    write_checkpoint(&data_dir, 1860);
+    Checkpoint::remove(&data_dir.checkpoint_file()).unwrap();
+
+    assert_eq!(Checkpoint::load(&data_dir.checkpoint_file()).unwrap(), None);
+    assert!(backups(&data_dir.checkpoint_file()).is_empty());
+}
+
+#[test]
+fn t_unfinished_session_recover() {
+    let data_dir = temp_data_dir("t-unfinished-session-recover");
+    let checkpoint = write_checkpoint(&data_dir, 1800);
+
+    let session = handle_unfinished_session(&data_dir, UnfinishedSession::Recover).unwrap();
+
+    assert_eq!(session, Some(checkpoint.into_session()));
+    assert_eq!(read_sessions(&data_dir.sessions_file()).unwrap().len(), 1);
+    assert!(!data_dir.checkpoint_file().exists());
+
+    // There is nothing left to recover
+    assert_eq!(
+        handle_unfinished_session(&data_dir, UnfinishedSession::Recover).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn t_unfinished_session_discard() {
+    let data_dir = temp_data_dir("t-unfinished-session-discard");
+
+    write_checkpoint(&data_dir, 1800);
+
+    assert_eq!(
+        handle_unfinished_session(&data_dir, UnfinishedSession::Discard).unwrap(),
+        None
+    );
+    assert!(read_sessions(&data_dir.sessions_file()).unwrap().is_empty());
+    assert!(!data_dir.checkpoint_file().exists());
+
+    // A session without any time played is never recorded, and the user is not asked
+    write_checkpoint(&data_dir, 0);
+
+    assert_eq!(
+        handle_unfinished_session(&data_dir, UnfinishedSession::Ask).unwrap(),
+        None
+    );
+    assert!(!data_dir.checkpoint_file().exists());
+}
+
+#[test]
+fn t_fake_watcher() {
+    let mut watcher = FakeWatcher::new([true, false, true]);
+
+    assert!(watcher.is_running("RocketLeague.exe"));
+    assert!(!watcher.is_running("RocketLeague.exe"));
+    assert!(watcher.is_running("RocketLeague.exe"));
+    assert!(!watcher.is_running("RocketLeague.exe"));
+    assert_eq!(watcher.checks(), 4);
+}
+
+#[test]
+fn t_refresh_watcher() {
+    let mut watcher = RefreshWatcher::new();
+
+    assert!(!watcher.is_running("rl-hours-tracker-missing-process.exe"));
+    assert!(!watcher.is_running("rl-hours-tracker-missing-process.exe"));
+}
+
+/// Creates a fake `/proc` folder with a process at `pid` which has the `comm` and `cmdline` files.
+fn fake_proc(name: &str, pid: &str, comm: &str, cmdline: &[u8]) -> PathBuf {
+    let root = temp_data_dir(name).root().to_path_buf();
+
+    fs::create_dir_all(root.join("1")).unwrap();
+    fs::write(root.join("1").join("comm"), "systemd\n").unwrap();
+    fs::write(root.join("1").join("cmdline"), b"/sbin/init\0").unwrap();
+    fs::create_dir_all(root.join("self")).unwrap();
+    fs::write(root.join("self").join("comm"), "RocketLeague.exe\n").unwrap();
+
+    fs::create_dir_all(root.join(pid)).unwrap();
+    fs::write(root.join(pid).join("comm"), comm).unwrap();
+    fs::write(root.join(pid).join("cmdline"), cmdline).unwrap();
+
+    root
+}
+
+#[test]
+fn t_proc_watcher() {
+    // Windows build run directly
+    let root = fake_proc("t-proc-watcher-comm", "4242", "RocketLeague.exe\n", b"");
+    assert!(ProcWatcher::with_root(root).is_running("RocketLeague.exe"));
+
+    // The kernel truncates comm to 15 bytes
+    let root = fake_proc("t-proc-watcher-truncated", "4242", "RocketLeague.ex\n", b"");
+    assert!(ProcWatcher::with_root(root).is_running("RocketLeague.exe"));
+
+    // Proton runs the game through wine-preloader with the Windows path as the first argument
+    let root = fake_proc(
+        "t-proc-watcher-proton",
+        "4242",
+        "wine-preloader\n",
+        b"Z:\\home\\user\\Games\\rocketleague\\Binaries\\Win64\\RocketLeague.exe\0-EpicPortal\0",
+    );
+    assert!(ProcWatcher::with_root(root).is_running("RocketLeague.exe"));
+
+    // Only the pid folders are checked
+    let root = fake_proc(
+        "t-proc-watcher-missing",
+        "4242",
+        "bash\n",
+        b"/bin/bash\0RocketLeague.exe\0",
+    );
+    assert!(!ProcWatcher::with_root(root).is_running("RocketLeague.exe"));
+
+    assert!(
+        !ProcWatcher::with_root("/rl-hours-tracker-missing-proc").is_running("RocketLeague.exe")
+    );
+}
+
+/// Builds a tracker for the test called `name` with the fake `watcher` and `idle` detector, which collects the
+/// events it emits.
+fn fake_tracker(
+    name: &str,
+    watcher: FakeWatcher,
+    idle: FakeIdle,
+    idle_timeout: Duration,
+) -> (Tracker, Arc<Mutex<Vec<TrackerEvent>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let collected = events.clone();
+
+    let tracker = Tracker::builder()
+        .data_dir(temp_data_dir(name))
+        .games(vec![Game::default()])
+        .watcher(watcher)
+        .idle_detector(idle)
+        .checkpoint_interval(0)
+        .idle_timeout(idle_timeout)
+        .poll_interval(Duration::from_millis(10))
+        .subscribe(move |event: &TrackerEvent| collected.lock().unwrap().push(event.clone()))
+        .build();
+
+    (tracker, events)
+}
+
+/// Returns the number of [`TrackerEvent::Tick`] events.
+fn ticks(events: &Mutex<Vec<TrackerEvent>>) -> usize {
+    events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|event| matches!(event, TrackerEvent::Tick { .. }))
+        .count()
+}
+
+#[test]
+fn t_live_stopwatch_process_exit() {
+    let (mut tracker, events) = fake_tracker(
+        "live-stopwatch-process-exit",
+        FakeWatcher::running_for(1),
+        FakeIdle::default(),
+        Duration::ZERO,
+    );
+    let mut checkpoint = Checkpoint::new(Local::now());
+
+    tracker.handle().fire(Trigger::GameStarted);
+    let end_reason = tracker.live_stopwatch(&Game::default(), &mut checkpoint);
+
+    assert_eq!(end_reason, EndReason::ProcessExit);
+    assert_eq!(ticks(&events), 1);
+    assert_eq!(
+        tracker.handle().state(),
+        TrackerState::Stopping { shutdown: false }
+    );
+}
+
+#[test]
+fn t_live_stopwatch_stop_tracker() {
+    let (mut tracker, events) = fake_tracker(
+        "live-stopwatch-stop-tracker",
+        FakeWatcher::running_for(10),
+        FakeIdle::default(),
+        Duration::ZERO,
+    );
+    let mut checkpoint = Checkpoint::new(Local::now());
+
+    tracker.handle().fire(Trigger::GameStarted);
+    tracker.handle().stop();
+
+    let end_reason = tracker.live_stopwatch(&Game::default(), &mut checkpoint);
+
+    assert_eq!(end_reason, EndReason::StopTracker);
+    assert_eq!(ticks(&events), 0);
+}
+
+#[test]
+fn t_glob_match() {
+    assert!(glob_match(
+        "*rocketleague.exe",
+        "Z:\\Games\\RocketLeague.exe"
+    ));
+    assert!(glob_match("z:/games/*", "Z:\\Games\\RocketLeague.exe"));
+    assert!(glob_match("*a*b?d*", "xxaxxbcdxx"));
+    assert!(glob_match("*", ""));
+    assert!(!glob_match("*a*b?d", "xxaxxbcdxx"));
+    assert!(!glob_match("rocket", "rocketleague"));
+}
+
+/// Creates the [`ProcessInfo`] of a process with a space separated `cmdline`.
+fn process_info(name: &str, exe: Option<&str>, cmdline: &str) -> ProcessInfo {
+    ProcessInfo {
+        name: name.to_string(),
+        exe: exe.map(PathBuf::from),
+        cmdline: cmdline.split(' ').map(String::from).collect(),
+    }
+}
+
+#[test]
+fn t_detection_rules() {
+    let rules = DetectionRules::default();
+
+    // Windows
+    assert!(rules.matches(&process_info(
+        "RocketLeague.exe",
+        Some("C:\\Program Files\\Epic Games\\rocketleague\\Binaries\\Win64\\RocketLeague.exe"),
+        "RocketLeague.exe -EpicPortal"
+    )));
+
+    // Wine keeps the truncated name
+    assert!(rules.matches(&process_info("RocketLeague.ex", None, "")));
+
+    // Proton runs the game through wine-preloader
+    assert!(rules.matches(&process_info(
+        "wine64-preloade",
+        Some("/home/user/.steam/steam/steamapps/common/Proton 9.0/files/bin/wine64-preloader"),
+        "Z:\\home\\user\\.steam\\steam\\steamapps\\common\\rocketleague\\Binaries\\Win64\\RocketLeague.exe"
+    )));
+
+    // The Steam reaper process and Heroic
+    assert!(rules.matches(&process_info(
+        "reaper",
+        Some("/home/user/.steam/steam/ubuntu12_32/reaper"),
+        "/home/user/.steam/steam/ubuntu12_32/reaper SteamLaunch AppId=252950 -- /home/user/.steam/steam/steamapps/common/Proton/proton waitforexitandrun"
+    )));
+    assert!(rules.matches(&process_info(
+        "legendary",
+        None,
+        "/opt/Heroic/resources/app.asar.unpacked/build/bin/linux/legendary launch Sugar --wine /usr/bin/wine"
+    )));
+
+    // Other processes are not detected
+    assert!(!rules.matches(&process_info("bash", Some("/usr/bin/bash"), "bash")));
+    assert!(!rules.matches(&process_info(
+        "reaper",
+        None,
+        "reaper SteamLaunch AppId=2529500 -- proton"
+    )));
+
+    // Launcher patterns can be turned off and custom patterns added
+    let rules = DetectionRules {
+        exe_patterns: vec![String::from("/opt/games/rl/*")],
+        cmdline_patterns: vec![String::from("*my-launcher --game rocket*")],
+        launcher_patterns: false,
+        ..DetectionRules::default()
+    };
+
+    assert!(!rules.matches(&process_info("legendary", None, "legendary launch Sugar")));
+    assert!(rules.matches(&process_info("game", Some("/opt/games/rl/game"), "game")));
+    assert!(rules.matches(&process_info(
+        "python3",
+        None,
+        "python3 /usr/bin/my-launcher --game rocket-league"
+    )));
+}
+
+#[test]
+fn t_detection_rules_from_config() {
+    let config: Config =
+        toml::from_str("[detection]\nexe_patterns = [\"*/rl/*\"]\nlauncher_patterns = false\n")
+            .unwrap();
+
+    assert_eq!(
+        config.detection,
+        DetectionRules {
+            process_names: vec![String::from("RocketLeague.exe")],
+            exe_patterns: vec![String::from("*/rl/*")],
+            cmdline_patterns: Vec::new(),
+            launcher_patterns: false,
+        }
+    );
+}
+
+#[test]
+fn t_proc_watcher_detects_proton() {
+    let root = fake_proc(
+        "t-proc-watcher-detects-proton",
+        "4242",
+        "reaper\n",
+        b"/home/user/.steam/steam/ubuntu12_32/reaper\0SteamLaunch\0AppId=252950\0--\0proton\0",
+    );
+
+    assert!(ProcWatcher::with_root(&root).detect(&DetectionRules::default()));
+    assert!(!ProcWatcher::with_root(&root).is_running("RocketLeague.exe"));
+}
+
+fn custom_game(id: &str, name: &str) -> CustomGame {
+    CustomGame {
+        id: id.to_string(),
+        name: name.to_string(),
+        process_names: vec![format!("{name}.exe")],
+        ..CustomGame::default()
+    }
+}
+
+#[test]
+fn t_waiting_message() {
+    let rocket_league = registry(&DetectionRules::default(), &[]);
+    let games = registry(
+        &DetectionRules::default(),
+        &[custom_game("fall-guys", "Fall Guys")],
+    );
+
+    assert_eq!(
+        waiting_message(&rocket_league),
+        "Waiting for Rocket League to start"
+    );
+    assert_eq!(waiting_message(&games), "Waiting for a game to start");
+}
+
+#[test]
+fn t_game_registry() {
+    let games = registry(
+        &DetectionRules::default(),
+        &[
+            custom_game("fall-guys", "Fall Guys"),
+            custom_game("fall-guys", "Fall Guys Again"),
+            custom_game("Bad Id", "Bad"),
+            custom_game(ROCKET_LEAGUE_ID, "Rocket League Again"),
+            custom_game("minecraft_java", "Minecraft"),
+        ],
+    );
+
+    let ids: Vec<&str> = games.iter().map(|game| game.id.as_str()).collect();
+
+    assert_eq!(ids, [ROCKET_LEAGUE_ID, "fall-guys", "minecraft_java"]);
+    assert!(games[0].rules.launcher_patterns);
+    assert!(!games[1].rules.launcher_patterns);
+    assert_eq!(games[1].rules.process_names, ["Fall Guys.exe"]);
+
+    let data_dir = DataDir::new("/tmp/rl-hours");
+
+    assert_eq!(games[0].data_dir(&data_dir), data_dir);
+    assert_eq!(
+        games[1].data_dir(&data_dir).root(),
+        Path::new("/tmp/rl-hours/games/fall-guys")
+    );
+}
+
+#[test]
+fn t_games_from_config() {
+    let config: Config = toml::from_str(
+        "[[games]]\nid = \"fall-guys\"\nname = \"Fall Guys\"\nprocess_names = [\"FallGuys_client_game.exe\"]\n",
+    )
+    .unwrap();
+
+    let games = config.games();
+
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[0], Game::default());
+    assert_eq!(games[1].name, "Fall Guys");
+    assert_eq!(games[1].icon, None);
+}
+
+#[test]
+fn t_recalculate_game() {
+    let data_dir = temp_data_dir("t-recalculate-game");
+    let game: Game = custom_game("fall-guys", "Fall Guys").into();
+    let game_dir = game.data_dir(&data_dir);
+
+    fs::create_dir_all(game_dir.root()).unwrap();
+    append_session(&game_dir.sessions_file(), &session_on("2024-09-15", 7200)).unwrap();
+
+    let report = recalculate_game(&data_dir, &game).unwrap();
+
+    assert_eq!(report.summary.total_seconds, 7200);
+    assert!(fs::read_to_string(game_dir.hours_file())
+        .unwrap()
+        .starts_with("Fall Guys Hours\nTotal Seconds: 7200s\n"));
+
+    // The Rocket League files are left alone
+    assert!(!data_dir.hours_file().exists());
+}
+
+#[test]
+fn t_latest_sessions_across_games() {
+    let data_dir = temp_data_dir("t-latest-sessions-across-games");
+    let games = registry(
+        &DetectionRules::default(),
+        &[
+            custom_game("fall-guys", "Fall Guys"),
+            custom_game("unplayed", "Unplayed"),
+        ],
+    );
+    let game_dir = games[1].data_dir(&data_dir);
+
+    fs::create_dir_all(game_dir.root()).unwrap();
+    append_session(&data_dir.sessions_file(), &session_on("2024-09-14", 60)).unwrap();
+    append_session(&data_dir.sessions_file(), &session_on("2024-09-16", 60)).unwrap();
+    append_session(&game_dir.sessions_file(), &session_on("2024-09-15", 60)).unwrap();
+
+    let sessions = latest_sessions(&data_dir, &games, 2).unwrap();
+    let names: Vec<&str> = sessions.iter().map(|(name, _)| name.as_str()).collect();
+
+    assert_eq!(names, ["Rocket League", "Fall Guys"]);
+    assert_eq!(sessions[0].1, session_on("2024-09-16", 60));
+}
+
+/// The background image of the main title in `home.css`.
+const TITLE_IMAGE: &str = "https://media.graphassets.com/resize=fit:clip,height:720,width:1280/output=format:webp/3jpQ4ljjRcGCAfNdzVUG";
+
+#[test]
+fn t_write_assets_offline() {
+    let data_dir = temp_data_dir("write-assets-offline");
+
+    write_assets(&data_dir, Theme::Bundled).unwrap();
+
+    for asset in ASSETS {
+        let written = fs::read(data_dir.website_dir().join(asset.path)).unwrap();
+        assert_eq!(written, asset.bytes, "{}", asset.path);
+    }
+
+    // The bundled stylesheets and scripts never load anything from the network, apart from the background image of
+    // the main title, which falls back to its background colour when it is offline
+    for asset in ASSETS.iter().filter(|asset| !asset.path.ends_with(".png")) {
+        let text = std::str::from_utf8(asset.bytes).unwrap();
+        let text = text.replace(TITLE_IMAGE, "");
+        assert!(!text.contains("http"), "{}", asset.path);
+    }
+}
+
+#[test]
+fn t_generate_page_is_offline() {
+    let data_dir = legacy_data_dir("generate-page-offline");
+    let mut hours_file = fs::File::open(data_dir.hours_file());
+
+    let page = generate_page(
+        &Templates::new(),
+        &mut hours_file,
+        &[],
+        &[],
+        &Charts::default(),
+    )
+    .unwrap();
+
+    assert!(page.contains("../css/fonts.css"));
+    assert!(!page.contains("fonts.googleapis.com"));
+
+    // The only remote link is the repository in the footer
+    assert_eq!(page.matches("https://").count(), 1);
+    assert!(page.contains("https://github.com/OneilNvM/rl-hours-tracker"));
+}
+
+#[test]
+fn t_default_template_matches_layout() {
+    let data_dir = legacy_data_dir("default-template-layout");
+    let mut hours_file = fs::File::open(data_dir.hours_file());
+    let sessions = vec![(String::from("<b>Game</b>"), session_on("2024-09-02", 300))];
+
+    let page = generate_page(
+        &Templates::new(),
+        &mut hours_file,
+        &sessions,
+        &[],
+        &Charts::default(),
+    )
+    .unwrap();
+
+    assert!(page.contains(r#"<body class="body adaptive">"#));
+    assert!(page.contains("Rocket League<br>Hours Tracker</h1>"));
+    assert!(page.contains("<p>Total Hours: 27.8hrs</p>"));
+    assert!(page.contains(r#"<a href="history.html">Session History</a>"#));
+    assert!(page.contains("&copy; OneilNvM 2024"));
+
+    // The name of the game is only shown with multiple games, and is escaped
+    assert!(page.contains("<p>2024-09-02 19:55 - 20:00 300s</p>"));
+    assert!(!page.contains("<b>Game</b>"));
+}
+
+#[test]
+fn t_user_templates_replace_defaults() {
+    let data_dir = temp_data_dir("user-templates");
+    fs::create_dir_all(data_dir.templates_dir()).unwrap();
+    fs::write(
+        data_dir.templates_dir().join("base.html"),
+        "<html><body>{% block body %}{% endblock %}<footer>ACME Esports</footer></body></html>",
+    )
+    .unwrap();
+    fs::write(data_dir.templates_dir().join("notes.txt"), "{% broken").unwrap();
+
+    let templates = Templates::load(&data_dir).unwrap();
+    let index = history_index(&templates, &[]).unwrap();
+
+    // The default history.html extends the user base.html
+    assert!(index.contains("<footer>ACME Esports</footer>"));
+    assert!(index.contains("No sessions have been recorded yet."));
+    assert!(!index.contains("OneilNvM"));
+
+    fs::write(data_dir.templates_dir().join("index.html"), "{% if %}").unwrap();
+    assert!(matches!(
+        Templates::load(&data_dir),
+        Err(TrackerError::Template(_))
+    ));
+
+    fs::remove_dir_all(data_dir.root()).unwrap();
+}
+
+#[test]
+fn t_write_theme() {
+    let data_dir = temp_data_dir("write-theme");
+    let css_dir = data_dir.theme_dir().join("css");
+    fs::create_dir_all(&css_dir).unwrap();
+    fs::write(css_dir.join("home.css"), "body { color: teal; }").unwrap();
+    fs::write(css_dir.join("brand.css"), ".footer { display: none; }").unwrap();
+
+    write_assets(&data_dir, Theme::Bundled).unwrap();
+    let stylesheets = write_theme(&data_dir).unwrap();
+
+    // The theme replaces the bundled home.css, and brand.css is linked from every page
+    assert_eq!(stylesheets, vec![String::from("../css/brand.css")]);
+    assert_eq!(
+        fs::read_to_string(data_dir.website_css_dir().join("home.css")).unwrap(),
+        "body { color: teal; }"
+    );
+    assert!(data_dir.website_css_dir().join("brand.css").is_file());
+
+    let mut templates = Templates::new();
+    templates.set_stylesheets(&stylesheets);
+    let index = history_index(&templates, &[]).unwrap();
+    // The slashes of the link are escaped, which the browser reads as "../css/brand.css"
+    assert!(index.contains(r#"<link href="..&#x2f;css&#x2f;brand.css" rel="stylesheet">"#));
+
+    // Without a theme folder, nothing is linked
+    fs::remove_dir_all(data_dir.theme_dir()).unwrap();
+    assert!(write_theme(&data_dir).unwrap().is_empty());
+
+    fs::remove_dir_all(data_dir.root()).unwrap();
+}
+
+#[test]
+fn t_live_stopwatch_pauses_while_idle() {
+    // Active for 2 checks, idle past the timeout for 2 checks, then active again until the game closes
+    let (mut tracker, events) = fake_tracker(
+        "live-stopwatch-pauses-while-idle",
+        FakeWatcher::running_for(5),
+        FakeIdle::new([0, 0, 120, 121, 0]),
+        Duration::from_secs(60),
+    );
+    let mut checkpoint = Checkpoint::new(Local::now());
+
+    tracker.handle().fire(Trigger::GameStarted);
+    let end_reason = tracker.live_stopwatch(&Game::default(), &mut checkpoint);
+
+    assert_eq!(end_reason, EndReason::ProcessExit);
+    assert_eq!(checkpoint.idle.len(), 1);
+
+    // The idle span starts when the player stopped being active, not when the timeout was reached
+    let span = checkpoint.idle[0];
+    assert!(span.seconds() >= 2 && span.seconds() <= 4, "{span:?}");
+
+    // The clock is wound back to 0 when the timeout is reached, and counts the last active second
+    let elapsed: Vec<(u64, bool)> = events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|event| match event {
+            TrackerEvent::Tick { elapsed, idle, .. } => Some((*elapsed, *idle)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        elapsed,
+        [(1, false), (2, false), (0, true), (0, true), (1, false)]
+    );
+}
+
+#[test]
+fn t_tracker_pause_is_not_counted() {
+    let (mut tracker, events) = fake_tracker(
+        "tracker-pause",
+        FakeWatcher::running_for(3),
+        FakeIdle::default(),
+        Duration::ZERO,
+    );
+    let mut checkpoint = Checkpoint::new(Local::now());
+    let handle = tracker.handle();
+
+    handle.fire(Trigger::GameStarted);
+    assert_eq!(handle.pause(), TrackerState::Paused);
+    let end_reason = tracker.live_stopwatch(&Game::default(), &mut checkpoint);
+
+    assert_eq!(end_reason, EndReason::ProcessExit);
+    assert_eq!(checkpoint.idle.len(), 1);
+
+    let events = events.lock().unwrap();
+    assert!(matches!(events[0], TrackerEvent::Paused { .. }));
+    assert!(events.iter().all(|event| match event {
+        TrackerEvent::Tick {
+            elapsed, paused, ..
+        } => *elapsed == 0 && *paused,
+        _ => true,
+    }));
+}
+
+#[test]
+fn t_tracker_records_session_and_emits_events() {
+    let (mut tracker, events) = fake_tracker(
+        "tracker-records-session",
+        FakeWatcher::running_for(3),
+        FakeIdle::default(),
+        Duration::ZERO,
+    );
+    let game = tracker.detect().unwrap();
+    let session = tracker.track(&game).unwrap();
+    let game_dir = game.data_dir(tracker.data_dir());
+
+    assert_eq!(session.end_reason, EndReason::ProcessExit);
+    assert_eq!(
+        read_sessions(&game_dir.sessions_file()).unwrap(),
+        std::slice::from_ref(&session)
+    );
+    assert!(game_dir.hours_file().exists());
+    assert_eq!(tracker.handle().state(), TrackerState::Waiting);
+
+    let events = events.lock().unwrap();
+    assert!(
+        matches!(&events[0], TrackerEvent::GameStarted { game: started, .. } if *started == game)
+    );
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| matches!(event, TrackerEvent::Tick { .. }))
+            .count(),
+        2
+    );
+    assert!(
+        matches!(&events[events.len() - 2], TrackerEvent::SessionEnded { session: ended, .. } if *ended == session)
+    );
+    assert!(matches!(
+        &events[events.len() - 1],
+        TrackerEvent::PastTwoUpdated { summary, .. } if summary.total_seconds == session.duration
+    ));
+}
+
+#[test]
+fn t_tracker_checkpoints_first_session_of_custom_game() {
+    let data_dir = temp_data_dir("tracker-checkpoints-custom-game");
+    let game: Game = custom_game("fall-guys", "Fall Guys").into();
+    let checkpoint_file = game.data_dir(&data_dir).checkpoint_file();
+    let checkpointed = Arc::new(Mutex::new(Vec::new()));
+    let collected = checkpointed.clone();
+    let watched = checkpoint_file.clone();
+
+    let mut tracker = Tracker::builder()
+        .data_dir(data_dir)
+        .games(vec![game.clone()])
+        .watcher(FakeWatcher::running_for(2))
+        .idle_detector(FakeIdle::default())
+        .checkpoint_interval(1)
+        .idle_timeout(Duration::ZERO)
+        .subscribe(move |event: &TrackerEvent| {
+            if let TrackerEvent::Tick { elapsed, .. } = event {
+                let checkpoint = Checkpoint::load(&watched).ok().flatten();
+                collected
+                    .lock()
+                    .unwrap()
+                    .push(checkpoint.map(|checkpoint| (*elapsed, checkpoint.duration)));
+            }
+        })
+        .build();
+
+    tracker.track(&game).unwrap();
+
+    // The checkpoint is on disk from the first second, and is removed once the session is recorded
+    assert_eq!(*checkpointed.lock().unwrap(), [Some((1, 1)), Some((2, 2))]);
+    assert!(!checkpoint_file.exists());
+}
+
+/// Returns a tracker which is stopped with [`TrackerHandle::stop`] on the first tick of its session, and the events
+/// it emitted.
+fn stopping_tracker(name: &str, watcher: FakeWatcher) -> (Tracker, Arc<Mutex<Vec<TrackerEvent>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let collected = events.clone();
+    let handle = TrackerHandle::new();
+    let stop = handle.clone();
+
+    let tracker = Tracker::builder()
+        .data_dir(temp_data_dir(name))
+        .games(vec![Game::default()])
+        .watcher(watcher)
+        .idle_detector(FakeIdle::default())
+        .checkpoint_interval(0)
+        .idle_timeout(Duration::ZERO)
+        .poll_interval(Duration::from_millis(10))
+        .handle(handle)
+        .subscribe(move |event: &TrackerEvent| {
+            if matches!(event, TrackerEvent::Tick { .. }) {
+                stop.stop();
+            }
+
+            collected.lock().unwrap().push(event.clone());
+        })
+        .build();
+
+    (tracker, events)
+}
+
+#[test]
+fn t_tracker_stop_waits_for_game_exit() {
+    let (mut tracker, _) = stopping_tracker(
+        "tracker-stop-waits-for-game-exit",
+        FakeWatcher::running_for(4),
+    );
+
+    let game = tracker.detect().unwrap();
+    let session = tracker.track(&game).unwrap();
+
+    assert_eq!(session.end_reason, EndReason::StopTracker);
+    assert_eq!(tracker.handle().state(), TrackerState::WaitingForExit);
+
+    // The game is still running, so a new session is not started
+    assert_eq!(tracker.detect(), None);
+    assert_eq!(tracker.detect(), None);
+    assert_eq!(tracker.handle().state(), TrackerState::WaitingForExit);
+
+    // The game was closed
+    assert_eq!(tracker.detect(), None);
+    assert_eq!(tracker.handle().state(), TrackerState::Waiting);
+}
+
+#[test]
+fn t_tracker_handle_stop_does_not_restart_the_session() {
+    let (tracker, events) = stopping_tracker(
+        "tracker-handle-stop-does-not-restart",
+        FakeWatcher::running_for(5),
+    );
+    let handle = tracker.handle();
+    let thread = tracker.start();
+    let ended = |events: &Mutex<Vec<TrackerEvent>>| {
+        events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|event| matches!(event, TrackerEvent::SessionEnded { .. }))
+    };
+
+    // Wait until the stopped session was recorded and the game was closed
+    for _ in 0..500 {
+        if ended(&events) && handle.state() == TrackerState::Waiting {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    assert_eq!(handle.state(), TrackerState::Waiting);
+    handle.shutdown();
+    thread.join().unwrap();
+
+    // The game kept running after the stop, but only one session was started
+    let events = events.lock().unwrap();
+    let started = events
+        .iter()
+        .filter(|event| matches!(event, TrackerEvent::GameStarted { .. }))
+        .count();
+
+    assert_eq!(started, 1);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        TrackerEvent::SessionEnded { session, .. } if session.end_reason == EndReason::StopTracker
+    )));
+}
+
+#[test]
+fn t_tracker_start_and_shutdown() {
+    let (tracker, _) = fake_tracker(
+        "tracker-start-and-shutdown",
+        FakeWatcher::new([]),
+        FakeIdle::default(),
+        Duration::ZERO,
+    );
+    let handle = tracker.handle();
+
+    let thread = tracker.start();
+    handle.shutdown();
+
+    let tracker = thread.join().unwrap();
+    assert_eq!(tracker.handle().state(), TrackerState::Stopped);
+}
+
+#[test]
+fn t_idle_config() {
+    let config: Config = toml::from_str("[idle]\ntimeout_minutes = 5\ncpu = false\n").unwrap();
+
+    assert_eq!(config.idle.timeout(), Duration::from_secs(300));
+    // Controller input is not seen by the input check, so it is off unless it is enabled
+    assert!(!config.idle.input);
+    assert!(!config.idle.cpu);
+
+    let disabled = IdleConfig {
+        enabled: false,
+        ..IdleConfig::default()
+    };
+
+    assert_eq!(disabled.timeout(), Duration::ZERO);
+}
+
+#[test]
+fn t_session_idle_time() {
+    let mut session = session_on("2024-09-15", 3600);
+
+    // Sessions without idle time keep the original format
+    assert!(!serde_json::to_string(&session).unwrap().contains("idle"));
+
+    session.idle.push(IdleSpan {
+        start: session.end - CDuration::seconds(1800),
+        end: session.end - CDuration::seconds(600),
+    });
+
+    let line = serde_json::to_string(&session).unwrap();
+
+    assert_eq!(session.idle_seconds(), 1200);
+    assert_eq!(serde_json::from_str::<Session>(&line).unwrap(), session);
+    assert!(session.to_string().ends_with("3600s (1200s idle)"));
+
+    let summary = HoursSummary::from_store(
+        &vec![session],
+        0,
+        NaiveDate::from_ymd_opt(2024, 9, 16).unwrap(),
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(summary.idle_seconds, 1200);
+    assert!(summary
+        .contents("Rocket League")
+        .ends_with("Idle Hours: 0.3hrs\n"));
+}
+
+#[test]
+fn t_prompt_decide() {
+    // The program is not in daemon mode during the tests, so only Ask is left to the user
+    assert_eq!(Prompt::Ask.decide(true), None);
+    assert_eq!(Prompt::Yes.decide(false), Some(true));
+    assert_eq!(Prompt::No.decide(true), Some(false));
+
+    let config: Config =
+        toml::from_str("daemon = true\nopen_website = \"no\"\nend_after_session = \"yes\"\n")
+            .unwrap();
+
+    assert!(config.daemon);
+    assert_eq!(config.open_website, Prompt::No);
+    assert_eq!(config.install_updates, Prompt::Ask);
+    assert_eq!(config.end_after_session, Prompt::Yes);
+}
+
+#[test]
+fn t_request_shutdown() {
+    let tracker = TrackerHandle::new();
+    tracker.fire(Trigger::GameStarted);
+
+    // A session in progress is stopped, so it is recorded before the program quits
+    request_shutdown(None, &tracker);
+
+    assert_eq!(tracker.state(), TrackerState::Stopping { shutdown: true });
+    assert!(tracker.is_shutdown());
+
+    let tracker = TrackerHandle::new();
+
+    request_shutdown(None, &tracker);
+
+    assert_eq!(tracker.state(), TrackerState::Stopped);
+}
+
+#[test]
+fn t_state_waiting_transitions() {
+    let waiting = TrackerState::Waiting;
+
+    assert_eq!(waiting.next(Trigger::GameStarted), TrackerState::Tracking);
+    assert_eq!(waiting.next(Trigger::Shutdown), TrackerState::Stopped);
+
+    // There is no session to pause, stop or record
+    for trigger in [
+        Trigger::GameClosed,
+        Trigger::Pause,
+        Trigger::Resume,
+        Trigger::Stop,
+        Trigger::SessionRecorded,
+    ] {
+        assert_eq!(waiting.next(trigger), waiting, "{trigger:?}");
+    }
+}
+
+#[test]
+fn t_state_tracking_transitions() {
+    let tracking = TrackerState::Tracking;
+    let stopping = TrackerState::Stopping { shutdown: false };
+
+    assert_eq!(tracking.next(Trigger::Pause), TrackerState::Paused);
+    assert_eq!(tracking.next(Trigger::GameClosed), stopping);
+    assert_eq!(tracking.next(Trigger::Stop), TrackerState::Ending);
+    assert_eq!(
+        tracking.next(Trigger::Shutdown),
+        TrackerState::Stopping { shutdown: true }
+    );
+
+    for trigger in [
+        Trigger::GameStarted,
+        Trigger::Resume,
+        Trigger::SessionRecorded,
+    ] {
+        assert_eq!(tracking.next(trigger), tracking, "{trigger:?}");
+    }
+}
+
+#[test]
+fn t_state_paused_transitions() {
+    let paused = TrackerState::Paused;
+    let stopping = TrackerState::Stopping { shutdown: false };
+
+    assert_eq!(paused.next(Trigger::Resume), TrackerState::Tracking);
+    assert_eq!(paused.next(Trigger::GameClosed), stopping);
+    assert_eq!(paused.next(Trigger::Stop), TrackerState::Ending);
+    assert_eq!(
+        paused.next(Trigger::Shutdown),
+        TrackerState::Stopping { shutdown: true }
+    );
+
+    for trigger in [
+        Trigger::GameStarted,
+        Trigger::Pause,
+        Trigger::SessionRecorded,
+    ] {
+        assert_eq!(paused.next(trigger), paused, "{trigger:?}");
+    }
+}
+
+#[test]
+fn t_state_stopping_transitions() {
+    let stopping = TrackerState::Stopping { shutdown: false };
+    let shutting_down = TrackerState::Stopping { shutdown: true };
+
+    assert_eq!(
+        stopping.next(Trigger::SessionRecorded),
+        TrackerState::Waiting
+    );
+    assert_eq!(
+        shutting_down.next(Trigger::SessionRecorded),
+        TrackerState::Stopped
+    );
+
+    // A shutdown during the recording is kept until the session is recorded
+    assert_eq!(stopping.next(Trigger::Shutdown), shutting_down);
+    assert_eq!(shutting_down.next(Trigger::Shutdown), shutting_down);
+
+    for trigger in [
+        Trigger::GameStarted,
+        Trigger::GameClosed,
+        Trigger::Pause,
+        Trigger::Resume,
+        Trigger::Stop,
+    ] {
+        assert_eq!(stopping.next(trigger), stopping, "{trigger:?}");
+        assert_eq!(shutting_down.next(trigger), shutting_down, "{trigger:?}");
+    }
+}
+
+#[test]
+fn t_state_stop_waits_for_game_exit() {
+    let ending = TrackerState::Ending;
+    let waiting_for_exit = TrackerState::WaitingForExit;
+
+    // The game is still running once the stopped session was recorded
+    assert_eq!(ending.next(Trigger::SessionRecorded), waiting_for_exit);
+    assert_eq!(
+        ending.next(Trigger::Shutdown),
+        TrackerState::Stopping { shutdown: true }
+    );
+    assert!(ending.is_tracking());
+    assert!(!ending.is_active());
+
+    // No session starts until the game was closed
+    assert_eq!(
+        waiting_for_exit.next(Trigger::GameClosed),
+        TrackerState::Waiting
+    );
+    assert_eq!(
+        waiting_for_exit.next(Trigger::Shutdown),
+        TrackerState::Stopped
+    );
+    assert!(!waiting_for_exit.is_tracking());
+
+    for trigger in [
+        Trigger::GameStarted,
+        Trigger::Pause,
+        Trigger::Resume,
+        Trigger::Stop,
+        Trigger::SessionRecorded,
+    ] {
+        assert_eq!(
+            waiting_for_exit.next(trigger),
+            waiting_for_exit,
+            "{trigger:?}"
+        );
+    }
+
+    for trigger in [
+        Trigger::GameStarted,
+        Trigger::GameClosed,
+        Trigger::Pause,
+        Trigger::Resume,
+        Trigger::Stop,
+    ] {
+        assert_eq!(ending.next(trigger), ending, "{trigger:?}");
+    }
+}
+
+#[test]
+fn t_state_stopped_is_final() {
+    for trigger in [
+        Trigger::GameStarted,
+        Trigger::GameClosed,
+        Trigger::Pause,
+        Trigger::Resume,
+        Trigger::Stop,
+        Trigger::Shutdown,
+        Trigger::SessionRecorded,
+    ] {
+        assert_eq!(
+            TrackerState::Stopped.next(trigger),
+            TrackerState::Stopped,
+            "{trigger:?}"
+        );
+    }
+}
+
+#[test]
+fn t_shared_state_notifies_receivers() {
+    let state = SharedState::new();
+    let mut receiver = state.subscribe();
+
+    // A trigger which does not change the state does not notify the receivers
+    assert_eq!(state.fire(Trigger::Stop), TrackerState::Waiting);
+    assert!(!receiver.has_changed().unwrap());
+
+    assert_eq!(state.fire(Trigger::GameStarted), TrackerState::Tracking);
+    assert!(receiver.has_changed().unwrap());
+    assert_eq!(*receiver.borrow_and_update(), TrackerState::Tracking);
+
+    // Every clone shares the same state
+    state.clone().fire(Trigger::Pause);
+    assert_eq!(state.get(), TrackerState::Paused);
+    assert_eq!(*receiver.borrow_and_update(), TrackerState::Paused);
+}
+
+#[test]
+fn t_cli_subcommands() {
+    let parse = |args: &[&str]| Cli::try_parse_from(args).unwrap();
+
+    assert_eq!(parse(&["rl-hours-tracker"]).command(), Command::Track);
+    assert_eq!(
+        parse(&["rl-hours-tracker", "report", "--since", "2024-09-01"]).command(),
+        Command::Report {
+            since: NaiveDate::from_ymd_opt(2024, 9, 1),
+            until: None,
+            window: None,
+            game: ROCKET_LEAGUE_ID.to_string(),
+        }
+    );
+    assert_eq!(
+        parse(&["rl-hours-tracker", "export", "--format", "csv"]).command(),
+        Command::Export {
+            format: ExportFormat::Csv,
+            output: None,
+            game: ROCKET_LEAGUE_ID.to_string(),
+        }
+    );
+    assert_eq!(
+        parse(&["rl-hours-tracker", "validate"]).command(),
+        Command::Validate
+    );
+    assert_eq!(
+        parse(&["rl-hours-tracker", "config", "set", "daemon", "true"]).command(),
+        Command::Config {
+            action: ConfigAction::Set {
+                key: "daemon".to_string(),
+                value: "true".to_string(),
+            }
+        }
+    );
+
+    let cli = parse(&[
+        "rl-hours-tracker",
+        "status",
+        "--data-dir",
+        "/tmp/rl",
+        "--daemon",
+    ]);
+
+    assert_eq!(cli.data_dir, Some(PathBuf::from("/tmp/rl")));
+    assert!(cli.daemon);
+
+    let cli = parse(&["rl-hours-tracker", "--data-dir=/tmp/rl"]);
+
+    assert_eq!(cli.data_dir, Some(PathBuf::from("/tmp/rl")));
+    assert!(!cli.daemon);
+    assert!(Cli::try_parse_from(["rl-hours-tracker", "report", "--since", "yesterday"]).is_err());
+
+    // The data folder is recovered and migrated before every command which reads it
+    assert!(parse(&["rl-hours-tracker", "report"])
+        .command()
+        .reads_data());
+    assert!(parse(&["rl-hours-tracker", "website"])
+        .command()
+        .reads_data());
+    assert!(!parse(&["rl-hours-tracker", "validate"])
+        .command()
+        .reads_data());
+    assert!(!parse(&["rl-hours-tracker", "config", "path"])
+        .command()
+        .reads_data());
+}
+
+#[test]
+fn t_config_get_set() {
+    let mut config = Config::default();
+
+    config.set("idle.timeout_minutes", "5").unwrap();
+    config.set("open_website", "no").unwrap();
+    config.set("data_dir", "/tmp/rl-hours").unwrap();
+    config.set("idle.cpu_threshold", "2").unwrap();
+
+    assert_eq!(config.idle.timeout_minutes, 5);
+    assert_eq!(config.open_website, Prompt::No);
+    assert_eq!(config.data_dir, Some(PathBuf::from("/tmp/rl-hours")));
+    assert_eq!(
+        config.get("idle.timeout_minutes").unwrap().as_integer(),
+        Some(5)
+    );
+    assert_eq!(config.get("open_website").unwrap().as_str(), Some("no"));
+
+    // Unknown keys and invalid values leave the configuration unchanged
+    assert!(config.set("idle.unknown", "1").is_err());
+    assert!(config.set("checkpoint_interval", "soon").is_err());
+    assert!(config.get("unknown").is_err());
+    assert_eq!(
+        config.checkpoint_interval,
+        Config::default().checkpoint_interval
+    );
+}
+
+#[test]
+fn t_config_set_in_file() {
+    let dir = temp_data_dir("t-config-set-in-file");
+    let path = dir.root().join("config.toml");
+
+    // A missing file is created from the default configuration
+    let (config, backup) = Config::set_in_file(&path, "idle.timeout_minutes", "5").unwrap();
+
+    assert_eq!(config.idle.timeout_minutes, 5);
+    assert_eq!(backup, None);
+    assert_eq!(Config::load_from(&path).unwrap(), config);
+
+    // A file which is not valid is kept as a backup and replaced
+    fs::write(&path, "idle = [not toml").unwrap();
+    let (config, backup) = Config::set_in_file(&path, "open_website", "no").unwrap();
+    let backup = backup.unwrap();
+
+    assert_eq!(config.open_website, Prompt::No);
+    assert_eq!(config.idle, Config::default().idle);
+    assert_eq!(backup, dir.root().join("config.toml.bak"));
+    assert_eq!(fs::read_to_string(backup).unwrap(), "idle = [not toml");
+    assert_eq!(Config::load_from(&path).unwrap(), config);
+
+    // The file is not replaced when the setting is not valid
+    fs::write(&path, "idle = [not toml").unwrap();
+    assert!(Config::set_in_file(&path, "unknown", "1").is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "idle = [not toml");
+}
+
+#[test]
+fn t_report_between_dates() {
+    let sessions = vec![
+        session_on("2024-09-01", 60),
+        session_on("2024-09-15", 3600),
+        session_on("2024-09-15", 1800),
+        session_on("2024-09-16", 600),
+    ];
+    let since = NaiveDate::from_ymd_opt(2024, 9, 2).unwrap();
+    let until = NaiveDate::from_ymd_opt(2024, 9, 15).unwrap();
+
+    let summary = report(&sessions, since, until).unwrap();
+
+    assert_eq!(summary.seconds, 5400);
+    assert_eq!(summary.sessions, 2);
+    assert_eq!(summary.days.len(), 1);
+    assert_eq!(summary.days[0].period_start, until);
+
+    // An empty period is not an error
+    let empty = report(
+        &sessions,
+        until + CDuration::days(5),
+        until + CDuration::days(9),
+    )
+    .unwrap();
+
+    assert_eq!((empty.seconds, empty.sessions), (0, 0));
+    assert!(empty.days.is_empty());
+
+    // Only the idle time inside the report is counted for a session which spans its start
+    let mut session = session_between("2024-09-14T22:00:00+00:00", "2024-09-15T02:00:00+00:00");
+    session.idle.push(IdleSpan {
+        start: DateTime::parse_from_rfc3339("2024-09-14T23:00:00+00:00").unwrap(),
+        end: DateTime::parse_from_rfc3339("2024-09-15T01:00:00+00:00").unwrap(),
+    });
+    session.duration = 7200;
+
+    let summary = report(&vec![session], until, until).unwrap();
+
+    assert_eq!(summary.seconds, 3600);
+    assert_eq!(summary.idle_seconds, 3600);
+}
+
+#[test]
+fn t_export_import_sessions() {
+    let source = temp_data_dir("t-export-sessions");
+    let target = temp_data_dir("t-import-sessions");
+
+    fs::create_dir_all(source.root()).unwrap();
+    append_session(&source.sessions_file(), &session_on("2024-09-14", 60)).unwrap();
+    append_session(&source.sessions_file(), &session_on("2024-09-15", 120)).unwrap();
+    append_session(&target.sessions_file(), &session_on("2024-09-14", 60)).unwrap();
+
+    let mut jsonl = Vec::new();
+    assert_eq!(
+        export_sessions(&source, ExportFormat::Jsonl, &mut jsonl).unwrap(),
+        2
+    );
+
+    // The session which is already recorded is skipped, including on a second import
+    assert_eq!(import_sessions(&target, jsonl.as_slice()).unwrap(), 1);
+    assert_eq!(import_sessions(&target, jsonl.as_slice()).unwrap(), 0);
+    assert_eq!(
+        read_sessions(&target.sessions_file()).unwrap(),
+        read_sessions(&source.sessions_file()).unwrap()
+    );
+
+    let mut csv = Vec::new();
+    export_sessions(&source, ExportFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(
+        lines[0],
+        "start,end,duration,idle_seconds,end_reason,app_version"
+    );
+    assert_eq!(
+        lines[2],
+        format!(
+            "2024-09-15T19:58:00+01:00,2024-09-15T20:00:00+01:00,120,0,process_exit,{}",
+            env!("CARGO_PKG_VERSION")
+        )
+    );
+
+    // Fields with commas, quotes or line breaks are quoted
+    let mut session = session_on("2024-09-16", 60);
+    session.app_version = "1.0, \"dev\"\nbuild".to_string();
+    append_session(&source.sessions_file(), &session).unwrap();
+
+    let mut csv = Vec::new();
+    export_sessions(&source, ExportFormat::Csv, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+
+    assert!(csv.ends_with("60,0,process_exit,\"1.0, \"\"dev\"\"\nbuild\"\n"));
+}
+
+#[test]
+fn t_status_of_games() {
+    let data_dir = temp_data_dir("t-status-of-games");
+    let games = registry(
+        &DetectionRules::default(),
+        &[custom_game("fall-guys", "Fall Guys")],
+    );
+
+    fs::create_dir_all(data_dir.root()).unwrap();
+    append_session(&data_dir.sessions_file(), &session_on("2024-09-14", 60)).unwrap();
+    Checkpoint::new(Local::now())
+        .save(&data_dir.checkpoint_file())
+        .unwrap();
+
+    let statuses = status(&data_dir, &games).unwrap();
+
+    // Games without any data are left out
+    assert_eq!(statuses.len(), 1);
+    assert_eq!(statuses[0].game.name, "Rocket League");
+    assert_eq!(statuses[0].summary.total_seconds, 60);
+    assert!(statuses[0].session.is_some());
+}
+
+#[test]
+fn t_window_parse() {
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+
+    assert_eq!("14d".parse::<Window>().unwrap(), Window::default());
+    assert_eq!("2w".parse::<Window>().unwrap(), Window::Days(14));
+    assert_eq!("month".parse::<Window>().unwrap(), Window::CalendarMonth);
+    assert_eq!(
+        "2024-09-01..2024-09-30".parse::<Window>().unwrap(),
+        Window::Range(date(1), date(30))
+    );
+    assert!("2024-09-30..2024-09-01".parse::<Window>().is_err());
+    assert!("fortnight".parse::<Window>().is_err());
+    assert!("d".parse::<Window>().is_err());
+    assert!("7é".parse::<Window>().is_err());
+    assert!("é".parse::<Window>().is_err());
+    assert_eq!(
+        format!("{MAX_WINDOW_DAYS}d").parse::<Window>().unwrap(),
+        Window::Days(MAX_WINDOW_DAYS)
+    );
+    assert!(format!("{}d", MAX_WINDOW_DAYS + 1)
+        .parse::<Window>()
+        .is_err());
+    assert!("4000000000d".parse::<Window>().is_err());
+    assert!("613566757w".parse::<Window>().is_err());
+
+    for window in ["7d", "month", "2024-09-01..2024-09-30"] {
+        assert_eq!(window.parse::<Window>().unwrap().to_string(), window);
+    }
+
+    let config: Config = toml::from_str(r#"windows = ["7d", "month"]"#).unwrap();
+
+    assert_eq!(config.windows, [Window::Days(7), Window::CalendarMonth]);
+    assert_eq!(Config::default().windows, [Window::default()]);
+}
+
+#[test]
+fn t_window_bounds() {
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+    let today = date(20);
+
+    assert_eq!(Window::Days(7).bounds(today), (date(13), today));
+    assert_eq!(Window::CalendarMonth.bounds(today), (date(1), today));
+    assert_eq!(
+        Window::Range(date(2), date(9)).bounds(today),
+        (date(2), date(9))
+    );
+    assert_eq!(
+        Window::Days(u32::MAX).bounds(today),
+        (NaiveDate::MIN, today)
+    );
+    assert_eq!(Window::default().label(), "Past Two Weeks");
+    assert_eq!(Window::Days(7).label(), "Past 7 Days");
+    assert_eq!(Window::CalendarMonth.label(), "This Month");
+}
+
+#[test]
+#[allow(deprecated)]
+fn t_window_seconds() {
+    let store = totals_fixture();
+    let today = NaiveDate::from_ymd_opt(2024, 9, 9).unwrap();
+
+    assert_eq!(
+        window_seconds(&store, Window::default(), today).unwrap(),
+        past_two_seconds(&store, today).unwrap()
+    );
+    assert_eq!(
+        window_seconds(&store, Window::Days(7), today).unwrap(),
+        1200
+    );
+    assert_eq!(
+        window_seconds(&store, Window::CalendarMonth, today).unwrap(),
+        1400
+    );
+
+    let error = window_seconds(&store, Window::Days(1), today + CDuration::days(5)).unwrap_err();
+    assert!(matches!(error, TrackerError::PastTwo(_)));
+
+    // The ends of the calendar are not widened past the earliest and latest dates
+    assert_eq!(
+        window_seconds(&store, Window::Range(NaiveDate::MIN, NaiveDate::MAX), today).unwrap(),
+        1500
+    );
+    assert_eq!(
+        report(&store, NaiveDate::MIN, NaiveDate::MAX)
+            .unwrap()
+            .seconds,
+        1500
+    );
+
+    let dates = ["2024-09-02 300s", "2024-09-05 60s", "2024-09-09 500s"];
+
+    assert_eq!(closest_date_in(&dates, Window::Days(5), today), Some(1));
+    assert_eq!(closest_date_in(&dates, Window::Days(1), today), Some(2));
+    assert_eq!(
+        closest_date_in(&dates, Window::Days(1), today + CDuration::days(5)),
+        None
+    );
+    assert_eq!(
+        closest_date_in(&dates, Window::Days(1), NaiveDate::MAX),
+        None
+    );
+}
+
+#[test]
+fn t_hours_summary_windows() {
+    let store = totals_fixture();
+    let today = NaiveDate::from_ymd_opt(2024, 9, 9).unwrap();
+    let windows = [
+        Window::default(),
+        Window::Days(7),
+        Window::CalendarMonth,
+        Window::Days(7),
+    ];
+
+    let summary = HoursSummary::from_store(&store, 0, today, &windows).unwrap();
+
+    // The past two weeks and duplicate windows are only shown once
+    assert_eq!(
+        summary.windows,
+        [(Window::Days(7), 1200), (Window::CalendarMonth, 1400)]
+    );
+    assert_eq!(
+        summary.to_string(),
+        "Rocket League Hours\nTotal Seconds: 1500s\nTotal Hours: 0.4hrs\nHours Past Two Weeks: 0.4hrs\nHours Past 7 Days: 0.3hrs\nHours This Month: 0.4hrs\nIdle Hours: 0.0hrs\n"
+    );
+    assert_eq!(hours_totals(&summary.to_string()).unwrap(), (1500, 0.4));
+
+    let cli = Cli::try_parse_from(["rl-hours-tracker", "report", "--window", "month"]).unwrap();
+
+    assert!(matches!(
+        cli.command(),
+        Command::Report {
+            window: Some(Window::CalendarMonth),
+            ..
+        }
+    ));
+    assert!(Cli::try_parse_from([
+        "rl-hours-tracker",
+        "report",
+        "--window",
+        "7d",
+        "--since",
+        "2024-09-01"
+    ])
+    .is_err());
+}
+
+/// Creates a session between the RFC 3339 timestamps `start` and `end` without any idle time.
+fn session_between(start: &str, end: &str) -> Session {
+    let start = DateTime::parse_from_rfc3339(start).unwrap();
+    let end = DateTime::parse_from_rfc3339(end).unwrap();
+
+    Session {
+        version: SESSION_VERSION,
+        start,
+        end,
+        duration: (end - start).num_seconds() as u64,
+        end_reason: EndReason::ProcessExit,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        idle: Vec::new(),
+    }
+}
+
+#[test]
+fn t_session_days_across_midnight() {
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+    let mut session = session_between("2024-09-14T22:00:00+01:00", "2024-09-15T02:00:00+01:00");
+
+    assert_eq!(session.days(), [(date(14), 7200), (date(15), 7200)]);
+    assert_eq!(session.seconds_between(date(15), date(20)), 7200);
+    assert!(!session.played_between(date(16), date(20)));
+
+    // Idle time is not credited to the day it happened on
+    let idle_start = DateTime::parse_from_rfc3339("2024-09-14T23:00:00+01:00").unwrap();
+    session.idle.push(IdleSpan {
+        start: idle_start,
+        end: idle_start + CDuration::hours(1),
+    });
+    session.duration = 10800;
+
+    assert_eq!(session.days(), [(date(14), 3600), (date(15), 7200)]);
+
+    // A session over several days is split at every midnight, and rounding is given to the last day
+    let mut session = session_between("2024-09-14T23:00:00+01:00", "2024-09-16T01:00:00+01:00");
+    session.duration = 1000;
+
+    let days = session.days();
+
+    assert_eq!(
+        days.iter().map(|(date, _)| *date).collect::<Vec<_>>(),
+        [date(14), date(15), date(16)]
+    );
+    assert_eq!(days.iter().map(|(_, seconds)| seconds).sum::<u64>(), 1000);
+
+    // Legacy sessions only know the date they ended on
+    let legacy = legacy_session(date(15), 90000);
+
+    assert_eq!(legacy.days(), [(date(15), 90000)]);
+}
+
+#[test]
+fn t_past_two_splits_sessions_across_midnight() {
+    let today = NaiveDate::from_ymd_opt(2024, 9, 29).unwrap();
+
+    // The session ended on the first day of the window, after starting the day before it
+    let sessions = vec![session_between(
+        "2024-09-14T22:00:00+01:00",
+        "2024-09-15T02:00:00+01:00",
+    )];
+
+    assert_eq!(past_two_seconds(&sessions, today).unwrap(), 7200);
+
+    // The session started on the last day of the window and ended after it
+    let window = Window::Range(
+        NaiveDate::from_ymd_opt(2024, 9, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2024, 9, 14).unwrap(),
+    );
+
+    assert_eq!(window_seconds(&sessions, window, today).unwrap(), 7200);
+
+    let totals = sessions
+        .totals(
+            Grouping::Day,
+            NaiveDate::from_ymd_opt(2024, 9, 1).unwrap(),
+            today,
+        )
+        .unwrap();
+
+    assert_eq!(
+        totals
+            .iter()
+            .map(|t| (t.seconds, t.sessions))
+            .collect::<Vec<_>>(),
+        [(7200, 1), (7200, 1)]
+    );
+    assert_eq!(group_totals(&sessions, Grouping::Month)[0].sessions, 1);
+}
+
+#[test]
+fn t_session_reads_version_one_records() {
+    let line = "{\"version\":1,\"start\":\"2024-09-15T19:00:00+01:00\",\"end\":\"2024-09-15T20:00:00+01:00\",\"duration\":3600,\"end_reason\":\"process_exit\",\"app_version\":\"0.6.0\"}";
+    let session: Session = serde_json::from_str(line).unwrap();
+
+    assert_eq!(session.version, 1);
+    assert_eq!(session.start.to_rfc3339(), "2024-09-15T19:00:00+01:00");
+    assert_eq!(session.end.to_rfc3339(), "2024-09-15T20:00:00+01:00");
+}
+
+#[test]
+fn t_session_round_trip_keeps_offset() {
+    let mut session = session_between("2024-09-14T23:00:00+09:00", "2024-09-15T01:00:00+09:00");
+    session.duration = 6000;
+    session.idle = vec![IdleSpan {
+        start: DateTime::parse_from_rfc3339("2024-09-15T00:00:00+09:00").unwrap(),
+        end: DateTime::parse_from_rfc3339("2024-09-15T00:20:00+09:00").unwrap(),
+    }];
+
+    let json = serde_json::to_string(&session).unwrap();
+    assert!(json.contains("\"start\":\"2024-09-14T14:00:00Z\""));
+    assert!(json.contains("\"offset\":\"+09:00\""));
+
+    let read: Session = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, session);
+    assert_eq!(read.idle[0].start.to_rfc3339(), "2024-09-15T00:00:00+09:00");
+}
+
+#[test]
+fn t_timezone_parse() {
+    assert_eq!("recorded".parse::<Timezone>().unwrap(), Timezone::Recorded);
+    assert_eq!("local".parse::<Timezone>().unwrap(), Timezone::Local);
+    assert_eq!(
+        "Europe/London".parse::<Timezone>().unwrap(),
+        Timezone::Named(chrono_tz::Europe::London)
+    );
+    assert_eq!(
+        Timezone::Named(chrono_tz::America::New_York).to_string(),
+        "America/New_York"
+    );
+    assert!("Mars/Olympus_Mons".parse::<Timezone>().is_err());
+}
+
+#[test]
+fn t_session_days_across_daylight_saving() {
+    let london = Timezone::Named(chrono_tz::Europe::London);
+    let date = |m: u32, d: u32| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+    // The clocks went forward at 01:00 on 2024-03-31, so the day had 23 hours
+    let day = session_between("2024-03-31T00:00:00+00:00", "2024-04-01T00:00:00+01:00");
+    assert_eq!(day.duration, 23 * 3600);
+    assert_eq!(day.days_in(&london), [(date(3, 31), 23 * 3600)]);
+
+    // The clocks went back at 02:00 on 2024-10-27, so the day had 25 hours
+    let day = session_between("2024-10-27T00:00:00+01:00", "2024-10-28T00:00:00+00:00");
+    assert_eq!(day.duration, 25 * 3600);
+    assert_eq!(day.days_in(&london), [(date(10, 27), 25 * 3600)]);
+
+    // Recorded in summer time and ended in winter time, split at midnight in London
+    let session = session_between("2024-10-26T23:00:00+01:00", "2024-10-27T01:00:00+00:00");
+    assert_eq!(
+        session.days_in(&london),
+        [(date(10, 26), 3600), (date(10, 27), 7200)]
+    );
+
+    // An hour played across the change is an hour, even though the clock shows two
+    let session = session_between("2024-03-31T00:30:00+00:00", "2024-03-31T02:30:00+01:00");
+    assert_eq!(session.days_in(&london), [(date(3, 31), 3600)]);
+}
+
+#[test]
+fn t_window_seconds_in_another_timezone() {
+    let today = NaiveDate::from_ymd_opt(2024, 9, 21).unwrap();
+    let window = Window::Days(6);
+    let new_york = Timezone::Named(chrono_tz::America::New_York);
+    let sessions = vec![
+        // 2024-09-15 in Tokyo, but 2024-09-14 in New York
+        session_between("2024-09-15T08:00:00+09:00", "2024-09-15T09:00:00+09:00"),
+        // Across midnight in Tokyo, but within 2024-09-21 in New York
+        session_between("2024-09-21T23:00:00+09:00", "2024-09-22T01:00:00+09:00"),
+    ];
+
+    assert_eq!(
+        window_seconds_in(&sessions, window, today, &Timezone::Recorded).unwrap(),
+        3600 + 3600
+    );
+    assert_eq!(
+        window_seconds_in(&sessions, window, today, &new_york).unwrap(),
+        7200
+    );
+
+    let error = window_seconds_in(&sessions[..1].to_vec(), window, today, &new_york).unwrap_err();
+    assert!(matches!(error, TrackerError::PastTwo(_)));
+}
+
+#[test]
+fn t_parse_dates_diagnostics() {
+    let contents =
+        "2024-09-15 58s\n\n2024-09-16\n2024-13-01 20s\n2024-09-17 20s extra\n2024-09-18 1200s\n";
+    let date = |d: u32| NaiveDate::from_ymd_opt(2024, 9, d).unwrap();
+
+    let error = parse_dates(contents, ParseMode::Strict).unwrap_err();
+    assert_eq!(error.file, "date.txt");
+    assert_eq!(error.line, 3);
+    assert_eq!(error.text, "2024-09-16");
+    assert_eq!(
+        error.to_string(),
+        "Parse Error: date.txt line 3: the seconds are missing: '2024-09-16'"
+    );
+
+    let parsed = parse_dates(contents, ParseMode::Lenient).unwrap();
+    assert_eq!(parsed.value, [(date(15), 58), (date(18), 1200)]);
+    assert_eq!(
+        parsed
+            .skipped
+            .iter()
+            .map(|error| error.line)
+            .collect::<Vec<usize>>(),
+        [3, 4, 5]
+    );
+}
+
+#[test]
+fn t_parse_hours_diagnostics() {
+    let contents = "Rocket League Hours\nTotal Seconds: 100040s\nTotal Hours: 27.8hrs\nHours Past Two Weeks: lots\n\n";
+
+    assert_eq!(
+        parse_hours(contents, ParseMode::Strict).unwrap_err(),
+        ParseError {
+            file: "hours.txt".to_string(),
+            line: 4,
+            text: "Hours Past Two Weeks: lots".to_string(),
+            message: "'lots' is not a number of hours, such as 27.8hrs".to_string(),
+        }
+    );
+
+    let parsed = parse_hours(contents, ParseMode::Lenient).unwrap();
+    assert_eq!(parsed.value.seconds, 100040);
+    assert_eq!(parsed.value.hours, 27.8);
+    assert_eq!(parsed.skipped.len(), 1);
+
+    // The totals are required in both modes
+    let error = parse_hours(
+        "Rocket League Hours\nTotal Hours: 27.8hrs\n",
+        ParseMode::Lenient,
+    )
+    .unwrap_err();
+    assert_eq!(error.line, 3);
+    assert_eq!(error.message, "the Total Seconds line is missing");
+
+    let error = parse_hours(
+        "Total Seconds: 1s\nTotal Hours: 0.0hrs\n",
+        ParseMode::Strict,
+    )
+    .unwrap_err();
+    assert_eq!(error.line, 1);
+}
+
+#[test]
+fn t_parse_hours_without_title() {
+    // The first line is reported, and its total is still read in lenient mode
+    let parsed = parse_hours(
+        "\nTotal Seconds: 100040s\nTotal Hours: 27.8hrs\n",
+        ParseMode::Lenient,
+    )
+    .unwrap();
+
+    assert_eq!(parsed.value.seconds, 100040);
+    assert_eq!(parsed.value.hours, 27.8);
+    assert_eq!(parsed.skipped.len(), 1);
+    assert_eq!(parsed.skipped[0].line, 2);
+    assert_eq!(parsed.skipped[0].text, "Total Seconds: 100040s");
+
+    // A first line which is neither the name of the game nor a total is reported once
+    let parsed = parse_hours(
+        "Rocket League\nTotal Seconds: 100040s\nTotal Hours: 27.8hrs\n",
+        ParseMode::Lenient,
+    )
+    .unwrap();
+
+    assert_eq!(parsed.value.seconds, 100040);
+    assert_eq!(parsed.skipped.len(), 1);
+    assert_eq!(parsed.skipped[0].line, 1);
+}
+
+#[test]
+#[allow(deprecated)]
+fn t_date_binary_search_blank_lines() {
+    let split_newline = vec!["", "2024-09-15 58s", "2024-09-16 2890s", ""];
+
+    assert_eq!(
+        date_binary_search(&split_newline, &"2024-09-16".to_string()),
+        Some(2)
+    );
+    assert_eq!(date_binary_search(&[], &"2024-09-16".to_string()), None);
+}
+
+#[test]
+fn t_migrate_lenient_skips_invalid_lines() {
+    let data_dir = legacy_data_dir("t-migrate-lenient-skips-invalid-lines");
+    let contents = format!("{LEGACY_DATE_FIXTURE}2024-09-30 ???\n");
+
+    fs::write(data_dir.date_file(), &contents).unwrap();
+
+    let error = migrate_with(&data_dir, ParseMode::Strict).unwrap_err();
+    assert!(matches!(
+        error,
+        TrackerError::Migration(MigrationError::InvalidLine { line: 15, .. })
+    ));
+    assert!(data_dir.date_file().exists());
+
+    let report = migrate_with(&data_dir, ParseMode::Lenient).unwrap();
+    assert_eq!(report.sessions, 14);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].text, "2024-09-30 ???");
+    assert_eq!(
+        fs::read_to_string(report.backup_dir.join("date.txt")).unwrap(),
+        contents
+    );
+}
+
+#[test]
+fn t_validate_data_dir() {
+    let data_dir = temp_data_dir("t-validate-data-dir");
+
+    assert_eq!(validate(&data_dir).unwrap(), []);
+
+    fs::write(data_dir.date_file(), "2024-09-15 58s\n2024-09-16 abc\n").unwrap();
+    fs::write(
+        data_dir.hours_file(),
+        "Rocket League Hours\nTotal Seconds: 58s\n",
+    )
+    .unwrap();
+    append_session(&data_dir.sessions_file(), &session_on("2024-09-15", 58)).unwrap();
+    fs::OpenOptions::new()
+        .append(true)
+        .open(data_dir.sessions_file())
+        .and_then(|mut file| std::io::Write::write_all(&mut file, b"{\"version\":2,\n"))
+        .unwrap();
+
+    let errors = validate(&data_dir).unwrap();
+    let locations: Vec<(&str, usize)> = errors
+        .iter()
+        .map(|error| (error.file.as_str(), error.line))
+        .collect();
+
+    assert_eq!(
+        locations,
+        [("date.txt", 2), ("hours.txt", 3), ("sessions.jsonl", 2)]
+    );
+}
+
+#[test]
+fn t_validate_games() {
+    let data_dir = temp_data_dir("t-validate-games");
+    let games = registry(
+        &DetectionRules::default(),
+        &[custom_game("fall-guys", "Fall Guys")],
+    );
+    let fall_guys = games[1].data_dir(&data_dir);
+
+    fs::create_dir_all(fall_guys.root()).unwrap();
+    fs::write(fall_guys.date_file(), "2024-09-15 58s\n2024-09-16 abc\n").unwrap();
+
+    let results = validate_games(&data_dir, &games).unwrap();
+    let counts: Vec<(&str, usize)> = results
+        .iter()
+        .map(|(game, errors)| (game.name.as_str(), errors.len()))
+        .collect();
+
+    // The folder of the custom game is checked as well as the root data folder
+    assert_eq!(counts, [("Rocket League", 0), ("Fall Guys", 1)]);
+    assert_eq!(results[1].1[0].line, 2);
+}
+
+#[test]
+fn t_tracker_error_conversions() {
+    let error: TrackerError = PastTwoError.into();
+    assert_eq!(
+        error.to_string(),
+        "no sessions were recorded in the past two weeks."
+    );
+
+    let error: TrackerError = std::io::Error::new(std::io::ErrorKind::NotFound, "gone").into();
+    assert!(matches!(error, TrackerError::Io(_)));
+    assert_eq!(error.to_string(), "IO Error: gone");
+
+    let error = parse_hours("", ParseMode::Strict).unwrap_err();
+    assert!(matches!(TrackerError::from(error), TrackerError::Parse(_)));
+
+    assert_eq!(
+        TrackerError::Invalid(3).to_string(),
+        "Validation Error: the data folder has 3 invalid lines"
+    );
+}
+
+#[test]
+fn t_live_subscriber_follows_the_session() {
+    let mut live = LiveSubscriber::new();
+    let status = live.status();
+    let game = Game::default();
+
+    live.on_event(&TrackerEvent::GameStarted {
+        game: game.clone(),
+        start: Local::now(),
+    });
+    live.on_event(&TrackerEvent::Tick {
+        game: game.clone(),
+        elapsed: 42,
+        idle: true,
+        paused: false,
+    });
+    assert_eq!(
+        *status.borrow(),
+        LiveStatus {
+            game_id: Some(game.id.clone()),
+            game: Some(game.name.clone()),
+            elapsed: 42,
+            idle: true,
+            paused: false,
+        }
+    );
+
+    live.on_event(&TrackerEvent::Paused { game: game.clone() });
+    assert!(status.borrow().paused);
+    assert_eq!(status.borrow().elapsed, 42);
+
+    live.on_event(&TrackerEvent::SessionEnded {
+        game,
+        session: session_on("2024-09-02", 42),
+    });
+    assert_eq!(*status.borrow(), LiveStatus::default());
+}
+
+#[tokio::test]
+async fn t_server_serves_the_api() {
+    let data_dir = temp_data_dir("t-server-serves-the-api");
+    let mut store = JsonlStore::new(data_dir.sessions_file());
+
+    for session in totals_fixture() {
+        store.add(&session).unwrap();
+    }
+
+    fs::create_dir_all(data_dir.website_pages_dir()).unwrap();
+    fs::write(
+        data_dir.website_pages_dir().join("index.html"),
+        "<h1>Hours</h1>",
+    )
+    .unwrap();
+
+    let mut live = LiveSubscriber::new();
+    let state = ServerState::new(
+        data_dir.clone(),
+        vec![Game::default()],
+        vec![Window::default()],
+        live.status(),
+    );
+    let tracker = TrackerHandle::new();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(serve(listener, state, tracker.clone()));
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    let get = |path: &str| client.get(format!("{url}{path}")).send();
+
+    let json = |text: String| serde_json::from_str::<serde_json::Value>(&text).unwrap();
+
+    let totals = json(get("/api/totals").await.unwrap().text().await.unwrap());
+    assert_eq!(totals[0]["id"], ROCKET_LEAGUE_ID);
+    assert_eq!(totals[0]["total_seconds"], 1500);
+
+    let sessions = json(
+        get("/api/sessions?from=2024-09-02&to=2024-09-08")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap(),
+    );
+    assert_eq!(sessions.as_array().unwrap().len(), 2);
+    assert_eq!(sessions[0]["game"], ROCKET_LEAGUE_ID);
+
+    // The newest session comes first
+    let latest = json(
+        get("/api/sessions?limit=1")
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap(),
+    );
+    assert_eq!(latest.as_array().unwrap().len(), 1);
+    assert_eq!(latest[0]["session"]["duration"], 500);
+
+    let unknown = get("/api/sessions?game=unknown").await.unwrap();
+    assert_eq!(unknown.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // The website is served from the website folder, and nothing outside of it
+    let index = get("/").await.unwrap();
+    assert_eq!(index.text().await.unwrap(), "<h1>Hours</h1>");
+    let outside = get("/pages/..%2F..%2Fsessions.jsonl").await.unwrap();
+    assert_eq!(outside.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let live_page = get("/live").await.unwrap().text().await.unwrap();
+    assert!(live_page.contains(r#"new EventSource("/api/live")"#));
+
+    // The live status is streamed every time it changes
+    let mut events = get("/api/live").await.unwrap();
+    let first = String::from_utf8(events.chunk().await.unwrap().unwrap().to_vec()).unwrap();
+    assert!(first.contains("event: status"), "{first}");
+    assert!(first.contains("\"elapsed\":0"), "{first}");
+
+    live.on_event(&TrackerEvent::Tick {
+        game: Game::default(),
+        elapsed: 42,
+        idle: false,
+        paused: false,
+    });
+    let next = String::from_utf8(events.chunk().await.unwrap().unwrap().to_vec()).unwrap();
+    assert!(next.contains("\"elapsed\":42"), "{next}");
+    drop(events);
+
+    let status = json(get("/api/status").await.unwrap().text().await.unwrap());
+    assert_eq!(status["elapsed"], 42);
+
+    // The server stops when the tracker shuts down
+    tracker.shutdown();
+    server.await.unwrap().unwrap();
+
+    fs::remove_dir_all(data_dir.root()).unwrap();
+}
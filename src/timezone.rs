@@ -0,0 +1,164 @@
+//! This module contains the [`Timezone`] which the dates of the sessions are reported in.
+//!
+//! Sessions are stored with their start and end in UTC, along with the offset of the timezone they were recorded in,
+//! so they always describe the same instants however the clock of the system changes. When the sessions are split
+//! into days for the past two weeks, the reports and the website, the dates are taken in the reporting timezone,
+//! which is set by `timezone` in `config.toml`:
+//! - `"recorded"` uses the offset each session was recorded in, so a session counts towards the day it was played on
+//!   wherever it was played. This is the default.
+//! - `"local"` uses the current timezone of the system, including its daylight saving changes.
+//! - A name from the IANA timezone database, such as `"Europe/London"`.
+//!
+//! ```toml
+//! timezone = "Europe/London"
+//! ```
+use chrono::{
+    DateTime, Days, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone,
+    Utc,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt::Display, str::FromStr, sync::OnceLock};
+
+static TIMEZONE: OnceLock<Timezone> = OnceLock::new();
+
+/// Used for returning errors when a [`Timezone`] could not be parsed
+#[derive(Debug)]
+pub struct TimezoneError {
+    message: String,
+}
+
+impl Display for TimezoneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Timezone Error: {}", self.message)
+    }
+}
+
+impl Error for TimezoneError {}
+
+/// The timezone which the dates of the sessions are reported in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Timezone {
+    /// The offset each session was recorded in.
+    #[default]
+    Recorded,
+    /// The current timezone of the system.
+    Local,
+    /// A timezone from the IANA timezone database.
+    Named(Tz),
+}
+
+/// Sets the reporting timezone of the program and returns it.
+///
+/// The timezone can only be set once, so if it was already set, the existing timezone is returned instead.
+pub fn init(timezone: Timezone) -> Timezone {
+    *TIMEZONE.get_or_init(|| timezone)
+}
+
+/// Returns the reporting timezone of the program. This is [`Timezone::Recorded`] if [`init`] was not called.
+pub fn current() -> Timezone {
+    TIMEZONE.get().copied().unwrap_or_default()
+}
+
+impl Timezone {
+    /// Returns the current date. The date of the system is used for [`Timezone::Recorded`], as it is the offset
+    /// a session would be recorded in now.
+    pub fn today(&self) -> NaiveDate {
+        match self {
+            Timezone::Recorded | Timezone::Local => Local::now().date_naive(),
+            Timezone::Named(tz) => Utc::now().with_timezone(tz).date_naive(),
+        }
+    }
+
+    /// Returns the `instant` with the offset of the timezone. The instant is unchanged for [`Timezone::Recorded`].
+    pub fn convert(&self, instant: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match self {
+            Timezone::Recorded => instant,
+            Timezone::Local => instant.with_timezone(&Local).fixed_offset(),
+            Timezone::Named(tz) => instant.with_timezone(tz).fixed_offset(),
+        }
+    }
+
+    /// Returns the first instant of `date` in the timezone, where `recorded` is the offset used for
+    /// [`Timezone::Recorded`]. If midnight was skipped by a daylight saving change, the first hour after it is used.
+    pub fn start_of_day(&self, date: NaiveDate, recorded: FixedOffset) -> DateTime<FixedOffset> {
+        let midnight = date.and_time(NaiveTime::MIN);
+
+        match self {
+            Timezone::Recorded => first_instant(&recorded, midnight),
+            Timezone::Local => first_instant(&Local, midnight),
+            Timezone::Named(tz) => first_instant(tz, midnight),
+        }
+    }
+
+    /// Returns the first instant of the day after the day which contains `instant`.
+    pub fn next_midnight(&self, instant: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        let date = self.convert(instant).date_naive();
+
+        match date.checked_add_days(Days::new(1)) {
+            Some(next) => self.start_of_day(next, *instant.offset()),
+            None => DateTime::<Utc>::MAX_UTC.fixed_offset(),
+        }
+    }
+}
+
+/// Returns the first instant of the local time `naive` in `tz`, moving forward an hour at a time while it does
+/// not exist.
+fn first_instant<T: TimeZone>(tz: &T, naive: NaiveDateTime) -> DateTime<FixedOffset> {
+    let mut naive = naive;
+
+    for _ in 0..24 {
+        if let Some(instant) = tz.from_local_datetime(&naive).earliest() {
+            return instant.fixed_offset();
+        }
+
+        naive += TimeDelta::hours(1);
+    }
+
+    Utc.from_utc_datetime(&naive).fixed_offset()
+}
+
+impl FromStr for Timezone {
+    type Err = TimezoneError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "recorded" => Ok(Timezone::Recorded),
+            "local" => Ok(Timezone::Local),
+            name => name
+                .parse()
+                .map(Timezone::Named)
+                .map_err(|_| TimezoneError {
+                    message: format!(
+                    "'{name}' is not a timezone, such as 'recorded', 'local' or 'Europe/London'"
+                ),
+                }),
+        }
+    }
+}
+
+impl TryFrom<String> for Timezone {
+    type Error = TimezoneError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Timezone> for String {
+    fn from(timezone: Timezone) -> Self {
+        timezone.to_string()
+    }
+}
+
+/// Formats the timezone in the same way as it is parsed, such as `Europe/London`.
+impl Display for Timezone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Timezone::Recorded => write!(f, "recorded"),
+            Timezone::Local => write!(f, "local"),
+            Timezone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
@@ -0,0 +1,588 @@
+//! This module contains the [`Tracker`], which records the sessions of the registered games without the tray icon or
+//! any of the prompts of the binary, so it can be driven from another UI or service.
+//!
+//! A [`Tracker`] is made with the [`TrackerBuilder`], which takes its defaults from `config.toml`. Every
+//! [`Subscriber`] is given a [`TrackerEvent`] when a game starts, every second while it runs, when the session ends,
+//! and when the hours of the game were recalculated. The [`TrackerHandle`] stops, pauses and resumes the tracker from
+//! another thread.
+//!
+//! ```no_run
+//! use rl_hours_tracker::tracker::{Tracker, TrackerEvent};
+//!
+//! let tracker = Tracker::builder()
+//!     .subscribe(|event: &TrackerEvent| {
+//!         if let TrackerEvent::SessionEnded { game, session } = event {
+//!             println!("{} was played for {}s", game.name, session.duration);
+//!         }
+//!     })
+//!     .build();
+//!
+//! let handle = tracker.handle();
+//! let thread = tracker.start();
+//!
+//! // Later, from the UI
+//! handle.shutdown();
+//! thread.join().unwrap();
+//! ```
+use crate::{
+    checkpoint::Checkpoint,
+    config::Config,
+    data_dir::DataDir,
+    error::TrackerResult,
+    games::Game,
+    hours::{recalculate_game, HoursSummary},
+    idle::{default_detector, IdleDetector},
+    process_watcher::{default_watcher, ProcessWatcher},
+    session::{EndReason, IdleSpan, Session},
+    state::{SharedState, TrackerState, Trigger},
+    store::open_store,
+};
+use chrono::{DateTime, Local};
+use log::{error, info, trace, warn};
+use std::{
+    fs,
+    path::Path,
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+use stopwatch::Stopwatch;
+use tokio::sync::watch;
+
+/// The events which are given to every [`Subscriber`] of a [`Tracker`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TrackerEvent {
+    /// The process of the `game` was found, and a session started at `start`.
+    GameStarted { game: Game, start: DateTime<Local> },
+    /// A second of the session passed. `elapsed` is the time counted so far in seconds, which does not grow while
+    /// the player is `idle` or the tracker is `paused`.
+    Tick {
+        game: Game,
+        elapsed: u64,
+        idle: bool,
+        paused: bool,
+    },
+    /// The tracker was paused with [`TrackerHandle::pause`].
+    Paused { game: Game },
+    /// The tracker was resumed with [`TrackerHandle::resume`].
+    Resumed { game: Game },
+    /// The `session` ended and was added to the session history of the `game`.
+    SessionEnded { game: Game, session: Session },
+    /// The hours of the `game` were recalculated after its session ended.
+    PastTwoUpdated { game: Game, summary: HoursSummary },
+}
+
+/// Receives the [`TrackerEvent`]s of a [`Tracker`]. Closures which take a `&TrackerEvent` are subscribers.
+pub trait Subscriber: Send {
+    /// Called with every event, on the thread which runs the tracker.
+    fn on_event(&mut self, event: &TrackerEvent);
+}
+
+impl<F: FnMut(&TrackerEvent) + Send> Subscriber for F {
+    fn on_event(&mut self, event: &TrackerEvent) {
+        self(event)
+    }
+}
+
+/// Controls a [`Tracker`] from any thread, through its [`TrackerState`]. Every clone controls the same tracker.
+#[derive(Debug, Clone, Default)]
+pub struct TrackerHandle {
+    state: SharedState,
+}
+
+impl TrackerHandle {
+    /// Creates a new handle, for a tracker which is waiting for a game.
+    pub fn new() -> TrackerHandle {
+        TrackerHandle::default()
+    }
+
+    /// Returns the state of the tracker.
+    pub fn state(&self) -> TrackerState {
+        self.state.get()
+    }
+
+    /// Returns a receiver which is woken up every time the state of the tracker changes.
+    pub fn subscribe(&self) -> watch::Receiver<TrackerState> {
+        self.state.subscribe()
+    }
+
+    /// Changes the state of the tracker by the `trigger`, and returns the new state.
+    pub(crate) fn fire(&self, trigger: Trigger) -> TrackerState {
+        self.state.fire(trigger)
+    }
+
+    /// Ends the session in progress. The game is not tracked again until it was closed, and the tracker then waits for
+    /// the next game.
+    pub fn stop(&self) -> TrackerState {
+        self.fire(Trigger::Stop)
+    }
+
+    /// Pauses the clock of the session in progress. The paused time is recorded as idle time.
+    pub fn pause(&self) -> TrackerState {
+        self.fire(Trigger::Pause)
+    }
+
+    /// Resumes the clock after [`TrackerHandle::pause`].
+    pub fn resume(&self) -> TrackerState {
+        self.fire(Trigger::Resume)
+    }
+
+    /// Ends the session in progress, and returns from [`Tracker::run`] once it was recorded.
+    pub fn shutdown(&self) -> TrackerState {
+        self.fire(Trigger::Shutdown)
+    }
+
+    /// Returns whether a session is in progress.
+    pub fn is_tracking(&self) -> bool {
+        self.state().is_tracking()
+    }
+
+    /// Returns whether the clock is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state() == TrackerState::Paused
+    }
+
+    /// Returns whether the tracker was asked to shut down.
+    pub fn is_shutdown(&self) -> bool {
+        self.state().is_shutting_down()
+    }
+}
+
+/// Builds a [`Tracker`]. Every setting which is not set is taken from `config.toml`.
+#[derive(Default)]
+pub struct TrackerBuilder {
+    config: Option<Config>,
+    data_dir: Option<DataDir>,
+    games: Option<Vec<Game>>,
+    watcher: Option<Box<dyn ProcessWatcher>>,
+    idle: Option<Box<dyn IdleDetector>>,
+    checkpoint_interval: Option<u64>,
+    idle_timeout: Option<Duration>,
+    poll_interval: Option<Duration>,
+    subscribers: Vec<Box<dyn Subscriber>>,
+    handle: Option<TrackerHandle>,
+}
+
+impl TrackerBuilder {
+    /// Sets the configuration the defaults are taken from, instead of loading `config.toml`.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Sets the data folder the sessions are recorded in. The default is [`DataDir::current`].
+    pub fn data_dir(mut self, data_dir: DataDir) -> Self {
+        self.data_dir = Some(data_dir);
+        self
+    }
+
+    /// Sets the games which are tracked. The default is every registered game.
+    pub fn games(mut self, games: Vec<Game>) -> Self {
+        self.games = Some(games);
+        self
+    }
+
+    /// Sets the watcher which finds the process of a game.
+    pub fn watcher(mut self, watcher: impl ProcessWatcher + 'static) -> Self {
+        self.watcher = Some(Box::new(watcher));
+        self
+    }
+
+    /// Sets the detector which reports how long the player has been idle.
+    pub fn idle_detector(mut self, idle: impl IdleDetector + 'static) -> Self {
+        self.idle = Some(Box::new(idle));
+        self
+    }
+
+    /// Sets how often the session in progress is checkpointed in seconds, where `0` never checkpoints it.
+    pub fn checkpoint_interval(mut self, seconds: u64) -> Self {
+        self.checkpoint_interval = Some(seconds);
+        self
+    }
+
+    /// Sets how long the player can be idle before the clock is paused, where [`Duration::ZERO`] disables the idle
+    /// detection.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long [`Tracker::run`] waits between checks while no game is running. The default is 2 seconds.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Adds a subscriber, which is given every [`TrackerEvent`].
+    pub fn subscribe(mut self, subscriber: impl Subscriber + 'static) -> Self {
+        self.subscribers.push(Box::new(subscriber));
+        self
+    }
+
+    /// Sets the handle which controls the tracker, so its state can be shared with other parts of a program.
+    pub fn handle(mut self, handle: TrackerHandle) -> Self {
+        self.handle = Some(handle);
+        self
+    }
+
+    /// Builds the [`Tracker`]. The configuration is only loaded if a setting was not set, and the default is used if
+    /// it could not be loaded.
+    pub fn build(self) -> Tracker {
+        let needs_config = self.games.is_none()
+            || self.idle.is_none()
+            || self.checkpoint_interval.is_none()
+            || self.idle_timeout.is_none();
+
+        let config = match self.config {
+            Some(config) => config,
+            None if needs_config => Config::load().unwrap_or_else(|e| {
+                warn!("could not load the configuration, using the default settings: {e}");
+                Config::default()
+            }),
+            None => Config::default(),
+        };
+
+        Tracker {
+            data_dir: self.data_dir.unwrap_or_else(|| DataDir::current().clone()),
+            games: self.games.unwrap_or_else(|| config.games()),
+            watcher: self.watcher.unwrap_or_else(default_watcher),
+            idle: self.idle.unwrap_or_else(|| default_detector(&config.idle)),
+            checkpoint_interval: self
+                .checkpoint_interval
+                .unwrap_or(config.checkpoint_interval),
+            idle_timeout: self.idle_timeout.unwrap_or_else(|| config.idle.timeout()),
+            poll_interval: self.poll_interval.unwrap_or(Duration::from_secs(2)),
+            subscribers: self.subscribers,
+            handle: self.handle.unwrap_or_default(),
+            stopped: None,
+        }
+    }
+}
+
+/// Records the sessions of the registered games, and gives the [`TrackerEvent`]s to its subscribers.
+pub struct Tracker {
+    data_dir: DataDir,
+    games: Vec<Game>,
+    watcher: Box<dyn ProcessWatcher>,
+    idle: Box<dyn IdleDetector>,
+    checkpoint_interval: u64,
+    idle_timeout: Duration,
+    poll_interval: Duration,
+    subscribers: Vec<Box<dyn Subscriber>>,
+    handle: TrackerHandle,
+    stopped: Option<Game>,
+}
+
+impl Tracker {
+    /// Returns a builder for a tracker.
+    pub fn builder() -> TrackerBuilder {
+        TrackerBuilder::default()
+    }
+
+    /// Returns a handle which controls the tracker from another thread.
+    pub fn handle(&self) -> TrackerHandle {
+        self.handle.clone()
+    }
+
+    /// Returns the data folder the sessions are recorded in.
+    pub fn data_dir(&self) -> &DataDir {
+        &self.data_dir
+    }
+
+    /// Returns the games which are tracked.
+    pub fn games(&self) -> &[Game] {
+        &self.games
+    }
+
+    /// Runs [`Tracker::run`] on a new thread, and returns the tracker once it was shut down.
+    pub fn start(mut self) -> JoinHandle<Tracker> {
+        thread::spawn(move || {
+            self.run();
+            self
+        })
+    }
+
+    /// Ends the session in progress. See [`TrackerHandle::stop`].
+    pub fn stop(&self) {
+        self.handle.stop();
+    }
+
+    /// Pauses the clock of the session in progress. See [`TrackerHandle::pause`].
+    pub fn pause(&self) {
+        self.handle.pause();
+    }
+
+    /// Resumes the clock. See [`TrackerHandle::resume`].
+    pub fn resume(&self) {
+        self.handle.resume();
+    }
+
+    /// Waits for a game to start and records its sessions, until the tracker is shut down. A session which could not
+    /// be recorded is logged, and is recovered from its checkpoint on the next launch.
+    pub fn run(&mut self) {
+        while !self.handle.is_shutdown() {
+            match self.detect() {
+                Some(game) => {
+                    if let Err(e) = self.track(&game) {
+                        error!("could not record the session of {}: {e}", game.name);
+                    }
+                }
+                None => thread::sleep(self.poll_interval),
+            }
+        }
+
+        info!("Tracker shut down");
+    }
+
+    /// Returns the first game whose process is running, if there is one. After a session was stopped while its game
+    /// kept running, no game is returned until that game is closed.
+    pub fn detect(&mut self) -> Option<Game> {
+        if self.handle.state() == TrackerState::WaitingForExit {
+            let running = self
+                .stopped
+                .as_ref()
+                .is_some_and(|game| self.watcher.detect(&game.rules));
+
+            if running {
+                return None;
+            }
+
+            self.fire(Trigger::GameClosed);
+        }
+
+        self.games
+            .iter()
+            .find(|game| self.watcher.detect(&game.rules))
+            .cloned()
+    }
+
+    /// This function records a session of the running `game`, which lasts until its process is closed or the tracker
+    /// is stopped. The session is checkpointed to `checkpoint.json` while it is in progress, and the time the player
+    /// was idle or the tracker was paused is left out of it. The [`Session`] is appended to the `sessions.jsonl` of
+    /// the game, and its `hours.txt` is rebuilt. The [`TrackerState`] goes from `Tracking` to `Stopping`, and then back
+    /// to `Waiting`, or to `Stopped` if the tracker was shut down during the session. A session which was stopped goes
+    /// through `Ending` to `WaitingForExit` instead, until [`Tracker::detect`] finds that the game was closed.
+    ///
+    /// # Errors
+    /// Returns an error if the folder of the game could not be created or the session could not be added to its
+    /// history. The checkpoint is kept in that case, so the session is recovered on the next launch.
+    pub fn track(&mut self, game: &Game) -> TrackerResult<Session> {
+        let mut sw = Stopwatch::start_new();
+        let start = Local::now();
+        let mut checkpoint = Checkpoint::new(start);
+
+        // The folder of a game which was just added is needed for the checkpoints of its first session
+        let game_dir = game.data_dir(&self.data_dir);
+        fs::create_dir_all(game_dir.root())
+            .unwrap_or_else(|e| error!("could not create the folder of {}: {e}", game.name));
+
+        self.fire(Trigger::GameStarted);
+        self.emit(TrackerEvent::GameStarted {
+            game: game.clone(),
+            start,
+        });
+
+        // Start live stopwatch
+        let end_reason = self.live_stopwatch(game, &mut checkpoint);
+
+        // Stop the stopwatch
+        sw.stop();
+
+        let result = self.record(game, start, &sw, checkpoint, end_reason);
+
+        // The stopped game is not tracked again until it is closed
+        self.stopped = (end_reason == EndReason::StopTracker).then(|| game.clone());
+
+        // The tracker waits for the next game, or shuts down, even if the session could not be recorded
+        self.fire(Trigger::SessionRecorded);
+
+        result
+    }
+
+    /// This function records the session which started at `start`, was timed by the stopwatch `sw`, and ended for
+    /// the `end_reason`.
+    fn record(
+        &mut self,
+        game: &Game,
+        start: DateTime<Local>,
+        sw: &Stopwatch,
+        mut checkpoint: Checkpoint,
+        end_reason: EndReason,
+    ) -> TrackerResult<Session> {
+        let game_dir = game.data_dir(&self.data_dir);
+
+        info!("Record Hours: START\n");
+
+        fs::create_dir_all(game_dir.root())?;
+
+        // The time the player was idle is not counted
+        let idle_seconds: u64 = checkpoint.idle.iter().map(IdleSpan::seconds).sum();
+        let seconds: u64 = (sw.elapsed_ms() as u64 / 1000).saturating_sub(idle_seconds);
+
+        trace!("<< fn record >> seconds: {seconds}, idle seconds: {idle_seconds}");
+
+        // Checkpoint the full session, so it can be recovered if it is not recorded
+        save_checkpoint(&mut checkpoint, &game_dir.checkpoint_file(), seconds);
+
+        let mut session = Session::new(start, Local::now(), seconds, end_reason);
+        session.idle = checkpoint.idle.clone();
+
+        // Append the session to sessions.jsonl, the previous log is left intact if this fails
+        open_store(&game_dir)?.add(&session)?;
+
+        Checkpoint::remove(&game_dir.checkpoint_file())
+            .unwrap_or_else(|e| error!("error removing checkpoint.json: {e}"));
+
+        self.emit(TrackerEvent::SessionEnded {
+            game: game.clone(),
+            session: session.clone(),
+        });
+
+        // Rebuild hours.txt from the session history, it is rebuilt again on the next launch if this fails
+        match recalculate_game(&self.data_dir, game) {
+            Ok(report) => self.emit(TrackerEvent::PastTwoUpdated {
+                game: game.clone(),
+                summary: report.summary,
+            }),
+            Err(e) => error!("error writing to hours.txt: {e}"),
+        }
+
+        info!("Record Hours: FINISHED\n");
+
+        Ok(session)
+    }
+
+    /// This function counts the seconds until the watcher reports that the process of the `game` was closed, or the
+    /// tracker is stopped, and emits a [`TrackerEvent::Tick`] every second. The `checkpoint` is saved every
+    /// checkpoint interval. The [`EndReason`] of the session is returned.
+    ///
+    /// The clock is paused while the tracker is paused, or once the idle detector reports that the player has been
+    /// idle for the idle timeout, in which case the time before the timeout is taken back off the clock. Both are
+    /// added to the `checkpoint` as idle spans. A timeout of [`Duration::ZERO`] disables the idle detection.
+    pub(crate) fn live_stopwatch(&mut self, game: &Game, checkpoint: &mut Checkpoint) -> EndReason {
+        let checkpoint_file = game.data_dir(&self.data_dir).checkpoint_file();
+        let mut timer_early = SystemTime::now();
+        let mut elapsed: u64 = 0;
+        let mut idle_since: Option<DateTime<Local>> = None;
+        let mut was_paused = false;
+
+        while self.handle.state().is_active() && self.watcher.detect(&game.rules) {
+            let timer_now = timer_early
+                .checked_add(Duration::from_millis(999))
+                .unwrap_or_else(|| {
+                    error!("could not return system time");
+                    SystemTime::now()
+                });
+
+            let delay = timer_now.duration_since(timer_early).unwrap_or_else(|e| {
+                warn!(
+                    "system time is ahead of the timer. SystemTime difference: {:?}",
+                    e.duration()
+                );
+                Duration::from_millis(1000)
+            });
+
+            let paused = self.handle.is_paused();
+
+            if paused != was_paused {
+                was_paused = paused;
+                self.emit(if paused {
+                    TrackerEvent::Paused { game: game.clone() }
+                } else {
+                    TrackerEvent::Resumed { game: game.clone() }
+                });
+            }
+
+            let idle_time = if self.idle_timeout.is_zero() {
+                Duration::ZERO
+            } else {
+                self.idle.idle_time(game)
+            };
+            let is_idle = !self.idle_timeout.is_zero() && idle_time >= self.idle_timeout;
+
+            if paused || is_idle {
+                // Take the time before the timeout back off the clock
+                if idle_since.is_none() {
+                    let idle_seconds = if is_idle {
+                        idle_time.as_secs().min(elapsed)
+                    } else {
+                        0
+                    };
+
+                    elapsed -= idle_seconds;
+                    idle_since =
+                        Some(Local::now() - chrono::Duration::seconds(idle_seconds as i64));
+
+                    info!("the clock was paused and {idle_seconds}s were taken off");
+                }
+            } else {
+                if let Some(idle_start) = idle_since.take() {
+                    checkpoint
+                        .idle
+                        .push(IdleSpan::new(idle_start, Local::now()));
+                    info!("the clock was resumed");
+                }
+
+                elapsed += 1;
+
+                // Checkpoint the session in progress
+                if self.checkpoint_interval > 0 && elapsed.is_multiple_of(self.checkpoint_interval)
+                {
+                    save_checkpoint(checkpoint, &checkpoint_file, elapsed);
+                }
+            }
+
+            self.emit(TrackerEvent::Tick {
+                game: game.clone(),
+                elapsed,
+                idle: is_idle,
+                paused,
+            });
+
+            thread::sleep(delay);
+
+            timer_early += Duration::from_millis(999)
+        }
+
+        trace!("<< fn live_stopwatch >> elapsed: {elapsed}s");
+
+        // Close the idle span which was open when the session ended
+        if let Some(idle_start) = idle_since {
+            checkpoint
+                .idle
+                .push(IdleSpan::new(idle_start, Local::now()));
+        }
+
+        // The session is still active if the game was closed, rather than the session being stopped
+        if self.handle.state().is_active() {
+            self.fire(Trigger::GameClosed);
+            EndReason::ProcessExit
+        } else {
+            EndReason::StopTracker
+        }
+    }
+
+    /// Changes the state of the tracker by the `trigger`.
+    fn fire(&self, trigger: Trigger) {
+        let state = self.handle.fire(trigger);
+        info!("the tracker is {state:?} after {trigger:?}");
+    }
+
+    /// Gives the `event` to every subscriber.
+    fn emit(&mut self, event: TrackerEvent) {
+        for subscriber in &mut self.subscribers {
+            subscriber.on_event(&event);
+        }
+    }
+}
+
+/// This function updates the `checkpoint` with the `seconds` played so far and writes it to the file at `path`.
+/// A failure is logged, since the session can still be recorded when it ends.
+fn save_checkpoint(checkpoint: &mut Checkpoint, path: &Path, seconds: u64) {
+    checkpoint.update(seconds);
+    checkpoint
+        .save(path)
+        .unwrap_or_else(|e| warn!("failed to write checkpoint.json: {e}"));
+
+    trace!("<< fn save_checkpoint >> checkpoint saved at {seconds}s");
+}
@@ -3,15 +3,11 @@
 //! section.
 use bytes::Bytes;
 use colour::{green, green_ln_bold, magenta, magenta_ln_bold, red, yellow_ln_bold};
-use core::str;
 use directories::BaseDirs;
 use log::{error, info, warn};
 use reqwest::{self, Client};
 use std::{
-    env,
-    error::Error,
-    fmt::Display,
-    fs,
+    env, fs,
     io::{self, Write},
     path::PathBuf,
     process, thread,
@@ -19,32 +15,10 @@ use std::{
 };
 use zip;
 
-/// Used for returing errors during update cleanups
-#[derive(Debug)]
-struct CleanupError {
-    message: String,
-}
-
-/// Used for returning errors during updates
-#[derive(Debug)]
-struct UpdateError {
-    message: String,
-}
-
-impl Display for UpdateError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Update Error: {}", self.message)
-    }
-}
-
-impl Display for CleanupError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Cleanup Error: {}", self.message)
-    }
-}
-
-impl Error for UpdateError {}
-impl Error for CleanupError {}
+use crate::{
+    config::Config,
+    error::{TrackerError, TrackerResult},
+};
 
 /// Asynchronous function which checks the the GitHub repository for the latest release
 /// of the program.
@@ -52,10 +26,13 @@ impl Error for CleanupError {}
 /// If there is a new release, the function then runs the [`update`] function to replace the
 /// old files for the program with the new files from the `update.zip` archive on github.
 ///
+/// The function returns `true` if an update was installed, in which case the program should close so the new
+/// version can start.
+///
 /// # Errors
-/// This function returns a [`reqwest::Error`] if there were any errors sending `GET` request to GitHub
+/// This function returns a [`TrackerError::Network`] if there were any errors sending `GET` request to GitHub
 /// or any error from the [`update`] function.
-pub async fn check_for_update() -> Result<(), Box<dyn Error>> {
+pub async fn check_for_update() -> TrackerResult<bool> {
     info!("Checking for updates...\n");
     // Check if there was a prior update to finish any additional cleanup
     let get_prior_update = process::Command::new("cmd")
@@ -64,7 +41,7 @@ pub async fn check_for_update() -> Result<(), Box<dyn Error>> {
 
     match get_prior_update {
         Ok(output) => {
-            let output_string = str::from_utf8(&output.stdout)?;
+            let output_string = String::from_utf8_lossy(&output.stdout);
 
             if output_string.contains("1") {
                 additional_cleanup()?
@@ -75,30 +52,35 @@ pub async fn check_for_update() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    let client = Client::new();
-
-    // Send a GET request to the GitHub for the latest release
-    let response = client
-        .get("https://github.com/OneilNvM/rl-hours-tracker/releases/latest")
-        .send()
-        .await?;
-
-    let url = response.url().to_string();
-
-    // Store a reverse split vector of the url separated by '/' character
-    let url_vec: Vec<&str> = url.rsplit("/").collect();
-
-    // Get the version number
-    let version = url_vec[0].replace("v", "");
+    let version = latest_version().await?;
 
     // Check if the latest version is equal to the current version
     if version == env!("CARGO_PKG_VERSION") {
         yellow_ln_bold!("Latest Version: {version}");
-        Ok(())
+        Ok(false)
     } else {
         let mut option = String::new();
 
         magenta_ln_bold!("NEW VERSION AVAILABLE!!\n");
+
+        // The prompt is skipped when it is answered by the configuration, or in daemon mode
+        match Config::load()
+            .unwrap_or_default()
+            .install_updates
+            .decide(false)
+        {
+            Some(true) => {
+                yellow_ln_bold!("\nDownloading update...\n");
+                update(&version).await?;
+                return Ok(true);
+            }
+            Some(false) => {
+                info!("version {version} is available but was not installed");
+                return Ok(false);
+            }
+            None => (),
+        }
+
         magenta!("Update to version '{version}' ");
         print!("(");
         green!("y");
@@ -115,22 +97,47 @@ pub async fn check_for_update() -> Result<(), Box<dyn Error>> {
         if option.trim().to_lowercase() == "y" {
             yellow_ln_bold!("\nDownloading update...\n");
             update(&version).await?;
-            Ok(())
+            Ok(true)
         } else {
-            Ok(())
+            Ok(false)
         }
     }
 }
 
+/// Asynchronous function which returns the version number of the latest release on the GitHub repository,
+/// without the leading `v`.
+///
+/// # Errors
+/// This function returns a [`TrackerError::Network`] if there were any errors sending `GET` request to GitHub.
+pub async fn latest_version() -> TrackerResult<String> {
+    let client = Client::new();
+
+    // Send a GET request to the GitHub for the latest release
+    let response = client
+        .get("https://github.com/OneilNvM/rl-hours-tracker/releases/latest")
+        .send()
+        .await?;
+
+    let url = response.url().to_string();
+
+    // Store a reverse split vector of the url separated by '/' character
+    let url_vec: Vec<&str> = url.rsplit("/").collect();
+
+    // Get the version number
+    Ok(url_vec[0].replace("v", ""))
+}
+
 /// This function updates the Rocket League Hours Tracker binary.
 ///
 /// A HTTP `GET` request is sent to the GitHub repo's release section to download the bytes
 /// for `update.zip`.
-/// The zip is then extracted and the new files replace the old files.
+/// The zip is then extracted and the new files replace the old files. The program should close once the update
+/// is complete, so the new version can start.
 ///
 /// # Errors
-/// This function returns file operation errors or a [`reqwest::Error`].
-pub async fn update(ver_num: &str) -> Result<(), Box<dyn Error>> {
+/// This function returns file operation errors, a [`TrackerError::Network`], or a [`TrackerError::Update`] if the
+/// update can only be installed with the newest installer.
+pub async fn update(ver_num: &str) -> TrackerResult<()> {
     let client = Client::new();
 
     let url = format!(
@@ -140,11 +147,9 @@ pub async fn update(ver_num: &str) -> Result<(), Box<dyn Error>> {
     let response = client.get(url).send().await?;
 
     if !response.status().is_success() {
-        yellow_ln_bold!("The newest update includes changes to the built-in updater.");
-        thread::sleep(Duration::from_secs(3));
-        yellow_ln_bold!("You will need to download the newest installer from GitHub.");
-        thread::sleep(Duration::from_secs(5));
-        process::exit(0)
+        return Err(TrackerError::Update(String::from(
+            "the newest update includes changes to the built-in updater, download the newest installer from GitHub",
+        )));
     }
 
     let download = response.bytes().await?;
@@ -154,9 +159,7 @@ pub async fn update(ver_num: &str) -> Result<(), Box<dyn Error>> {
 
     if base_dir.is_none() {
         error!("base dir returned None");
-        return Err(Box::new(UpdateError {
-            message: String::from("base dir returned None"),
-        }));
+        return Err(TrackerError::Update(String::from("base dir returned None")));
     }
 
     let app_dir = base_dir
@@ -189,18 +192,18 @@ pub async fn update(ver_num: &str) -> Result<(), Box<dyn Error>> {
         warn!("issue setting up PRIOR_UPDATE: {e}");
     }
 
-    process::exit(0)
+    Ok(())
 }
 
-fn additional_cleanup() -> Result<(), Box<dyn Error>> {
+fn additional_cleanup() -> TrackerResult<()> {
     info!("Starting additional cleanup of previous version");
     let base_dir = BaseDirs::new();
 
     if base_dir.is_none() {
         error!("base dir returned None");
-        return Err(Box::new(CleanupError {
-            message: String::from("base dir returned None"),
-        }));
+        return Err(TrackerError::Update(String::from(
+            "base dir returned None during the cleanup",
+        )));
     }
 
     let app_dir = base_dir
@@ -224,7 +227,7 @@ fn additional_cleanup() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn extract_update(app_dir: PathBuf, download: Bytes) -> Result<(), Box<dyn Error>> {
+fn extract_update(app_dir: PathBuf, download: Bytes) -> TrackerResult<()> {
     yellow_ln_bold!("Created 'tmp' directory...");
 
     let file_name = app_dir.join("tmp").join("update.zip");
@@ -259,7 +262,7 @@ fn extract_update(app_dir: PathBuf, download: Bytes) -> Result<(), Box<dyn Error
     Ok(())
 }
 
-fn extract_local_zip(app_dir: &PathBuf, download: &Bytes) -> Result<(), Box<dyn Error>> {
+fn extract_local_zip(app_dir: &PathBuf, download: &Bytes) -> TrackerResult<()> {
     let file_name = app_dir.join("update.zip");
 
     fs::write(file_name, download)?;
@@ -1,17 +1,41 @@
 //! This module contains the functionality to generate the Html, CSS, and JavaScript for the
 //! Rocket League Hours Tracker website.
-use crate::IoResult;
-use build_html::{Container, ContainerType, Html, HtmlContainer, HtmlElement, HtmlPage, HtmlTag};
+//!
+//! When more games than Rocket League are registered, the website also shows the hours of every game and their
+//! combined total, and the latest sessions of all games.
+//!
+//! The CSS, JavaScript and images are bundled in the binary as the [`ASSETS`], so the website is generated without
+//! any network access. The fonts are only used when they are installed, with similar system fonts as a fallback.
+//! The files on GitHub can be used instead by setting the [`Theme`] in `config.toml`:
+//!
+//! ```toml
+//! [website]
+//! theme = "github"
+//! ```
+use crate::{
+    calculate_past_two::Window,
+    charts::Charts,
+    config::Config,
+    data_dir::DataDir,
+    error::TrackerResult,
+    games::Game,
+    history::write_history_pages,
+    hours::{summarise, HoursSummary},
+    session::Session,
+    store::open_store,
+    templates::Templates,
+    timezone, IoResult,
+};
 use bytes::Bytes;
 use colour::{green, green_ln_bold, red};
 use log::{error, warn};
 use reqwest::{Client, Response};
+use serde::{Deserialize, Serialize};
 use std::{
-    error::Error as ErrorTrait,
-    fs::{write, File},
+    cmp::Reverse,
+    fs::{self, write, File},
     io::{self, Error, ErrorKind, Read, Write},
-    process,
-    slice::Iter,
+    path::Path,
 };
 use tokio::runtime::Runtime;
 use webbrowser;
@@ -58,10 +82,12 @@ impl<'a> Github<'a> {
     /// ## Usage
     ///
     /// ```
-    ///let mut github_repo = Github::new("OneilNvM", "rl-hours-tracker", "master", "src", "main.rs");
+    /// # use rl_hours_tracker::website_files::Github;
+    /// let mut github_repo = Github::new("OneilNvM", "rl-hours-tracker", "master", "src", "main.rs");
     ///
     /// // Example Output: "https://raw.githubusercontent.com/OneilNvM/rl-hours-tracker/refs/heads/master/src/main.rs"
     /// github_repo.build_url();
+    /// # assert_eq!(github_repo.get_url(), "https://raw.githubusercontent.com/OneilNvM/rl-hours-tracker/refs/heads/master/src/main.rs");
     /// ```
     pub fn build_url(&mut self) {
         let url = format!(
@@ -78,10 +104,12 @@ impl<'a> Github<'a> {
     /// ## Usage
     ///
     /// ```
-    ///let mut github_repo = Github::new("OneilNvM", "rl-hours-tracker", "master", "images", "img.png");
+    /// # use rl_hours_tracker::website_files::Github;
+    /// let mut github_repo = Github::new("OneilNvM", "rl-hours-tracker", "master", "images", "img.png");
     ///
     /// // Example Output: "https://github.com/OneilNvM/rl-hours-tracker/blob/master/images/img.png"
     /// github_repo.build_image_url();
+    /// # assert_eq!(github_repo.get_url(), "https://github.com/OneilNvM/rl-hours-tracker/blob/master/images/img.png");
     /// ```
     pub fn build_image_url(&mut self) {
         let url = format!(
@@ -95,8 +123,10 @@ impl<'a> Github<'a> {
 /// This stores the file and image responses from the `GET` requests to GitHub
 #[derive(Debug, Clone)]
 pub struct GHResponse {
-    raw_url: Vec<String>,
-    image_url: Vec<Bytes>,
+    /// The text of the files, in the order of their Urls.
+    pub raw_url: Vec<String>,
+    /// The bytes of the images, in the order of their Urls.
+    pub image_url: Vec<Bytes>,
 }
 
 impl GHResponse {
@@ -110,38 +140,43 @@ impl GHResponse {
 ///
 /// ## Usage
 ///
-/// ```
-/// let response = send_request(url).await;
+/// ```no_run
+/// # use rl_hours_tracker::website_files::send_request;
+/// # async fn run(url: &String) -> rl_hours_tracker::error::TrackerResult<()> {
+/// let response = send_request(url).await?;
 ///
 /// let text = response.text().await;
+/// # Ok(())
+/// # }
 /// ```
-pub async fn send_request(url: &String) -> Response {
+///
+/// # Errors
+/// Returns a [`TrackerError::Network`](crate::error::TrackerError::Network) if the request could not be sent.
+pub async fn send_request(url: &String) -> TrackerResult<Response> {
     // Construct a new client instance
     let client = Client::new();
 
     // Send the GET request
-    let request = client.get(url).send().await;
-
-    // Handle the request
-    match request {
-        Ok(response) => response,
-        Err(e) => {
-            error!("error sending get request for url: {url}\n{e}");
-            process::exit(1);
-        }
-    }
+    client.get(url).send().await.map_err(|e| {
+        error!("error sending get request for url: {url}\n{e}");
+        e.into()
+    })
 }
 
 /// Handles the response received from [`send_request`].
 ///
 /// This function specifically handles the Urls from the [`Github`] instance, which was created
 /// through [`Github::build_url`].
-pub async fn handle_response(urls: Vec<String>) -> Vec<String> {
+///
+/// # Errors
+/// Returns a [`TrackerError::Network`](crate::error::TrackerError::Network)
+/// if a request failed or its text could not be read.
+pub async fn handle_response(urls: Vec<String>) -> TrackerResult<Vec<String>> {
     let mut text_vec: Vec<String> = Vec::new();
 
     // Loop through the Urls
     for url in urls {
-        let response = send_request(&url).await;
+        let response = send_request(&url).await?;
 
         let text = response.text().await;
 
@@ -150,24 +185,28 @@ pub async fn handle_response(urls: Vec<String>) -> Vec<String> {
             Ok(result) => text_vec.push(result),
             Err(e) => {
                 error!("error retrieving full response text: {e}");
-                process::exit(1);
+                return Err(e.into());
             }
         }
     }
 
-    text_vec
+    Ok(text_vec)
 }
 
 /// Handles the response received from [`send_request`].
 ///
 /// This function specifically handles the urls from the [`Github`] instance, which was created
 /// through [`Github::build_image_url`].
-pub async fn handle_image_response(urls: Vec<String>) -> Vec<Bytes> {
+///
+/// # Errors
+/// Returns a [`TrackerError::Network`](crate::error::TrackerError::Network)
+/// if a request failed or its bytes could not be read.
+pub async fn handle_image_response(urls: Vec<String>) -> TrackerResult<Vec<Bytes>> {
     let mut blob_vec: Vec<Bytes> = Vec::new();
 
     // Loop through the Urls
     for url in urls {
-        let response = send_request(&url).await;
+        let response = send_request(&url).await?;
 
         let blob = response.bytes().await;
 
@@ -176,170 +215,409 @@ pub async fn handle_image_response(urls: Vec<String>) -> Vec<Bytes> {
             Ok(result) => blob_vec.push(result),
             Err(e) => {
                 error!("error retrieving response bytes: {e}");
-                process::exit(1);
+                return Err(e.into());
             }
         }
     }
 
-    blob_vec
+    Ok(blob_vec)
 }
 
 /// Runs the asynchronous functions to completion and returns a [`GHResponse`] instance.
 ///
 /// This creates a new [`Runtime`] instance and runs the async functions to completion with [`Runtime::block_on`].
-pub fn run_async_functions(
-    urls1: Vec<String>,
-    urls2: Vec<String>,
-) -> Result<GHResponse, Box<dyn ErrorTrait>> {
+///
+/// # Errors
+/// Returns an error if the runtime could not be created or any of the requests failed.
+pub fn run_async_functions(urls1: Vec<String>, urls2: Vec<String>) -> TrackerResult<GHResponse> {
     let rt = Runtime::new()?;
 
     // Run the async functions
-    let result1 = rt.block_on(handle_response(urls1));
-    let result2 = rt.block_on(handle_image_response(urls2));
+    let result1 = rt.block_on(handle_response(urls1))?;
+    let result2 = rt.block_on(handle_image_response(urls2))?;
 
     Ok(GHResponse::new(result1, result2))
 }
 
+/// A file of the website which is bundled in the binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Asset {
+    /// The path of the file, relative to the website folder.
+    pub path: &'static str,
+    /// The contents of the file.
+    pub bytes: &'static [u8],
+}
+
+/// The CSS, JavaScript and images of the website, which are written by [`write_assets`] without any network access.
+pub const ASSETS: &[Asset] = &[
+    Asset {
+        path: "css/main.css",
+        bytes: include_bytes!("../website/css/main.css"),
+    },
+    Asset {
+        path: "css/home.css",
+        bytes: include_bytes!("../website/css/home.css"),
+    },
+    Asset {
+        path: "css/fonts.css",
+        bytes: include_bytes!("../website/css/fonts.css"),
+    },
+    Asset {
+        path: "css/charts.css",
+        bytes: include_bytes!("../website/css/charts.css"),
+    },
+    Asset {
+        path: "css/history.css",
+        bytes: include_bytes!("../website/css/history.css"),
+    },
+    Asset {
+        path: "js/animations.js",
+        bytes: include_bytes!("../website/js/animations.js"),
+    },
+    Asset {
+        path: "js/history.js",
+        bytes: include_bytes!("../website/js/history.js"),
+    },
+    Asset {
+        path: "images/rl-icon-grey.png",
+        bytes: include_bytes!("../website/images/rl-icon-grey.png"),
+    },
+    Asset {
+        path: "images/rl-icon-white.png",
+        bytes: include_bytes!("../website/images/rl-icon-white.png"),
+    },
+];
+
+/// Where the CSS, JavaScript and images of the website come from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// The [`ASSETS`] which are bundled in the binary.
+    #[default]
+    Bundled,
+    /// The files on the master branch of the GitHub repository, which are downloaded every time the website is
+    /// generated. The bundled file is used for any file which could not be downloaded.
+    Github,
+}
+
+/// The settings of the website.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebsiteConfig {
+    /// Where the CSS, JavaScript and images of the website come from.
+    pub theme: Theme,
+}
+
+/// This function writes the [`ASSETS`] into the website folder of the `data_dir`. With [`Theme::Github`], the files
+/// are downloaded from the GitHub repository instead, and the bundled file is written for any file which could not
+/// be downloaded, so the website is always complete.
+///
+/// # Errors
+/// Returns a [`TrackerError::Io`](crate::error::TrackerError::Io) if a folder or file could not be written.
+pub fn write_assets(data_dir: &DataDir, theme: Theme) -> TrackerResult<()> {
+    let downloaded = match theme {
+        Theme::Bundled => Vec::new(),
+        Theme::Github => download_assets(),
+    };
+
+    for (idx, asset) in ASSETS.iter().enumerate() {
+        let path = data_dir.website_dir().join(asset.path);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match downloaded.get(idx).and_then(Option::as_ref) {
+            Some(bytes) => write(&path, bytes)?,
+            None => write(&path, asset.bytes)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// This function copies the files in the theme folder of the `data_dir` over the website folder, so a theme file
+/// replaces the bundled asset with the same path. It returns the paths of the theme stylesheets which do not replace
+/// an asset, relative to the website pages, such as `../css/brand.css`, so they can be linked from every page.
+///
+/// # Errors
+/// Returns a [`TrackerError::Io`](crate::error::TrackerError::Io) if a file of the theme could not be copied.
+pub fn write_theme(data_dir: &DataDir) -> TrackerResult<Vec<String>> {
+    let mut stylesheets = Vec::new();
+
+    if data_dir.theme_dir().is_dir() {
+        copy_theme(
+            &data_dir.theme_dir(),
+            &data_dir.website_dir(),
+            "",
+            &mut stylesheets,
+        )?;
+    }
+
+    stylesheets.sort();
+
+    Ok(stylesheets)
+}
+
+/// Copies the files in the `from` folder into the `to` folder, where `prefix` is the path of the folder relative to
+/// the theme folder, and adds the paths of the new stylesheets to `stylesheets`.
+fn copy_theme(
+    from: &Path,
+    to: &Path,
+    prefix: &str,
+    stylesheets: &mut Vec<String>,
+) -> TrackerResult<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = format!("{prefix}{name}");
+
+        if entry.file_type()?.is_dir() {
+            copy_theme(
+                &entry.path(),
+                &to.join(&name),
+                &format!("{path}/"),
+                stylesheets,
+            )?;
+            continue;
+        }
+
+        fs::copy(entry.path(), to.join(&name))?;
+
+        if name.ends_with(".css") && !ASSETS.iter().any(|asset| asset.path == path) {
+            stylesheets.push(format!("../{path}"));
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads the [`ASSETS`] from the GitHub repository, in the same order. A file which could not be downloaded is
+/// [`None`].
+fn download_assets() -> Vec<Option<Bytes>> {
+    let rt = match Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            warn!("could not download the website theme, using the bundled files: {e}");
+            return Vec::new();
+        }
+    };
+
+    ASSETS
+        .iter()
+        .map(|asset| {
+            let (folder, file) = asset.path.rsplit_once('/').unwrap_or(("", asset.path));
+            let folder = format!("website/{folder}");
+
+            let mut github = Github::new("OneilNvM", "rl-hours-tracker", "master", &folder, file);
+            github.build_url();
+
+            match rt.block_on(download(&github.get_url())) {
+                Ok(bytes) => Some(bytes),
+                Err(e) => {
+                    warn!(
+                        "could not download {}, using the bundled file: {e}",
+                        asset.path
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Downloads the file at the `url`.
+async fn download(url: &String) -> TrackerResult<Bytes> {
+    let response = send_request(url).await?.error_for_status()?;
+
+    Ok(response.bytes().await?)
+}
+
 /// This function is used to generate the necessary files for the Rocket League Hours Tracker website.
 /// It accepts a bool [`bool`] as an argument which determines whether the option to open the website
-/// in the browser should appear or not.
+/// in the browser should appear or not. The option is answered by the `open_website` setting of the [`Config`]
+/// when it is set, or in daemon mode.
+///
+/// The website is generated without any network access, unless the `theme` of the [`WebsiteConfig`] is
+/// [`Theme::Github`].
 ///
 /// # Errors
-/// Returns an [`io::Error`] if there were any file operations which failed
-pub fn generate_website_files(boolean: bool) -> Result<(), Box<dyn ErrorTrait>> {
-    // Create Github instances for the website files
-    let mut github_main_css = Github::new(
-        "OneilNvM",
-        "rl-hours-tracker",
-        "master",
-        "website/css",
-        "main.css",
-    );
-    let mut github_home_css = Github::new(
-        "OneilNvM",
-        "rl-hours-tracker",
-        "master",
-        "website/css",
-        "home.css",
-    );
-    let mut github_animations_js = Github::new(
-        "OneilNvM",
-        "rl-hours-tracker",
-        "master",
-        "website/js",
-        "animations.js",
-    );
-    let mut github_grey_icon = Github::new(
-        "OneilNvM",
-        "rl-hours-tracker",
-        "master",
-        "website/images",
-        "rl-icon-grey.png",
-    );
-    let mut github_white_icon = Github::new(
-        "OneilNvM",
-        "rl-hours-tracker",
-        "master",
-        "website/images",
-        "rl-icon-white.png",
-    );
+/// Returns a [`TrackerError::Io`](crate::error::TrackerError::Io) if there were any file operations which failed.
+pub fn generate_website_files(boolean: bool) -> TrackerResult<()> {
+    let config = Config::load().unwrap_or_else(|e| {
+        warn!("could not load the configuration, only Rocket League is shown: {e}");
+        Config::default()
+    });
 
-    // Build the Urls for the Github instances
-    github_main_css.build_url();
-    github_home_css.build_url();
-    github_animations_js.build_url();
-    github_grey_icon.build_url();
-    github_white_icon.build_url();
-
-    let github_text_vec = vec![
-        github_main_css.url,
-        github_home_css.url,
-        github_animations_js.url,
-    ];
-
-    let github_blob_vec = vec![github_grey_icon.url, github_white_icon.url];
-
-    // Run asynchronous functions and return a GHResponse instance
-    let ghresponse = run_async_functions(github_text_vec, github_blob_vec)?;
-
-    let mut bytes_iter = ghresponse.image_url.iter();
-    let mut raw_iter = ghresponse.raw_url.iter();
-
-    // Write the image bytes
-    write(
-        "C:\\RLHoursFolder\\website\\images\\rl-icon-grey.png",
-        bytes_iter.next().unwrap(),
-    )
-    .unwrap_or_else(|e| warn!("failed to write rl-icon-grey.png: {e}"));
-    write(
-        "C:\\RLHoursFolder\\website\\images\\rl-icon-white.png",
-        bytes_iter.next().unwrap(),
-    )
-    .unwrap_or_else(|e| warn!("failed to write rl-icon-white.png: {e}"));
+    write_assets(DataDir::current(), config.website.theme)?;
 
     // Create the files for the website
-    create_website_files(&mut raw_iter, boolean)
+    create_website_files(&config, boolean)
 }
 
-fn create_website_files(
-    raw_iter: &mut Iter<'_, String>,
-    boolean: bool,
-) -> Result<(), Box<dyn ErrorTrait>> {
-    // Create and open files
-    let mut index = File::create("C:\\RLHoursFolder\\website\\pages\\index.html")?;
-    let main_styles = File::create("C:\\RLHoursFolder\\website\\css\\main.css");
-    let home_styles = File::create("C:\\RLHoursFolder\\website\\css\\home.css");
-    let animations_js = File::create("C:\\RLHoursFolder\\website\\js\\animations.js");
-    let mut hours_file = File::open("C:\\RLHoursFolder\\hours.txt");
-    let mut date_file = File::open("C:\\RLHoursFolder\\date.txt");
-
-    // Creates the main.css file
-    match main_styles {
-        Ok(mut ms_file) => {
-            // Writes the CSS content to the file
-            match ms_file.write_all(raw_iter.next().unwrap().as_bytes()) {
-                Ok(_) => (),
-                Err(e) => warn!("failed to write to main.css: {e}"),
+/// The hours of a game which are displayed in the website.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameHours {
+    /// The game.
+    pub game: Game,
+    /// The path of the icon of the game, relative to the website pages.
+    pub icon: Option<String>,
+    /// The totals of the game.
+    pub summary: HoursSummary,
+}
+
+/// This function calculates the [`GameHours`] of each of the `games` in the `data_dir`, and copies the icons of the
+/// games into the website images folder. Games which could not be summarised are left out.
+pub fn game_hours(data_dir: &DataDir, games: &[Game]) -> Vec<GameHours> {
+    games
+        .iter()
+        .filter_map(|game| {
+            let game_dir = game.data_dir(data_dir);
+
+            if !game_dir.root().is_dir() {
+                return None;
+            }
+
+            match summarise(&game_dir) {
+                Ok(summary) => Some(GameHours {
+                    game: game.clone(),
+                    icon: game_icon(data_dir, game),
+                    summary,
+                }),
+                Err(e) => {
+                    warn!("could not summarise the hours of {}: {e}", game.name);
+                    None
+                }
             }
+        })
+        .collect()
+}
+
+/// Returns the path of the icon of the `game` relative to the website pages. The icon of a custom game is copied
+/// into the website images folder of the `data_dir`.
+fn game_icon(data_dir: &DataDir, game: &Game) -> Option<String> {
+    if game.is_rocket_league() {
+        return Some(String::from("../images/rl-icon-white.png"));
+    }
+
+    let icon = game.icon.as_ref()?;
+    let file_name = match icon.extension() {
+        Some(ext) => format!("{}-icon.{}", game.id, ext.to_string_lossy()),
+        None => format!("{}-icon", game.id),
+    };
+
+    match fs::copy(icon, data_dir.website_images_dir().join(&file_name)) {
+        Ok(_) => Some(format!("../images/{file_name}")),
+        Err(e) => {
+            warn!("failed to copy the icon of {}: {e}", game.name);
+            None
         }
-        Err(e) => warn!("failed to create main.css: {e}"),
     }
+}
 
-    // Creates the home.css file
-    match home_styles {
-        Ok(mut hs_file) => {
-            // Writes the CSS content to the file
-            match hs_file.write_all(raw_iter.next().unwrap().as_bytes()) {
-                Ok(_) => (),
-                Err(e) => warn!("failed to write to home.css: {e}"),
-            }
+/// This function returns the latest `count` sessions across all of the `games` in the `data_dir`, newest first.
+/// Each session is returned with the name of its game.
+///
+/// # Errors
+/// Returns an error if the session store of a game could not be read.
+pub fn latest_sessions(
+    data_dir: &DataDir,
+    games: &[Game],
+    count: usize,
+) -> TrackerResult<Vec<(String, Session)>> {
+    let mut sessions = Vec::new();
+
+    for game in games {
+        let game_dir = game.data_dir(data_dir);
+
+        if !game_dir.root().is_dir() {
+            continue;
+        }
+
+        for session in open_store(&game_dir)?.latest(count)? {
+            sessions.push((game.name.clone(), session));
         }
-        Err(e) => warn!("failed to create home.css: {e}"),
     }
 
-    // Creates the animations.js file
-    match animations_js {
-        Ok(mut a_js_file) => {
-            // Writes the JavaScript content to the file
-            match a_js_file.write_all(raw_iter.next().unwrap().as_bytes()) {
-                Ok(_) => (),
-                Err(e) => warn!("failed to write to animations.js: {e}"),
-            }
+    sessions.sort_by_key(|(_, session)| Reverse(session.end));
+    sessions.truncate(count);
+
+    Ok(sessions)
+}
+
+/// This function returns every session of all of the `games` in the `data_dir`, newest first. Each session is
+/// returned with the name of its game.
+///
+/// # Errors
+/// Returns an error if the session store of a game could not be read.
+pub fn all_sessions(data_dir: &DataDir, games: &[Game]) -> TrackerResult<Vec<(String, Session)>> {
+    let mut sessions = Vec::new();
+
+    for game in games {
+        let game_dir = game.data_dir(data_dir);
+
+        if !game_dir.root().is_dir() {
+            continue;
+        }
+
+        for session in open_store(&game_dir)?.sessions()? {
+            sessions.push((game.name.clone(), session));
         }
-        Err(e) => warn!("failed to create animations.js: {e}"),
     }
 
-    // Generate the website
-    let contents: String = generate_page(&mut hours_file, &mut date_file)?;
+    sessions.sort_by_key(|(_, session)| Reverse(session.end));
+
+    Ok(sessions)
+}
+
+fn create_website_files(config: &Config, boolean: bool) -> TrackerResult<()> {
+    let data_dir = DataDir::current();
+    let index_path = data_dir.website_pages_dir().join("index.html");
+    let games = config.games();
+
+    // Create and open files
+    fs::create_dir_all(data_dir.website_pages_dir())?;
+    let mut index = File::create(&index_path)?;
+    let mut hours_file = File::open(data_dir.hours_file());
+    let sessions = latest_sessions(data_dir, &games, 7)?;
+    let hours = game_hours(data_dir, &games);
+    let history = all_sessions(data_dir, &games)?;
+    let charts = Charts::new(
+        &history
+            .iter()
+            .map(|(_, session)| session.clone())
+            .collect::<Vec<Session>>(),
+        config.windows.first().copied().unwrap_or_default(),
+        timezone::current().today(),
+    );
 
-    // Initialize the 'contents' variable with the Html
-    let page = contents.replace("<body>", "<body class=\"body adaptive\">");
+    // Copy the theme over the assets, and load the user templates
+    let mut templates = Templates::load(data_dir)?;
+    templates.set_stylesheets(&write_theme(data_dir)?);
+
+    // Generate the website
+    let page = generate_page(&templates, &mut hours_file, &sessions, &hours, &charts)?;
 
     // Writes the index.html file
     index.write_all(page.as_bytes())?;
 
-    // Prompt the user with the option to open the website
-    if boolean {
+    // Writes the session history pages, which are linked from the navigation
+    write_history_pages(&templates, data_dir, &history, hours.len() > 1)?;
+
+    // Prompt the user with the option to open the website, unless it is answered by the configuration
+    let open_website = if boolean {
+        config.open_website.decide(false)
+    } else {
+        Some(false)
+    };
+
+    if open_website == Some(true) && webbrowser::open(&index_path.to_string_lossy()).is_ok() {
+        green_ln_bold!("OK\n");
+    }
+
+    if open_website.is_none() {
         let mut option = String::new();
 
         print!("Open hours website in browser (");
@@ -350,10 +628,10 @@ fn create_website_files(
         io::stdout()
             .flush()
             .unwrap_or_else(|_| println!("Open hours website in browser (y/n)?"));
-        io::stdin().read_line(&mut option).unwrap();
+        io::stdin().read_line(&mut option)?;
 
         if option.trim().to_lowercase() == "y"
-            && webbrowser::open("C:\\RLHoursFolder\\website\\pages\\index.html").is_ok()
+            && webbrowser::open(&index_path.to_string_lossy()).is_ok()
         {
             green_ln_bold!("OK\n");
         }
@@ -362,158 +640,193 @@ fn create_website_files(
     Ok(())
 }
 
-/// This function generates the necessary Html for the website via the [`build_html`] library. The `hours_file` and `date_file`
-/// parameters are both mutable [`Result<File>`] references which provides us with a [`File`] if it is successful, or [`io::Error`] if
-/// it fails. This function then returns a [`Result<String>`] of the Html.
+/// The heading of the website, which is shown on two lines, such as "Rocket League" and "Hours Tracker".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    /// The name of the game, without its last word.
+    pub name: String,
+    /// The last word of the name of the game, which is followed by "Tracker".
+    pub last_word: String,
+}
+
+/// A session which is shown in the website.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PageSession {
+    /// The name of the game of the session.
+    pub game: String,
+    /// The session, such as "2024-09-02 19:55 - 20:00 300s".
+    pub text: String,
+}
+
+/// The hours of a game, or of all games, which are shown in the website. The hours are formatted to one decimal
+/// place, such as "27.8".
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PageHours {
+    /// The name of the game, or "All Games".
+    pub name: String,
+    /// The path of the icon of the game, relative to the website pages.
+    pub icon: Option<String>,
+    /// The hours played while the player was active.
+    pub active: String,
+    /// The hours the player was idle.
+    pub idle: String,
+    /// The hours played in the past two weeks.
+    pub past_two: String,
+    /// The hours played in each of the windows in `config.toml`.
+    pub windows: Vec<PageWindow>,
+}
+
+/// The hours played in a window, such as "2.0" hours "this month".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PageWindow {
+    /// The name of the window in lowercase, such as "this month".
+    pub label: String,
+    /// The hours played in the window.
+    pub hours: String,
+}
+
+/// The variables of the `index.html` template.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexContext<'a> {
+    /// The heading of the website.
+    pub heading: Heading,
+    /// The lines of `hours.txt` after the name of the game, such as "Total Hours: 27.8hrs".
+    pub hours: Vec<String>,
+    /// The latest sessions of all games, newest first.
+    pub sessions: Vec<PageSession>,
+    /// Whether more than one game is tracked, which shows the name of the game of each session and the hours of every
+    /// game.
+    pub multiple_games: bool,
+    /// The hours of every game.
+    pub games: Vec<PageHours>,
+    /// The combined hours of every game.
+    pub all_games: PageHours,
+    /// The SVG charts of the time played.
+    pub charts: &'a Charts,
+}
+
+/// This function generates the Html for the website by rendering the `index.html` template of the `templates`. The
+/// `hours_file` parameter is a mutable [`Result<File>`] reference which provides us with a [`File`] if it is
+/// successful, or [`io::Error`] if it fails. The `sessions` are the latest recorded [`Session`]s of all games with the
+/// name of their game, newest first, `hours` are the [`GameHours`] of every game, and `charts` are the SVG [`Charts`]
+/// of the time played. This function then returns a [`TrackerResult<String>`] of the Html.
 ///
 /// # Errors
-/// This function returns an [`io::Error`] if there were any errors during file operations.
-fn generate_page(
+/// This function returns a [`TrackerError::Io`](crate::error::TrackerError::Io) if there were any errors during file
+/// operations, or a [`TrackerError::Template`](crate::error::TrackerError::Template) if the template could not be
+/// rendered.
+pub fn generate_page(
+    templates: &Templates,
     hours_file: &mut IoResult<File>,
-    date_file: &mut IoResult<File>,
-) -> IoResult<String> {
-    let mut page = HtmlPage::new()
-    .with_title("Rocket League Hours Tracker")
-    .with_meta(vec![("charset", "UTF-8")])
-    .with_meta(vec![("name", "viewport"), ("content", "width=device-width, initial-scale=1.0")])
-    .with_head_link("../css/main.css", "stylesheet")
-    .with_head_link("../css/home.css", "stylesheet")
-    .with_head_link("https://fonts.googleapis.com", "preconnect")
-    .with_head_link_attr("https://fonts.gstatic.com", "preconnect", [("crossorigin", "")])
-    .with_head_link("https://fonts.googleapis.com/css2?family=Bebas+Neue&display=swap", "stylesheet")
-    .with_head_link("https://fonts.googleapis.com/css2?family=Bebas+Neue&family=Oswald:wght@200..700&display=swap", "stylesheet")
-    .with_script_link("../js/animations.js");
-
-    page.add_container(
-        Container::new(ContainerType::Div)
-            .with_attributes(vec![("class", "animation-div adaptive")])
-            .with_raw(""),
-    );
-
+    sessions: &[(String, Session)],
+    hours: &[GameHours],
+    charts: &Charts,
+) -> TrackerResult<String> {
     let mut hrs_content = String::new();
-    let mut date_content = String::new();
 
     if let Ok(ref mut hrs_file) = hours_file {
         if hrs_file.read_to_string(&mut hrs_content).is_err() {
             return Err(Error::new(
                 ErrorKind::InvalidData,
                 "The files contents are not valid UTF-8.",
-            ));
-        }
-    } else {
-        return Err(Error::new(ErrorKind::NotFound, "The file 'hours.txt' could not be opened. Either it does not exist or it is not in the 'RLHoursFolder' directory."));
-    }
-
-    if let Ok(ref mut dt_file) = date_file {
-        if dt_file.read_to_string(&mut date_content).is_err() {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                "The files contents are not valid UTF-8.",
-            ));
+            )
+            .into());
         }
     } else {
-        return Err(Error::new(ErrorKind::NotFound, "The file 'hours.txt' could not be opened. Either it does not exist or it is not in the 'RLHoursFolder' directory."));
+        return Err(Error::new(ErrorKind::NotFound, "The file 'hours.txt' could not be opened. Either it does not exist or it is not in the data directory.").into());
     }
 
     let mut hrs_lines: Vec<&str> = hrs_content.split("\n").collect();
-    let mut date_lines: Vec<&str> = date_content.split("\n").collect();
 
     hrs_lines.pop();
-    date_lines.pop();
-
-    let main_heading_vec: Vec<&str> = hrs_lines.remove(0).split_whitespace().collect();
-
-    let main_heading = format!(
-        "{} {}<br>{} Tracker",
-        main_heading_vec[0], main_heading_vec[1], main_heading_vec[2]
-    );
-
-    page.add_container(
-        Container::new(ContainerType::Header)
-            .with_attributes(vec![("class", "header")])
-            .with_container(Container::new(ContainerType::Div).with_header_attr(
-                1,
-                main_heading,
-                vec![("class", "main-title bebas-neue-regular")],
-            )),
-    );
 
-    let nav_container = HtmlElement::new(HtmlTag::Div)
-        .with_attribute("class", "nav-container flex-column")
-        .with_container(
-            Container::new(ContainerType::Div)
-                .with_attributes(vec![("class", "your-hours-div nav-div")])
-                .with_link("#hours", "Your Hours"),
-        )
-        .with_container(
-            Container::new(ContainerType::Div)
-                .with_attributes(vec![("class", "date-and-times-div nav-div")])
-                .with_link("#dates", "Date And Times"),
-        );
-
-    page.add_container(
-        Container::new(ContainerType::Nav)
-            .with_attributes(vec![("class", "nav oswald-font-500")])
-            .with_html(nav_container),
-    );
-
-    let mut hours_div =
-        HtmlElement::new(HtmlTag::Div).with_attribute("class", "hours-div flex-column adaptive");
-
-    let mut dates_div = HtmlElement::new(HtmlTag::Div).with_attribute(
-        "class",
-        "dates-div flex-column flex-align-justify-center adaptive",
-    );
+    // Split the heading before its last word, e.g. "Rocket League<br>Hours Tracker"
+    let heading = match hrs_lines.remove(0).trim().rsplit_once(' ') {
+        Some((name, last)) => Heading {
+            name: name.to_string(),
+            last_word: last.to_string(),
+        },
+        None => Heading {
+            name: String::from("Hours"),
+            last_word: String::new(),
+        },
+    };
+
+    let context = IndexContext {
+        heading,
+        hours: hrs_lines.iter().map(|line| line.to_string()).collect(),
+        sessions: sessions
+            .iter()
+            .map(|(game_name, session)| PageSession {
+                game: game_name.clone(),
+                text: session.to_string(),
+            })
+            .collect(),
+        multiple_games: hours.len() > 1,
+        games: hours
+            .iter()
+            .map(|game_hours| {
+                page_hours(
+                    &game_hours.game.name,
+                    game_hours.icon.clone(),
+                    &game_hours.summary,
+                )
+            })
+            .collect(),
+        all_games: all_games_hours(hours),
+        charts,
+    };
+
+    templates.render("index.html", context)
+}
 
-    for line in hrs_lines {
-        hours_div.add_paragraph(line);
+/// Formats the `summary` of a game as [`PageHours`].
+fn page_hours(name: &str, icon: Option<String>, summary: &HoursSummary) -> PageHours {
+    PageHours {
+        name: name.to_string(),
+        icon,
+        active: format!("{:.1}", summary.total_hours()),
+        idle: format!("{:.1}", summary.idle_hours()),
+        past_two: format!("{:.1}", summary.past_two_hours()),
+        windows: page_windows(&summary.windows),
     }
+}
 
-    date_lines.reverse();
-
-    let mut counter: usize = 0;
-
-    if date_lines.len() >= 7 {
-        while counter <= 6 {
-            dates_div.add_paragraph(date_lines[counter]);
-
-            counter += 1;
-        }
-    } else {
-        for line in date_lines {
-            dates_div.add_paragraph(line);
+/// This function adds the `hours` of every game together into the [`PageHours`] of all games.
+fn all_games_hours(hours: &[GameHours]) -> PageHours {
+    let total_seconds: u64 = hours.iter().map(|h| h.summary.total_seconds).sum();
+    let past_two_seconds: u64 = hours.iter().map(|h| h.summary.past_two_seconds).sum();
+    let idle_seconds: u64 = hours.iter().map(|h| h.summary.idle_seconds).sum();
+
+    // Every game has the same windows, so they are added together by position
+    let mut windows: Vec<(Window, u64)> = hours
+        .first()
+        .map(|h| h.summary.windows.clone())
+        .unwrap_or_default();
+
+    for game_hours in hours.iter().skip(1) {
+        for (total, (_, seconds)) in windows.iter_mut().zip(&game_hours.summary.windows) {
+            total.1 += seconds;
         }
     }
 
-    let hours_div_container = HtmlElement::new(HtmlTag::Div)
-        .with_attribute("id", "hours")
-        .with_attribute("class", "hours-div-container color flex-column")
-        .with_header(2, "Your Hours Played")
-        .with_html(hours_div);
-
-    let dates_div_container = HtmlElement::new(HtmlTag::Div)
-        .with_attribute("id", "dates")
-        .with_attribute("class", "dates-div-container color flex-column")
-        .with_header(2, "Your time played<br>in the last 7 sessions")
-        .with_html(dates_div);
-
-    page.add_container(
-        Container::new(ContainerType::Main)
-            .with_attributes(vec![("class", "main flex-column color oswald-font-500")])
-            .with_html(hours_div_container)
-            .with_html(dates_div_container),
-    );
-
-    page.add_container(
-        Container::new(ContainerType::Footer)
-            .with_attributes(vec![("class", "footer flex-row oswald-font-700")])
-            .with_paragraph("&copy; OneilNvM 2024 ")
-            .with_link_attr(
-                "https://github.com/OneilNvM/rl-hours-tracker",
-                "Rocket League Hours Tracker Github",
-                [("target", "_blank")],
-            ),
-    );
+    PageHours {
+        name: String::from("All Games"),
+        icon: None,
+        active: format!("{:.1}", total_seconds as f64 / 3600_f64),
+        idle: format!("{:.1}", idle_seconds as f64 / 3600_f64),
+        past_two: format!("{:.1}", past_two_seconds as f64 / 3600_f64),
+        windows: page_windows(&windows),
+    }
+}
 
-    Ok(page.to_html_string())
+/// Formats the hours of the `windows` which are shown after the past two weeks, such as "2.0" hours "this month".
+fn page_windows(windows: &[(Window, u64)]) -> Vec<PageWindow> {
+    windows
+        .iter()
+        .map(|(window, seconds)| PageWindow {
+            label: window.label().to_lowercase(),
+            hours: format!("{:.1}", *seconds as f64 / 3600_f64),
+        })
+        .collect()
 }
@@ -1,13 +1,14 @@
 //! This modules contains the functionality for creating the tray icon for the program
 //! and creating the thread for the event loop to run in.
+use crate::daemon::request_shutdown;
+use crate::error::TrackerResult;
+use crate::tracker::TrackerHandle;
 use colour::yellow_ln_bold;
 use image::{ImageFormat, ImageReader};
 use log::{error, info};
 use winit::event_loop::EventLoop;
-use std::error::Error;
 use std::io::{Cursor, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
 use tray_icon::menu::{IsMenuItem, MenuEvent, MenuItem};
 use tray_icon::{menu::Menu, Icon};
 use tray_icon::{TrayIcon, TrayIconBuilder, TrayIconEvent};
@@ -23,35 +24,33 @@ pub enum UserEvent {
 }
 
 struct Application {
-    currently_tracking: Option<Arc<Mutex<AtomicBool>>>,
-    stop_tracker: Option<Arc<Mutex<AtomicBool>>>,
+    tracker: TrackerHandle,
     tray_icon: Option<TrayIcon>,
 }
 
 impl Application {
-    fn new() -> Application {
+    fn new(tracker: TrackerHandle) -> Application {
         Application {
-            currently_tracking: None,
-            stop_tracker: None,
+            tracker,
             tray_icon: None,
         }
     }
 
-    pub fn new_tray_icon() -> TrayIcon {
+    /// Creates the tray icon with its menu.
+    ///
+    /// # Errors
+    /// Returns a [`TrackerError::Image`](crate::error::TrackerError::Image) or
+    /// [`TrackerError::Icon`](crate::error::TrackerError::Icon) if the image could not be loaded, or a
+    /// [`TrackerError::Tray`](crate::error::TrackerError::Tray) if the tray icon could not be created.
+    pub fn new_tray_icon() -> TrackerResult<TrayIcon> {
         info!("Creating system tray icon");
-        let image = load_image(IMAGE_BYTES).unwrap_or_else(|e| {
-            error!("error occurred when loading image: {e}");
-            panic!("could not load image for tray icon");
-        });
-        TrayIconBuilder::new()
+        let image = load_image(IMAGE_BYTES)?;
+
+        Ok(TrayIconBuilder::new()
             .with_menu(Box::new(Self::new_tray_menu()))
             .with_tooltip("RL Hours Tracker")
             .with_icon(image)
-            .build()
-            .unwrap_or_else(|e| {
-                error!("error occurred creating tray icon: {e}");
-                panic!("could not create tray icon");
-            })
+            .build()?)
     }
 
     fn new_tray_menu() -> Menu {
@@ -68,18 +67,15 @@ impl Application {
         tray_menu
     }
 
-    fn set_currently_tracking(&mut self, currently_tracking: &Arc<Mutex<AtomicBool>>) -> &mut Self {
-        info!("Setting currently_tracking in winit Application");
-        self.currently_tracking = Some(currently_tracking.clone());
-
-        self
-    }
-
-    fn set_stop_tracker(&mut self, stop_tracker: &Arc<Mutex<AtomicBool>>) -> &mut Self {
-        info!("Setting stop_tracking in winit Application");
-        self.stop_tracker = Some(stop_tracker.clone());
-
-        self
+    /// Stops the session in progress so it is recorded before the program quits, and returns `true`
+    /// if there was a session in progress.
+    fn shutdown_after_session(&self) -> bool {
+        if self.tracker.is_tracking() {
+            request_shutdown(None, &self.tracker);
+            true
+        } else {
+            false
+        }
     }
 }
 
@@ -101,8 +97,9 @@ impl ApplicationHandler<UserEvent> for Application {
     ) {
         if winit::event::StartCause::Init == cause {
             #[cfg(not(target_os = "linux"))]
-            {
-                self.tray_icon = Some(Self::new_tray_icon())
+            match Self::new_tray_icon() {
+                Ok(tray_icon) => self.tray_icon = Some(tray_icon),
+                Err(e) => error!("running without the tray icon: {e}"),
             }
         }
     }
@@ -117,59 +114,21 @@ impl ApplicationHandler<UserEvent> for Application {
             UserEvent::MenuEvent(menu) => {
                 if menu.id == "1001" {
                     info!("Exit menu item clicked");
+
+                    // The tracker quits the event loop once the session is recorded
+                    if self.shutdown_after_session() {
+                        return;
+                    }
+
                     _event_loop.exit();
                     print!("{}[2K\r", 27 as char);
                     std::io::stdout()
                         .flush()
                         .expect("could not flush the output stream");
                     yellow_ln_bold!("Goodbye!");
-                } else if menu.id == "1002"
-                    && self
-                        .currently_tracking
-                        .as_ref()
-                        .unwrap_or_else(|| {
-                            error!("currently_tracking is None");
-                            panic!("currently_tracking is None");
-                        })
-                        .try_lock()
-                        .unwrap_or_else(|e| {
-                            error!(
-                                "error when attempting to access lock for currently_tracking: {e}"
-                            );
-                            panic!("error when attempting to access lock for currently_tracking");
-                        })
-                        .load(Ordering::SeqCst)
-                {
+                } else if menu.id == "1002" && self.tracker.is_tracking() {
                     info!("Stop Tracker menu item clicked");
-                    self
-                        .stop_tracker
-                        .as_mut()
-                        .unwrap_or_else(|| {
-                            error!("currently_tracking is None");
-                            panic!("currently_tracking is None");
-                        })
-                        .try_lock()
-                        .unwrap_or_else(|e| {
-                            error!(
-                                "error when attempting to access lock for currently_tracking: {e}"
-                            );
-                            panic!("error when attempting to access lock for currently_tracking");
-                        }).store(true, Ordering::SeqCst);
-
-                    self
-                        .currently_tracking
-                        .as_mut()
-                        .unwrap_or_else(|| {
-                            error!("currently_tracking is None");
-                            panic!("currently_tracking is None");
-                        })
-                        .try_lock()
-                        .unwrap_or_else(|e| {
-                            error!(
-                                "error when attempting to access lock for currently_tracking: {e}"
-                            );
-                            panic!("error when attempting to access lock for currently_tracking");
-                        }).store(false, Ordering::SeqCst);
+                    self.tracker.stop();
                 }
             }
             UserEvent::QuitApp(quit) => {
@@ -183,11 +142,18 @@ impl ApplicationHandler<UserEvent> for Application {
     }
 }
 
+/// Creates the tray icon and runs the `event_loop` until the program quits.
+///
+/// # Errors
+/// Returns an error if the image of the tray icon could not be loaded, or the `event_loop` could not be run. The
+/// tracker keeps running in that case, so the program can carry on without the tray icon.
 pub fn initialize_tray_icon(
     event_loop: EventLoop<UserEvent>,
-    stop_tracker: Arc<Mutex<AtomicBool>>,
-    currently_tracking: Arc<Mutex<AtomicBool>>,
-) {
+    tracker: TrackerHandle,
+) -> TrackerResult<()> {
+    // The bundled image is checked before the event loop takes over the thread
+    load_image(IMAGE_BYTES)?;
+
     let proxy = event_loop.create_proxy();
     TrayIconEvent::set_event_handler(Some(move |event| {
         let _ = proxy.send_event(UserEvent::TrayIconEvent(event));
@@ -198,41 +164,27 @@ pub fn initialize_tray_icon(
         let _ = proxy.send_event(UserEvent::MenuEvent(event));
     }));
 
-    let proxy = event_loop.create_proxy();
-    ctrlc::set_handler(move || {
-        info!("Interrupting program");
-        print!("{}[2K\r", 27 as char);
-        std::io::stdout()
-            .flush()
-            .expect("could not flush the output stream");
-        yellow_ln_bold!("Goodbye!");
-        let _ = proxy.send_event(UserEvent::QuitApp(AtomicBool::new(true)));
-    })
-    .unwrap_or_else(|e| {
-        error!("could not create handler for ctrlc: {e}");
-    });
-
     #[cfg(target_os = "linux")]
     std::thread::spawn(|| {
         let _ = gtk::init();
 
-        let _tray_icon = Application::new_tray_icon();
-
-        info!("Starting main event loop with gtk");
-        gtk::main();
+        match Application::new_tray_icon() {
+            Ok(_tray_icon) => {
+                info!("Starting main event loop with gtk");
+                gtk::main();
+            }
+            Err(e) => error!("running without the tray icon: {e}"),
+        }
     });
 
-    let mut app = Application::new();
-    app.set_stop_tracker(&stop_tracker);
-    app.set_currently_tracking(&currently_tracking);
+    let mut app = Application::new(tracker);
 
-    if let Err(e) = event_loop.run_app(&mut app) {
-        error!("Error: {e:?}");
-        colour::e_red_ln!("Error: {e:?}");
-    }
+    event_loop.run_app(&mut app)?;
+
+    Ok(())
 }
 
-pub fn load_image(image_bytes: &[u8]) -> Result<Icon, Box<dyn Error>> {
+pub fn load_image(image_bytes: &[u8]) -> TrackerResult<Icon> {
     info!("Loading Rocket League Hours Tracker icon");
     let mut image_reader = ImageReader::new(Cursor::new(image_bytes));
     image_reader.set_format(ImageFormat::Ico);
@@ -0,0 +1,39 @@
+function loader() {
+    let filters = document.getElementById('history-filters');
+
+    if (filters === null) {
+        return;
+    }
+
+    filters.addEventListener("input", filterSessions);
+    filters.addEventListener("reset", () => setTimeout(filterSessions));
+}
+
+function filterSessions() {
+    let from = document.getElementById('filter-from').value;
+    let to = document.getElementById('filter-to').value;
+    let minSeconds = (Number(document.getElementById('filter-minutes').value) || 0) * 60;
+
+    let rows = document.querySelectorAll('#history-table tbody tr');
+    let shown = 0;
+
+    for (let row of rows) {
+        let date = row.dataset.date;
+        let duration = Number(row.dataset.duration);
+
+        // Dates in the YYYY-MM-DD format are compared as strings
+        let visible = (from === "" || date >= from)
+            && (to === "" || date <= to)
+            && duration >= minSeconds;
+
+        row.hidden = !visible;
+
+        if (visible) {
+            shown++;
+        }
+    }
+
+    document.getElementById('history-count').textContent = shown + " of " + rows.length + " sessions";
+}
+
+document.addEventListener("DOMContentLoaded", loader);